@@ -0,0 +1,415 @@
+use crate::error::{AppError, AppResult};
+use crate::path_policy::Access;
+
+/// 参数值的类型
+#[derive(Clone, Copy, Debug)]
+pub enum ValueKind {
+    /// 开关，可带可选的 `true` / `false`
+    Flag,
+    /// 任意文本（不能为空）
+    Text,
+    /// 输出位置，必须位于允许写入的目录中
+    Path,
+    /// 外部程序路径，不允许父目录引用
+    Executable,
+    /// 文件名，不允许路径分隔符
+    FileName,
+    /// 整数范围（含两端）
+    Int { min: i64, max: i64 },
+    /// 枚举值（不区分大小写）
+    Enum(&'static [&'static str]),
+    /// 速度，如 `15M`、`800K`
+    Speed,
+    /// 时长 `HH:mm:ss`
+    Duration,
+    /// 下载范围，如 `0-10`、`10-`、`-99`、`05:00-20:00`
+    Range,
+    /// 请求头 `Name: value`
+    Header,
+    /// 解密密钥 `KID:KEY` 或 0x 开头的十六进制
+    Key,
+    /// 混流选项 `format=mp4[:key=value...]`
+    Mux,
+}
+
+/// 单个参数的定义
+pub struct ArgSpec {
+    pub name: &'static str,
+    pub kind: ValueKind,
+}
+
+const fn arg(name: &'static str, kind: ValueKind) -> ArgSpec {
+    ArgSpec { name, kind }
+}
+
+const LOG_LEVELS: &[&str] = &["DEBUG", "INFO", "WARN", "ERROR", "OFF"];
+const DECRYPTION_ENGINES: &[&str] = &["FFMPEG", "MP4DECRYPT", "SHAKA_PACKAGER"];
+const HLS_METHODS: &[&str] = &[
+    "AES_128", "AES_128_ECB", "CENC", "CHACHA20", "NONE", "SAMPLE_AES", "SAMPLE_AES_CTR", "UNKNOWN",
+];
+
+/// N_m3u8DL-RE 允许的参数及其取值规则
+pub const N_M3U8DL_ARGS: &[ArgSpec] = &[
+    // 基础参数
+    arg("--save-dir", ValueKind::Path),
+    arg("--save-name", ValueKind::FileName),
+    arg("--thread-count", ValueKind::Int { min: 1, max: 256 }),
+    arg("--auto-select", ValueKind::Flag),
+    arg("--no-log", ValueKind::Flag),
+    arg("--max-speed", ValueKind::Speed),
+    arg("--live-record-limit", ValueKind::Duration),
+    arg("--live-real-time-merge", ValueKind::Flag),
+    arg("--live-wait-time", ValueKind::Int { min: 1, max: 3600 }),
+
+    // 内容选择参数
+    arg("--select-video", ValueKind::Text),
+    arg("--select-audio", ValueKind::Text),
+    arg("--select-subtitle", ValueKind::Text),
+    arg("--drop-video", ValueKind::Text),
+    arg("--drop-audio", ValueKind::Text),
+    arg("--drop-subtitle", ValueKind::Text),
+
+    // 解密相关参数
+    arg("--key", ValueKind::Key),
+    arg("--custom-hls-method", ValueKind::Enum(HLS_METHODS)),
+    arg("--decryption-key", ValueKind::Text),
+    arg("--decryption-engine", ValueKind::Enum(DECRYPTION_ENGINES)),
+    arg("--decryption-binary-path", ValueKind::Executable),
+    arg("--mp4-real-time-decryption", ValueKind::Flag),
+
+    // 混流相关参数
+    arg("--mux-after-done", ValueKind::Mux),
+    arg("--binary-merge", ValueKind::Flag),
+
+    // HTTP 相关参数
+    arg("-H", ValueKind::Header),
+    arg("--http-header", ValueKind::Header),
+    arg("--user-agent", ValueKind::Text),
+    arg("--referer", ValueKind::Text),
+
+    // 高级参数
+    arg("--custom-range", ValueKind::Range),
+    arg("--date-range", ValueKind::Range),
+    arg("--append-user-agent", ValueKind::Flag),
+    arg("--log-level", ValueKind::Enum(LOG_LEVELS)),
+    arg("--tmp-dir", ValueKind::Path),
+    arg("--del-after-done", ValueKind::Flag),
+
+    // 重试和超时参数
+    arg("--retry-count", ValueKind::Int { min: 0, max: 100 }),
+    arg("--download-retry-delay", ValueKind::Int { min: 0, max: 600 }),
+    arg("--auto-subtitle-fix", ValueKind::Flag),
+
+    // 显示和输出控制参数
+    arg("--no-ansi-color", ValueKind::Flag),
+    arg("--no-date-info", ValueKind::Flag),
+    arg("--no-log-color", ValueKind::Flag),
+    arg("--use-shaka-packager", ValueKind::Flag),
+    arg("--check-segments-count", ValueKind::Flag),
+];
+
+fn find_spec(name: &str) -> Option<&'static ArgSpec> {
+    N_M3U8DL_ARGS.iter().find(|spec| spec.name == name)
+}
+
+fn is_bool(value: &str) -> bool {
+    value.eq_ignore_ascii_case("true") || value.eq_ignore_ascii_case("false")
+}
+
+/// 按参数定义校验 N_m3u8DL-RE 参数：第一个参数为下载地址，其余为参数及其取值
+pub fn validate(args: &[String]) -> AppResult<()> {
+    let mut i = 0;
+    // 跳过 URL 参数（第一个参数通常是 URL）
+    if let Some(first) = args.first() {
+        if first.starts_with("http://") || first.starts_with("https://") {
+            i = 1;
+        }
+    }
+
+    while i < args.len() {
+        let token = &args[i];
+        if !token.starts_with('-') {
+            return Err(AppError::invalid_argument(format!("多余的参数值: {}", token)));
+        }
+        // 支持 `--name=value` 形式
+        let (name, inline) = match token.split_once('=') {
+            Some((name, value)) if token.starts_with("--") => (name, Some(value)),
+            _ => (token.as_str(), None),
+        };
+        let spec = find_spec(name)
+            .ok_or_else(|| AppError::invalid_argument(format!("不允许的参数: {}", name)))?;
+        i += 1;
+
+        if let ValueKind::Flag = spec.kind {
+            let value = match inline {
+                Some(value) => Some(value),
+                // 开关后的 true/false 视为其取值
+                None => args.get(i).filter(|v| is_bool(v)).map(|v| {
+                    i += 1;
+                    v.as_str()
+                }),
+            };
+            if let Some(value) = value.filter(|v| !is_bool(v)) {
+                return Err(AppError::invalid_argument(
+                    format!("参数 {} 的值只能为 true 或 false: {}", name, value)));
+            }
+            continue;
+        }
+
+        let value = match inline {
+            Some(value) => value,
+            None => {
+                let value = args.get(i)
+                    .ok_or_else(|| AppError::invalid_argument(format!("参数 {} 缺少取值", name)))?;
+                i += 1;
+                value.as_str()
+            }
+        };
+        // 以 `-` 开头的取值会被下载器当作参数解析（范围 `-99` 除外）
+        if value.starts_with('-') && !matches!(spec.kind, ValueKind::Range) {
+            return Err(AppError::invalid_argument(format!("参数 {} 缺少取值（{} 不能作为取值）", name, value)));
+        }
+        validate_value(spec, value)?;
+    }
+    Ok(())
+}
+
+/// 校验单个取值
+fn validate_value(spec: &ArgSpec, value: &str) -> AppResult<()> {
+    let name = spec.name;
+    let invalid = |expected: &str| {
+        AppError::invalid_argument(format!("参数 {} 的值不合法: {}（应为{}）", name, value, expected))
+    };
+    if value.trim().is_empty() {
+        return Err(AppError::invalid_argument(format!("参数 {} 的值不能为空", name)));
+    }
+    if value.contains(['\r', '\n', '\0']) {
+        return Err(invalid("单行文本"));
+    }
+
+    match spec.kind {
+        ValueKind::Flag | ValueKind::Text => Ok(()),
+        ValueKind::Path => crate::path_policy::check(value, Access::Write).map(|_| ()),
+        ValueKind::Executable => crate::validate_path_safety(value),
+        ValueKind::FileName => {
+            if value.contains(['/', '\\']) || value.contains("..") {
+                return Err(invalid("不含路径分隔符的文件名"));
+            }
+            Ok(())
+        }
+        ValueKind::Int { min, max } => match value.parse::<i64>() {
+            Ok(n) if (min..=max).contains(&n) => Ok(()),
+            _ => Err(invalid(&format!(" {} 到 {} 之间的整数", min, max))),
+        },
+        ValueKind::Enum(values) => {
+            if values.iter().any(|v| v.eq_ignore_ascii_case(value)) {
+                return Ok(());
+            }
+            Err(invalid(&format!(" {} 之一", values.join(" / "))))
+        }
+        ValueKind::Speed => {
+            let number = value.strip_suffix(['M', 'K', 'm', 'k']).unwrap_or(value);
+            match number.parse::<f64>() {
+                Ok(n) if n > 0.0 => Ok(()),
+                _ => Err(invalid("正数加可选单位 M/K，如 15M")),
+            }
+        }
+        ValueKind::Duration => {
+            if is_clock(value) {
+                return Ok(());
+            }
+            Err(invalid(" HH:mm:ss 格式的时长"))
+        }
+        ValueKind::Range => {
+            let valid = value.split_once('-').is_some_and(|(start, end)| {
+                !(start.is_empty() && end.is_empty())
+                    && (start.is_empty() || is_range_bound(start))
+                    && (end.is_empty() || is_range_bound(end))
+            });
+            if valid {
+                return Ok(());
+            }
+            Err(invalid("分片序号或时间范围，如 0-10、05:00-20:00"))
+        }
+        ValueKind::Header => match value.split_once(':') {
+            Some((header, _)) if !header.trim().is_empty() && !header.contains(' ') => Ok(()),
+            _ => Err(invalid(" Name: value 格式的请求头")),
+        },
+        ValueKind::Key => {
+            // 密钥格式: KID:KEY 或 0x开头的十六进制
+            let hex = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_hexdigit());
+            let valid = match value.split_once(':') {
+                // KID 可能带有 UUID 形式的连字符
+                Some((kid, key)) => hex(&kid.replace('-', "")) && hex(key),
+                None => value.strip_prefix("0x").is_some_and(hex),
+            };
+            if valid {
+                return Ok(());
+            }
+            Err(AppError::invalid_argument("密钥格式不正确，应为 KID:KEY 或 0x 开头的十六进制"))
+        }
+        ValueKind::Mux => {
+            let valid = value.split(':').all(|part| {
+                part.split_once('=').is_some_and(|(k, v)| !k.is_empty() && !v.is_empty())
+            });
+            if valid && value.starts_with("format=") {
+                return Ok(());
+            }
+            Err(invalid(" format=mp4[:muxer=ffmpeg] 格式"))
+        }
+    }
+}
+
+/// `HH:mm:ss`
+fn is_clock(value: &str) -> bool {
+    let parts: Vec<&str> = value.split(':').collect();
+    parts.len() == 3
+        && parts.iter().all(|p| p.len() == 2 && p.chars().all(|c| c.is_ascii_digit()))
+        && parts[1] < "60" && parts[2] < "60"
+}
+
+/// 范围端点：分片序号，或 `mm:ss` / `HH:mm:ss` 时间（秒可带小数）
+fn is_range_bound(value: &str) -> bool {
+    let parts: Vec<&str> = value.split(':').collect();
+    match parts.len() {
+        1 => value.chars().all(|c| c.is_ascii_digit()),
+        2 | 3 => parts.iter().all(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit() || c == '.')),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(args: &[&str]) -> AppResult<()> {
+        let args: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+        validate(&args)
+    }
+
+    fn accepts(flag: &str, value: &str) -> bool {
+        check(&["https://example.com/a.m3u8", flag, value]).is_ok()
+    }
+
+    #[test]
+    fn accepts_url_followed_by_known_args() {
+        assert!(check(&["https://example.com/a.m3u8", "--save-name", "video", "--thread-count", "8"]).is_ok());
+        assert!(check(&["--thread-count", "8"]).is_ok());
+        assert!(check(&[]).is_ok());
+    }
+
+    #[test]
+    fn rejects_unknown_args_and_stray_values() {
+        assert!(check(&["https://example.com/a.m3u8", "--exec", "rm"]).is_err());
+        assert!(check(&["https://example.com/a.m3u8", "video"]).is_err());
+        // 非 http(s) 的首个参数不视为下载地址
+        assert!(check(&["file:///etc/passwd", "--auto-select"]).is_err());
+    }
+
+    #[test]
+    fn flags_take_optional_bool() {
+        assert!(check(&["--auto-select"]).is_ok());
+        assert!(check(&["--auto-select", "true", "--no-log", "FALSE"]).is_ok());
+        assert!(check(&["--auto-select=false"]).is_ok());
+        assert!(check(&["--auto-select=yes"]).is_err());
+        // 开关后的非布尔值是多余的参数值
+        assert!(check(&["--auto-select", "yes"]).is_err());
+    }
+
+    #[test]
+    fn values_are_required_and_cannot_look_like_flags() {
+        assert!(check(&["--save-name"]).is_err());
+        assert!(check(&["--save-name", "--auto-select"]).is_err());
+        assert!(check(&["--save-name", "  "]).is_err());
+        assert!(check(&["--save-name", "a\nb"]).is_err());
+        assert!(check(&["--save-name=video"]).is_ok());
+        assert!(check(&["--save-name="]).is_err());
+    }
+
+    #[test]
+    fn file_name_rejects_paths() {
+        assert!(accepts("--save-name", "my video"));
+        assert!(!accepts("--save-name", "../video"));
+        assert!(!accepts("--save-name", "dir/video"));
+        assert!(!accepts("--save-name", "dir\\video"));
+    }
+
+    #[test]
+    fn int_respects_range() {
+        assert!(accepts("--thread-count", "1"));
+        assert!(accepts("--thread-count", "256"));
+        assert!(!accepts("--thread-count", "0"));
+        assert!(!accepts("--thread-count", "257"));
+        assert!(!accepts("--thread-count", "eight"));
+        assert!(accepts("--retry-count", "0"));
+        assert!(!accepts("--live-wait-time", "3601"));
+    }
+
+    #[test]
+    fn enum_is_case_insensitive() {
+        assert!(accepts("--log-level", "debug"));
+        assert!(accepts("--log-level", "ERROR"));
+        assert!(!accepts("--log-level", "TRACE"));
+        assert!(accepts("--decryption-engine", "shaka_packager"));
+        assert!(!accepts("--custom-hls-method", "AES_256"));
+    }
+
+    #[test]
+    fn speed_accepts_units() {
+        assert!(accepts("--max-speed", "15M"));
+        assert!(accepts("--max-speed", "800k"));
+        assert!(accepts("--max-speed", "1.5M"));
+        assert!(!accepts("--max-speed", "0M"));
+        assert!(!accepts("--max-speed", "15G"));
+        assert!(!accepts("--max-speed", "M"));
+    }
+
+    #[test]
+    fn duration_is_clock_format() {
+        assert!(accepts("--live-record-limit", "01:30:00"));
+        assert!(!accepts("--live-record-limit", "1:30:00"));
+        assert!(!accepts("--live-record-limit", "01:60:00"));
+        assert!(!accepts("--live-record-limit", "01:30"));
+    }
+
+    #[test]
+    fn range_allows_open_ends_and_times() {
+        assert!(accepts("--custom-range", "0-10"));
+        assert!(accepts("--custom-range", "10-"));
+        assert!(accepts("--custom-range", "-99"));
+        assert!(accepts("--custom-range", "05:00-20:00"));
+        assert!(accepts("--custom-range", "00:05:00.5-00:20:00"));
+        assert!(!accepts("--custom-range", "-"));
+        assert!(!accepts("--custom-range", "10"));
+        assert!(!accepts("--custom-range", "a-b"));
+    }
+
+    #[test]
+    fn header_needs_name_and_colon() {
+        assert!(accepts("-H", "Cookie: a=b"));
+        assert!(accepts("--http-header", "X-Token:abc"));
+        assert!(!accepts("-H", "Cookie a=b"));
+        assert!(!accepts("-H", ": value"));
+        assert!(!accepts("-H", "Bad Name: value"));
+    }
+
+    #[test]
+    fn key_is_kid_key_or_hex() {
+        assert!(accepts("--key", "0123456789abcdef0123456789abcdef:00112233445566778899aabbccddeeff"));
+        assert!(accepts("--key", "01234567-89ab-cdef-0123-456789abcdef:00112233"));
+        assert!(accepts("--key", "0x00112233445566778899aabbccddeeff"));
+        assert!(!accepts("--key", "00112233445566778899aabbccddeeff"));
+        assert!(!accepts("--key", "kid:key"));
+        assert!(!accepts("--key", "0123:"));
+        assert!(!accepts("--key", "0x"));
+    }
+
+    #[test]
+    fn mux_requires_format_first() {
+        assert!(accepts("--mux-after-done", "format=mp4"));
+        assert!(accepts("--mux-after-done", "format=mkv:muxer=mkvmerge"));
+        assert!(!accepts("--mux-after-done", "muxer=ffmpeg:format=mp4"));
+        assert!(!accepts("--mux-after-done", "format=mp4:muxer"));
+        assert!(!accepts("--mux-after-done", "mp4"));
+    }
+}
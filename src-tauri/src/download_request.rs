@@ -0,0 +1,228 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use tauri::command;
+use crate::error::{AppError, AppResult};
+use crate::job_store::TEMP_DIR_PREFIX;
+
+/// 当前的下载请求格式版本；格式变化时递增，并在 `to_args` 中兼容旧版本
+pub const REQUEST_VERSION: u32 = 1;
+
+fn default_version() -> u32 {
+    REQUEST_VERSION
+}
+
+/// 流选择（对应 `--select-*` / `--drop-*`），取值为 N_m3u8DL-RE 的选择表达式，如 `best`、`lang=zh-Hans`
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamSelectors {
+    pub video: Option<String>,
+    pub audio: Option<String>,
+    pub subtitle: Option<String>,
+}
+
+/// 直播录制选项
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct LiveOptions {
+    /// 录制时长上限，格式 `HH:mm:ss`
+    pub record_limit: Option<String>,
+    /// 录制时实时合并
+    #[serde(default)]
+    pub real_time_merge: bool,
+    /// 刷新列表的间隔（秒）
+    pub wait_time: Option<u32>,
+}
+
+/// 混流输出格式
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MuxFormat {
+    #[default]
+    Mp4,
+    Mkv,
+}
+
+/// 混流工具
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Muxer {
+    Ffmpeg,
+    Mkvmerge,
+}
+
+/// 下载完成后的混流选项（对应 `--mux-after-done`）
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MuxOptions {
+    #[serde(default)]
+    pub format: MuxFormat,
+    pub muxer: Option<Muxer>,
+    /// 不混入字幕
+    #[serde(default)]
+    pub skip_sub: bool,
+}
+
+/// 下载请求，由后端转换为 N_m3u8DL-RE 参数
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadRequest {
+    /// 请求格式版本，未指定时视为当前版本
+    #[serde(default = "default_version")]
+    pub version: u32,
+    pub url: String,
+    pub save_dir: String,
+    pub save_name: String,
+    /// 临时目录，未指定时使用 `<save_dir>/.temp_<时间戳>`
+    pub tmp_dir: Option<String>,
+    pub thread_count: Option<u32>,
+    #[serde(default)]
+    pub select: StreamSelectors,
+    #[serde(default)]
+    pub drop: StreamSelectors,
+    /// 请求头（名称 -> 值）
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// 解密密钥，格式 `KID:KEY`
+    #[serde(default)]
+    pub keys: Vec<String>,
+    /// 下载范围，如 `0-10` 或 `05:00-20:00`
+    pub custom_range: Option<String>,
+    /// 限速，如 `15M`
+    pub max_speed: Option<String>,
+    pub retry_count: Option<u32>,
+    /// 分片二进制合并
+    #[serde(default)]
+    pub binary_merge: bool,
+    pub live: Option<LiveOptions>,
+    pub mux: Option<MuxOptions>,
+}
+
+impl DownloadRequest {
+    /// 校验请求并转换为 N_m3u8DL-RE 参数
+    pub fn to_args(&self) -> AppResult<Vec<String>> {
+        if self.version == 0 || self.version > REQUEST_VERSION {
+            return Err(AppError::invalid_argument(format!("不支持的下载请求版本: {}", self.version)));
+        }
+        if !(self.url.starts_with("http://") || self.url.starts_with("https://")) {
+            return Err(AppError::invalid_argument("下载地址必须以 http:// 或 https:// 开头"));
+        }
+        if self.save_name.trim().is_empty() || self.save_name.contains(['/', '\\']) || self.save_name.contains("..") {
+            return Err(AppError::invalid_argument("保存名称不合法"));
+        }
+
+        let tmp_dir = match &self.tmp_dir {
+            Some(dir) => dir.clone(),
+            None => Path::new(&self.save_dir)
+                .join(format!("{}{}", TEMP_DIR_PREFIX, chrono::Utc::now().timestamp_millis()))
+                .to_string_lossy()
+                .to_string(),
+        };
+
+        let mut args = vec![
+            self.url.clone(),
+            "--save-dir".to_string(), self.save_dir.clone(),
+            "--save-name".to_string(), self.save_name.clone(),
+            "--tmp-dir".to_string(), tmp_dir,
+        ];
+
+        if let Some(count) = self.thread_count {
+            args.extend(["--thread-count".to_string(), count.to_string()]);
+        }
+
+        push_selectors(&mut args, "select", &self.select);
+        push_selectors(&mut args, "drop", &self.drop);
+
+        for (name, value) in &self.headers {
+            if name.is_empty() || name.contains([':', '\r', '\n']) || value.contains(['\r', '\n']) {
+                return Err(AppError::invalid_argument(format!("请求头不合法: {}", name)));
+            }
+            args.extend(["-H".to_string(), format!("{}: {}", name, value)]);
+        }
+
+        for key in &self.keys {
+            args.extend(["--key".to_string(), key.clone()]);
+        }
+
+        if let Some(range) = &self.custom_range {
+            args.extend(["--custom-range".to_string(), range.clone()]);
+        }
+        if let Some(speed) = &self.max_speed {
+            args.extend(["--max-speed".to_string(), speed.clone()]);
+        }
+        if let Some(count) = self.retry_count {
+            args.extend(["--retry-count".to_string(), count.to_string()]);
+        }
+        if self.binary_merge {
+            args.push("--binary-merge".to_string());
+        }
+
+        if let Some(live) = &self.live {
+            if let Some(limit) = &live.record_limit {
+                args.extend(["--live-record-limit".to_string(), limit.clone()]);
+            }
+            if live.real_time_merge {
+                args.push("--live-real-time-merge".to_string());
+            }
+            if let Some(wait) = live.wait_time {
+                args.extend(["--live-wait-time".to_string(), wait.to_string()]);
+            }
+        }
+
+        if let Some(mux) = &self.mux {
+            args.extend(["--mux-after-done".to_string(), mux_value(mux)]);
+        }
+
+        // 输出解析依赖无颜色、INFO 级别的控制台输出；日志由本程序统一记录
+        args.extend([
+            "--no-ansi-color".to_string(),
+            "--no-log".to_string(),
+            "--log-level".to_string(), "INFO".to_string(),
+        ]);
+
+        // 取值（保存目录、线程数、密钥、范围等）按参数定义校验
+        crate::validate_n_m3u8dl_args(&args)?;
+        Ok(args)
+    }
+}
+
+fn push_selectors(args: &mut Vec<String>, action: &str, selectors: &StreamSelectors) {
+    let streams = [
+        ("video", &selectors.video),
+        ("audio", &selectors.audio),
+        ("subtitle", &selectors.subtitle),
+    ];
+    for (stream, value) in streams {
+        if let Some(value) = value {
+            args.extend([format!("--{}-{}", action, stream), value.clone()]);
+        }
+    }
+}
+
+/// `--mux-after-done` 的值，如 `format=mkv:muxer=mkvmerge:skip_sub=true`
+fn mux_value(mux: &MuxOptions) -> String {
+    let format = match mux.format {
+        MuxFormat::Mp4 => "mp4",
+        MuxFormat::Mkv => "mkv",
+    };
+    let mut value = format!("format={}", format);
+    if let Some(muxer) = mux.muxer {
+        value.push_str(match muxer {
+            Muxer::Ffmpeg => ":muxer=ffmpeg",
+            Muxer::Mkvmerge => ":muxer=mkvmerge",
+        });
+    }
+    if mux.skip_sub {
+        value.push_str(":skip_sub=true");
+    }
+    value
+}
+
+// ==================== 下载请求命令 ====================
+
+/// 按下载请求将任务加入下载队列（受 maxConcurrentDownloads 限制），返回任务 ID
+#[command]
+pub async fn start_download(app: tauri::AppHandle, request: DownloadRequest) -> AppResult<String> {
+    let args = request.to_args()?;
+    Ok(crate::queue::enqueue(&app, args, Some(request.save_dir), 0))
+}
@@ -0,0 +1,366 @@
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use crate::redact;
+use crate::runner::{ProcessOutcome, RunError, SpawnError};
+
+/// 命令返回的结果
+pub type AppResult<T> = Result<T, AppError>;
+
+/// 稳定的错误码，前端按错误码区分处理
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    /// 外部工具不存在
+    ToolMissing,
+    /// 磁盘空间不足
+    DiskFull,
+    /// 没有访问权限（本地文件）
+    PermissionDenied,
+    /// 服务器拒绝访问（HTTP 401/403）
+    Forbidden,
+    /// 资源不存在（HTTP 404 或本地文件不存在）
+    NotFound,
+    /// 网络错误（连接失败、DNS、服务端繁忙等）
+    Network,
+    /// 进程超时
+    Timeout,
+    /// 参数不合法
+    InvalidArgument,
+    /// 路径不安全
+    UnsafePath,
+    /// 路径不在允许访问的目录中
+    PathNotAllowed,
+    /// 用户取消
+    Cancelled,
+    /// 解密失败（密钥错误或缺失）
+    DecryptionFailed,
+    /// 任务不存在或已结束
+    JobNotFound,
+    /// 任务当前状态不允许该操作
+    InvalidState,
+    /// 外部工具执行失败（未识别的原因）
+    ProcessFailed,
+    /// 文件读写失败
+    Io,
+    /// 其他内部错误
+    Internal,
+}
+
+impl ErrorCode {
+    /// 是否为暂时性失败（重试可能成功）
+    pub fn is_transient(&self) -> bool {
+        matches!(self, ErrorCode::Network | ErrorCode::Timeout)
+    }
+}
+
+/// 工具输出中已知失败行（小写匹配）对应的错误码。
+/// 按顺序取第一个匹配项：具体的条目（如 `504 gateway timeout`）必须排在通用条目（如 `timeout`）之前；
+/// 以字母或数字开头、结尾的条目只按完整的词匹配
+const FAILURE_PATTERNS: &[(&str, ErrorCode)] = &[
+    ("no space left on device", ErrorCode::DiskFull),
+    ("disk full", ErrorCode::DiskFull),
+    ("not enough space", ErrorCode::DiskFull),
+    ("403 forbidden", ErrorCode::Forbidden),
+    ("(forbidden)", ErrorCode::Forbidden),
+    ("(403)", ErrorCode::Forbidden),
+    ("401 unauthorized", ErrorCode::Forbidden),
+    ("(unauthorized)", ErrorCode::Forbidden),
+    ("404 not found", ErrorCode::NotFound),
+    ("(not found)", ErrorCode::NotFound),
+    ("(404)", ErrorCode::NotFound),
+    ("502 bad gateway", ErrorCode::Network),
+    ("503 service unavailable", ErrorCode::Network),
+    ("504 gateway timeout", ErrorCode::Network),
+    ("429 too many requests", ErrorCode::Network),
+    ("(bad gateway)", ErrorCode::Network),
+    ("(service unavailable)", ErrorCode::Network),
+    ("(gateway timeout)", ErrorCode::Network),
+    ("(too many requests)", ErrorCode::Network),
+    ("(502)", ErrorCode::Network),
+    ("(503)", ErrorCode::Network),
+    ("(504)", ErrorCode::Network),
+    ("(429)", ErrorCode::Network),
+    ("permission denied", ErrorCode::PermissionDenied),
+    ("access to the path", ErrorCode::PermissionDenied),
+    ("no key found", ErrorCode::DecryptionFailed),
+    ("no key was found", ErrorCode::DecryptionFailed),
+    ("decrypt failed", ErrorCode::DecryptionFailed),
+    ("decryption failed", ErrorCode::DecryptionFailed),
+    ("invalid key", ErrorCode::DecryptionFailed),
+    ("connection reset", ErrorCode::Network),
+    ("connection refused", ErrorCode::Network),
+    ("connection closed", ErrorCode::Network),
+    ("connection aborted", ErrorCode::Network),
+    ("network is unreachable", ErrorCode::Network),
+    ("no such host", ErrorCode::Network),
+    ("name or service not known", ErrorCode::Network),
+    ("temporary failure in name resolution", ErrorCode::Network),
+    ("httprequestexception", ErrorCode::Network),
+    ("ssl connection", ErrorCode::Network),
+    ("the response ended prematurely", ErrorCode::Network),
+    ("an error occurred while sending the request", ErrorCode::Network),
+    ("timed out", ErrorCode::Timeout),
+    ("timeout", ErrorCode::Timeout),
+];
+
+/// `pattern` 是否出现在 `text` 中；以字母或数字开头（结尾）的条目前（后）不能紧接字母或数字
+fn contains_pattern(text: &str, pattern: &str) -> bool {
+    let is_word = |c: Option<char>| c.is_some_and(|c| c.is_ascii_alphanumeric());
+    let anchor_start = pattern.starts_with(|c: char| c.is_ascii_alphanumeric());
+    let anchor_end = pattern.ends_with(|c: char| c.is_ascii_alphanumeric());
+    text.match_indices(pattern).any(|(start, _)| {
+        let before = text[..start].chars().next_back();
+        let after = text[start + pattern.len()..].chars().next();
+        (!anchor_start || !is_word(before)) && (!anchor_end || !is_word(after))
+    })
+}
+
+/// 识别工具输出中的已知失败行
+pub fn classify_line(line: &str) -> Option<ErrorCode> {
+    let lower = line.to_lowercase();
+    FAILURE_PATTERNS.iter()
+        .find(|(pattern, _)| contains_pattern(&lower, pattern))
+        .map(|(_, code)| *code)
+}
+
+/// 按进程尾部输出识别失败原因（越靠后的行越接近失败原因），返回错误码和对应的行
+pub fn classify_outcome(outcome: &ProcessOutcome) -> Option<(ErrorCode, String)> {
+    outcome.stdout_tail.iter()
+        .chain(outcome.stderr_tail.iter())
+        .rev()
+        .find_map(|line| classify_line(line).map(|code| (code, line.trim().to_string())))
+}
+
+/// 返回给前端的错误
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AppError {
+    pub code: ErrorCode,
+    /// 面向用户的说明（返回前端时隐藏其中的密钥、Cookie 等敏感内容）
+    #[serde(serialize_with = "redact::serialize")]
+    pub message: String,
+    /// 工具输出等排查信息
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "redact::serialize_opt")]
+    pub details: Option<String>,
+}
+
+impl AppError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        AppError { code, message: message.into(), details: None }
+    }
+
+    pub fn with_details(mut self, details: impl Into<String>) -> Self {
+        self.details = Some(details.into());
+        self
+    }
+
+    pub fn invalid_argument(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::InvalidArgument, message)
+    }
+
+    pub fn unsafe_path(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::UnsafePath, message)
+    }
+
+    pub fn job_not_found(job_id: &str) -> Self {
+        Self::new(ErrorCode::JobNotFound, format!("任务不存在或已结束: {}", job_id))
+    }
+
+    pub fn invalid_state(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::InvalidState, message)
+    }
+
+    pub fn cancelled(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::Cancelled, message)
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::Internal, message)
+    }
+
+    /// 文件读写错误，按错误类型区分磁盘已满、权限不足和文件不存在
+    pub fn io(context: &str, e: &std::io::Error) -> Self {
+        let code = if is_disk_full(e) {
+            ErrorCode::DiskFull
+        } else {
+            match e.kind() {
+                std::io::ErrorKind::PermissionDenied => ErrorCode::PermissionDenied,
+                std::io::ErrorKind::NotFound => ErrorCode::NotFound,
+                _ => ErrorCode::Io,
+            }
+        };
+        Self::new(code, format!("{}: {}", context, e))
+    }
+
+    /// 外部工具以失败状态退出，按输出识别原因，完整尾部输出放在详情中
+    pub fn process_failed(message: impl Into<String>, outcome: &ProcessOutcome) -> Self {
+        let message = message.into();
+        let (code, message) = match classify_outcome(outcome) {
+            Some((code, line)) => (code, format!("{}: {}", message, line)),
+            None => (ErrorCode::ProcessFailed, message),
+        };
+        Self::new(code, message).with_details(outcome.failure_message())
+    }
+}
+
+/// 磁盘已满（ENOSPC / ERROR_DISK_FULL / ERROR_HANDLE_DISK_FULL）
+fn is_disk_full(e: &std::io::Error) -> bool {
+    match e.raw_os_error() {
+        #[cfg(unix)]
+        Some(28) => true,
+        #[cfg(windows)]
+        Some(39) | Some(112) => true,
+        _ => false,
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for AppError {}
+
+/// 尚未细分错误码的内部错误信息
+impl From<String> for AppError {
+    fn from(message: String) -> Self {
+        AppError::internal(message)
+    }
+}
+
+impl From<&str> for AppError {
+    fn from(message: &str) -> Self {
+        AppError::internal(message)
+    }
+}
+
+/// 只有可执行文件不存在才视为工具缺失，权限不足、格式错误、句柄耗尽等归为文件读写错误
+impl From<SpawnError> for AppError {
+    fn from(e: SpawnError) -> Self {
+        let code = match e.kind {
+            std::io::ErrorKind::NotFound => ErrorCode::ToolMissing,
+            _ => ErrorCode::Io,
+        };
+        AppError::new(code, e.message)
+    }
+}
+
+impl From<RunError> for AppError {
+    fn from(e: RunError) -> Self {
+        match e {
+            RunError::Spawn(e) => e.into(),
+            RunError::Wait(message) => AppError::internal(message),
+            RunError::Timeout(_) => AppError::new(ErrorCode::Timeout, e.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_representative_tool_output() {
+        let cases = [
+            ("System.IO.IOException: No space left on device : '/data/a.ts'", ErrorCode::DiskFull),
+            ("[ERROR] Response status code does not indicate success: 403 (Forbidden).", ErrorCode::Forbidden),
+            ("Response status code does not indicate success: 401 (Unauthorized).", ErrorCode::Forbidden),
+            ("Response status code does not indicate success: 404 (Not Found).", ErrorCode::NotFound),
+            ("Response status code does not indicate success: 504 (Gateway Timeout).", ErrorCode::Network),
+            ("HTTP/1.1 504 Gateway Timeout", ErrorCode::Network),
+            ("Response status code does not indicate success: 429 (Too Many Requests).", ErrorCode::Network),
+            ("System.UnauthorizedAccessException: Access to the path '/x' is denied.", ErrorCode::PermissionDenied),
+            ("[ERROR] No key found for this stream", ErrorCode::DecryptionFailed),
+            ("mp4decrypt: decryption failed", ErrorCode::DecryptionFailed),
+            ("System.Net.Http.HttpRequestException: Connection reset by peer", ErrorCode::Network),
+            ("System.Net.Http.HttpRequestException: Name or service not known", ErrorCode::Network),
+            ("The operation has timed out.", ErrorCode::Timeout),
+            ("The request was canceled due to the configured HttpClient.Timeout of 100 seconds elapsing.", ErrorCode::Timeout),
+        ];
+        for (line, code) in cases {
+            assert_eq!(classify_line(line), Some(code), "{}", line);
+        }
+    }
+
+    #[test]
+    fn ignores_lines_that_only_contain_pattern_fragments() {
+        let lines = [
+            "Press any key to continue",
+            "There is no keyboard attached",
+            "monkey=1 donkey=2",
+            "Setting timeouts to 30s",
+            "Vid 1920x1080 | 4300 Kbps ━━━━━━━━━━ 60/119 50.42%",
+            "12:00:00.000 INFO : Decrypting segments...",
+            "Segment 4040 downloaded",
+        ];
+        for line in lines {
+            assert_eq!(classify_line(line), None, "{}", line);
+        }
+    }
+
+    #[test]
+    fn matches_patterns_on_word_boundaries() {
+        assert!(contains_pattern("operation timeout.", "timeout"));
+        assert!(contains_pattern("timeout", "timeout"));
+        assert!(!contains_pattern("timeouts", "timeout"));
+        assert!(!contains_pattern("httptimeout", "timeout"));
+        assert!(contains_pattern("status: 403 (forbidden)", "(forbidden)"));
+        assert!(contains_pattern("error(403)", "(403)"));
+    }
+
+    #[cfg(unix)]
+    fn outcome(code: i32, stdout: &[&str], stderr: &[&str]) -> ProcessOutcome {
+        use std::os::unix::process::ExitStatusExt;
+        ProcessOutcome {
+            status: std::process::ExitStatus::from_raw(code << 8),
+            exit_code: Some(code),
+            signal: None,
+            duration: std::time::Duration::from_secs(1),
+            stdout_tail: stdout.iter().map(|s| s.to_string()).collect(),
+            stderr_tail: stderr.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn classifies_outcome_by_last_matching_line() {
+        let failed = outcome(1, &["403 (Forbidden)"], &["Connection refused", "Exiting"]);
+        assert_eq!(classify_outcome(&failed), Some((ErrorCode::Network, "Connection refused".to_string())));
+
+        let error = AppError::process_failed("下载失败", &failed);
+        assert_eq!(error.code, ErrorCode::Network);
+        assert_eq!(error.message, "下载失败: Connection refused");
+        assert!(error.details.is_some());
+
+        let unknown = AppError::process_failed("下载失败", &outcome(2, &["done"], &[]));
+        assert_eq!(unknown.code, ErrorCode::ProcessFailed);
+        assert_eq!(unknown.message, "下载失败");
+    }
+
+    #[test]
+    fn transient_codes() {
+        assert!(ErrorCode::Network.is_transient());
+        assert!(ErrorCode::Timeout.is_transient());
+        assert!(!ErrorCode::Forbidden.is_transient());
+        assert!(!ErrorCode::DiskFull.is_transient());
+    }
+
+    #[test]
+    fn io_errors_map_to_codes() {
+        let not_found = std::io::Error::from(std::io::ErrorKind::NotFound);
+        assert_eq!(AppError::io("读取失败", &not_found).code, ErrorCode::NotFound);
+        let denied = std::io::Error::from(std::io::ErrorKind::PermissionDenied);
+        assert_eq!(AppError::io("写入失败", &denied).code, ErrorCode::PermissionDenied);
+        #[cfg(unix)]
+        assert_eq!(AppError::io("写入失败", &std::io::Error::from_raw_os_error(28)).code, ErrorCode::DiskFull);
+    }
+
+    #[test]
+    fn only_missing_executable_is_tool_missing() {
+        let spawn = |kind| AppError::from(RunError::Spawn(SpawnError { kind, message: "执行失败".to_string() }));
+        assert_eq!(spawn(std::io::ErrorKind::NotFound).code, ErrorCode::ToolMissing);
+        assert_eq!(spawn(std::io::ErrorKind::PermissionDenied).code, ErrorCode::Io);
+        assert_eq!(spawn(std::io::ErrorKind::Other).code, ErrorCode::Io);
+    }
+}
@@ -0,0 +1,326 @@
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use crate::logger;
+use crate::runner::{Activity, LineParser, OutputStream};
+use crate::LogEvent;
+
+/// ffmpeg 的单次进度（来自 `-progress` 输出）
+#[derive(Clone, Debug)]
+pub struct FfmpegProgress {
+    /// 已处理的时长（秒）
+    pub out_time: f64,
+    /// 百分比，未知总时长时为 None
+    pub percent: Option<f64>,
+    pub fps: Option<f64>,
+    /// 处理速度倍率（相对实时）
+    pub speed: Option<f64>,
+    /// 预计剩余时间（秒）
+    pub eta_seconds: Option<u64>,
+}
+
+impl FfmpegProgress {
+    /// 转换为与下载日志相同结构的事件
+    pub fn to_log_event(&self, job_id: Option<&str>) -> LogEvent {
+        let mut message = match self.percent {
+            Some(percent) => format!("处理进度: {:.1}%", percent),
+            None => format!("已处理: {}", format_duration(self.out_time as u64)),
+        };
+        if let Some(fps) = self.fps {
+            message.push_str(&format!("，{:.0} fps", fps));
+        }
+        if let Some(speed) = self.speed {
+            message.push_str(&format!("，{:.2}x", speed));
+        }
+        if let Some(eta) = self.eta_seconds {
+            message.push_str(&format!("，剩余 {}", format_duration(eta)));
+        }
+
+        LogEvent {
+            level: "INFO".to_string(),
+            message,
+            progress: self.percent,
+            speed: self.speed.map(|s| format!("{:.2}x", s)),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            job_id: job_id.map(|id| id.to_string()),
+            fps: self.fps,
+            eta_seconds: self.eta_seconds,
+            error_code: None,
+        }
+    }
+}
+
+/// 解析 `-progress` 输出的 `key=value` 行，每个 `progress=` 行产生一次进度
+pub struct ProgressParser {
+    duration: Option<f64>,
+    out_time: f64,
+    fps: Option<f64>,
+    speed: Option<f64>,
+}
+
+impl ProgressParser {
+    /// `duration` 为输入总时长（秒），用于计算百分比和剩余时间
+    pub fn new(duration: Option<f64>) -> Self {
+        ProgressParser {
+            duration: duration.filter(|d| *d > 0.0),
+            out_time: 0.0,
+            fps: None,
+            speed: None,
+        }
+    }
+
+    pub fn feed(&mut self, line: &str) -> Option<FfmpegProgress> {
+        let (key, value) = line.trim().split_once('=')?;
+        let value = value.trim();
+        match key {
+            "out_time_us" => {
+                if let Ok(us) = value.parse::<i64>() {
+                    self.out_time = us.max(0) as f64 / 1_000_000.0;
+                }
+            }
+            "fps" => self.fps = value.parse::<f64>().ok().filter(|f| *f > 0.0),
+            // 形如 `1.5x`，开始阶段为 `N/A`
+            "speed" => {
+                self.speed = value.trim_end_matches('x').parse::<f64>().ok().filter(|s| *s > 0.0);
+            }
+            "progress" => return Some(self.snapshot(value == "end")),
+            _ => {}
+        }
+        None
+    }
+
+    fn snapshot(&self, finished: bool) -> FfmpegProgress {
+        let percent = if finished {
+            Some(100.0)
+        } else {
+            self.duration.map(|d| (self.out_time / d * 100.0).clamp(0.0, 100.0))
+        };
+        let eta_seconds = match (self.duration, self.speed) {
+            _ if finished => Some(0),
+            (Some(d), Some(speed)) => Some(((d - self.out_time).max(0.0) / speed).round() as u64),
+            _ => None,
+        };
+        FfmpegProgress {
+            out_time: self.out_time,
+            percent,
+            fps: self.fps,
+            speed: self.speed,
+            eta_seconds,
+        }
+    }
+}
+
+/// ffmpeg 输出解析策略：stdout 上的 `-progress` 输出转换为进度事件，stderr 写入工具日志
+pub struct FfmpegParser {
+    progress: Mutex<ProgressParser>,
+    job_id: Option<String>,
+}
+
+impl FfmpegParser {
+    pub fn new(duration: Option<f64>, job_id: Option<&str>) -> Self {
+        FfmpegParser {
+            progress: Mutex::new(ProgressParser::new(duration)),
+            job_id: job_id.map(|id| id.to_string()),
+        }
+    }
+}
+
+impl LineParser for FfmpegParser {
+    fn parse(&self, stream: OutputStream, line: &str) -> Option<LogEvent> {
+        if stream == OutputStream::Stdout && is_progress_line(line) {
+            let progress = self.progress.lock().unwrap().feed(line)?;
+            return Some(progress.to_log_event(self.job_id.as_deref()));
+        }
+        log::debug!("ffmpeg: {}", line);
+        let _ = match &self.job_id {
+            Some(job_id) => logger::write_job_log("ffmpeg", "INFO", job_id, None, line),
+            None => logger::write_tool_log("ffmpeg", "INFO", line),
+        };
+        None
+    }
+
+    /// 已处理时长和输出大小增加时视为有进展，其余 `-progress` 行（帧率、速度等）不算
+    fn activity(&self, stream: OutputStream, line: &str) -> Activity {
+        if stream != OutputStream::Stdout || !is_progress_line(line) {
+            return Activity::Output;
+        }
+        let (key, value) = match line.trim().split_once('=') {
+            Some(pair) => pair,
+            None => return Activity::Status,
+        };
+        let key = match key {
+            "out_time_us" => "out_time_us",
+            "total_size" => "total_size",
+            _ => return Activity::Status,
+        };
+        match value.trim().parse::<u64>() {
+            Ok(value) => Activity::Progress(key, value),
+            Err(_) => Activity::Status,
+        }
+    }
+}
+
+/// 是否为 `-progress` 输出的 `key=value` 行
+pub fn is_progress_line(line: &str) -> bool {
+    match line.trim().split_once('=') {
+        Some((key, _)) => !key.is_empty()
+            && key.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_'),
+        None => false,
+    }
+}
+
+/// 在参数前加入机器可读的进度输出（写入 stdout），并关闭 stderr 上的统计行
+pub fn with_progress_args(args: &[String]) -> Vec<String> {
+    let mut full = vec![
+        "-progress".to_string(),
+        "pipe:1".to_string(),
+        "-nostats".to_string(),
+    ];
+    full.extend(args.iter().cloned());
+    full
+}
+
+/// 参数中的第一个输入文件
+pub fn first_input(args: &[String]) -> Option<&str> {
+    args.iter().position(|a| a == "-i")
+        .and_then(|i| args.get(i + 1))
+        .map(|s| s.as_str())
+}
+
+/// 探测输入文件时长（秒）：ffmpeg 在没有输出文件时会打印输入信息后退出
+pub fn probe_duration(ffmpeg: &str, input: &str) -> Option<f64> {
+    let output = Command::new(ffmpeg)
+        .args(["-hide_banner", "-i", input])
+        .stdin(Stdio::null())
+        .output()
+        .inspect_err(|e| log::warn!("探测时长失败: {} ({})", input, e))
+        .ok()?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let duration = stderr.lines().find_map(parse_duration_line);
+    log::info!("输入时长: {} -> {:?}", input, duration);
+    duration
+}
+
+/// 解析 `  Duration: 00:01:23.45, start: 0.000000, bitrate: ...`
+fn parse_duration_line(line: &str) -> Option<f64> {
+    let rest = line.trim().strip_prefix("Duration:")?;
+    let value = rest.split(',').next()?.trim();
+    let parts: Vec<&str> = value.split(':').collect();
+    if parts.len() != 3 {
+        return None;
+    }
+    let h = parts[0].parse::<f64>().ok()?;
+    let m = parts[1].parse::<f64>().ok()?;
+    let s = parts[2].parse::<f64>().ok()?;
+    Some(h * 3600.0 + m * 60.0 + s)
+}
+
+fn format_duration(seconds: u64) -> String {
+    format!("{:02}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed_all(parser: &mut ProgressParser, lines: &[&str]) -> Vec<FfmpegProgress> {
+        lines.iter().filter_map(|line| parser.feed(line)).collect()
+    }
+
+    #[test]
+    fn reports_progress_on_progress_lines() {
+        let mut parser = ProgressParser::new(Some(10.0));
+        let updates = feed_all(&mut parser, &[
+            "frame=100",
+            "fps=25.0",
+            "out_time_us=4000000",
+            "speed=2.0x",
+            "progress=continue",
+        ]);
+        assert_eq!(updates.len(), 1);
+        let progress = &updates[0];
+        assert_eq!(progress.out_time, 4.0);
+        assert_eq!(progress.percent, Some(40.0));
+        assert_eq!(progress.fps, Some(25.0));
+        assert_eq!(progress.speed, Some(2.0));
+        assert_eq!(progress.eta_seconds, Some(3));
+    }
+
+    #[test]
+    fn end_of_progress_is_complete() {
+        let mut parser = ProgressParser::new(Some(10.0));
+        let updates = feed_all(&mut parser, &["out_time_us=9000000", "progress=end"]);
+        assert_eq!(updates[0].percent, Some(100.0));
+        assert_eq!(updates[0].eta_seconds, Some(0));
+    }
+
+    #[test]
+    fn unknown_duration_has_no_percent() {
+        let mut parser = ProgressParser::new(None);
+        let updates = feed_all(&mut parser, &["out_time_us=3000000", "speed=1.5x", "progress=continue"]);
+        assert_eq!(updates[0].out_time, 3.0);
+        assert_eq!(updates[0].percent, None);
+        assert_eq!(updates[0].eta_seconds, None);
+
+        // 时长为 0 视为未知
+        let mut parser = ProgressParser::new(Some(0.0));
+        assert_eq!(parser.feed("progress=continue").unwrap().percent, None);
+    }
+
+    #[test]
+    fn ignores_unavailable_values() {
+        let mut parser = ProgressParser::new(Some(10.0));
+        let updates = feed_all(&mut parser, &[
+            "out_time_us=2000000",
+            "progress=continue",
+            "out_time_us=N/A",
+            "fps=0.00",
+            "speed=N/A",
+            "progress=continue",
+        ]);
+        assert_eq!(updates[1].out_time, 2.0);
+        assert_eq!(updates[1].fps, None);
+        assert_eq!(updates[1].speed, None);
+        assert_eq!(updates[1].eta_seconds, None);
+
+        assert!(parser.feed("out_time_us=-5").is_none());
+        assert_eq!(parser.feed("progress=continue").unwrap().out_time, 0.0);
+    }
+
+    #[test]
+    fn parses_duration_lines() {
+        let duration = parse_duration_line("  Duration: 00:01:23.45, start: 0.000000, bitrate: 1000 kb/s").unwrap();
+        assert!((duration - 83.45).abs() < 1e-9);
+        assert_eq!(parse_duration_line("Duration: 01:00:00.00, start: 0.0"), Some(3600.0));
+        assert_eq!(parse_duration_line("  Duration: N/A, start: 0.000000, bitrate: N/A"), None);
+        assert_eq!(parse_duration_line("  Stream #0:0: Video: h264"), None);
+    }
+
+    #[test]
+    fn recognizes_progress_lines() {
+        assert!(is_progress_line("out_time_us=1000"));
+        assert!(is_progress_line("progress=end"));
+        assert!(!is_progress_line("Error opening input: No such file"));
+        assert!(!is_progress_line("Metadata: title=abc"));
+        assert!(!is_progress_line("=1"));
+    }
+
+    #[test]
+    fn only_advancing_counters_are_activity() {
+        let parser = FfmpegParser::new(None, None);
+        assert_eq!(parser.activity(OutputStream::Stdout, "out_time_us=1000"), Activity::Progress("out_time_us", 1000));
+        assert_eq!(parser.activity(OutputStream::Stdout, "total_size=2048"), Activity::Progress("total_size", 2048));
+        assert_eq!(parser.activity(OutputStream::Stdout, "out_time_us=N/A"), Activity::Status);
+        assert_eq!(parser.activity(OutputStream::Stdout, "speed=1.2x"), Activity::Status);
+        assert_eq!(parser.activity(OutputStream::Stderr, "frame=1 fps=0.0"), Activity::Output);
+    }
+
+    #[test]
+    fn builds_progress_args() {
+        let args: Vec<String> = ["-i", "in.mp4", "out.mp4"].iter().map(|s| s.to_string()).collect();
+        let full = with_progress_args(&args);
+        assert_eq!(&full[..3], ["-progress", "pipe:1", "-nostats"]);
+        assert_eq!(first_input(&full), Some("in.mp4"));
+        assert_eq!(first_input(&full[3..4]), None);
+        assert_eq!(format_duration(3725), "01:02:05");
+    }
+}
@@ -0,0 +1,760 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use chrono::{DateTime, Datelike, Local};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use tauri::{command, Emitter};
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::redact;
+
+/// 下载历史文件名（位于应用数据目录，与旧版前端存储的文件相同，旧记录可直接读取）
+const HISTORY_FILE: &str = "download_history.json";
+
+/// 分页查询的默认条数
+const DEFAULT_PAGE_SIZE: usize = 50;
+
+/// 统计中返回的来源主机数量
+const TOP_HOSTS: usize = 10;
+
+/// 历史记录（新记录在前），所有窗口共用
+static STORE: Lazy<Mutex<Store>> = Lazy::new(|| Mutex::new(Store {
+    path: None,
+    records: Vec::new(),
+    save_error: None,
+}));
+
+struct Store {
+    path: Option<PathBuf>,
+    records: Vec<HistoryRecord>,
+    /// 无法安全保存的原因（原文件无法读取或损坏后无法备份），此时拒绝保存以免覆盖原有记录
+    save_error: Option<String>,
+}
+
+impl Store {
+    /// 先写入临时文件再重命名，避免写入中断时损坏历史文件
+    fn save(&self) -> AppResult<()> {
+        if let Some(reason) = &self.save_error {
+            return Err(AppError::new(ErrorCode::Io, format!("下载历史无法保存: {}", reason)));
+        }
+        let path = self.path.as_ref()
+            .ok_or_else(|| AppError::internal("下载历史未初始化"))?;
+        let content = serde_json::to_string_pretty(&self.records)
+            .map_err(|e| AppError::internal(format!("序列化历史记录失败: {}", e)))?;
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, content)
+            .map_err(|e| AppError::io("保存历史记录失败", &e))?;
+        std::fs::rename(&tmp, path)
+            .map_err(|e| AppError::io("保存历史记录失败", &e))
+    }
+
+    fn find_mut(&mut self, id: &str) -> Option<&mut HistoryRecord> {
+        self.records.iter_mut().find(|r| r.id == id)
+    }
+}
+
+/// 下载状态
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryStatus {
+    Downloading,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+/// 下载历史记录（对应前端 `DownloadRecord`）
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryRecord {
+    /// 由下载任务产生的记录使用任务 ID
+    #[serde(default)]
+    pub id: String,
+    pub title: String,
+    pub mpd_url: String,
+    pub status: HistoryStatus,
+    #[serde(default)]
+    pub progress: f64,
+    #[serde(default)]
+    pub created_at: String,
+    pub completed_at: Option<String>,
+    pub save_dir: Option<String>,
+    pub files: Option<Vec<String>>,
+    pub error_message: Option<String>,
+    pub error_code: Option<ErrorCode>,
+    /// 输出文件总大小（字节）
+    pub file_size: Option<u64>,
+    pub duration: Option<String>,
+    /// 任务的单独日志文件，可通过 `read_job_log` 读取
+    pub log_file: Option<String>,
+    /// 后期处理失败的原因（下载本身已完成，状态保持 completed）
+    pub post_process_error: Option<String>,
+}
+
+/// 记录的部分更新，未指定的字段保持不变
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryPatch {
+    pub title: Option<String>,
+    pub status: Option<HistoryStatus>,
+    pub progress: Option<f64>,
+    pub completed_at: Option<String>,
+    pub files: Option<Vec<String>>,
+    pub error_message: Option<String>,
+    pub error_code: Option<ErrorCode>,
+    pub file_size: Option<u64>,
+    pub duration: Option<String>,
+    /// 只由下载任务设置
+    #[serde(skip)]
+    pub log_file: Option<String>,
+    /// 只由后期处理设置
+    #[serde(skip)]
+    pub post_process_error: Option<String>,
+}
+
+impl HistoryRecord {
+    fn apply(&mut self, patch: HistoryPatch) {
+        if let Some(title) = patch.title {
+            self.title = title;
+        }
+        if let Some(status) = patch.status {
+            self.status = status;
+        }
+        if let Some(progress) = patch.progress {
+            self.progress = progress;
+        }
+        if patch.completed_at.is_some() {
+            self.completed_at = patch.completed_at;
+        }
+        if patch.files.is_some() {
+            self.files = patch.files;
+        }
+        if patch.error_message.is_some() {
+            self.error_message = patch.error_message;
+        }
+        if patch.error_code.is_some() {
+            self.error_code = patch.error_code;
+        }
+        if patch.file_size.is_some() {
+            self.file_size = patch.file_size;
+        }
+        if patch.duration.is_some() {
+            self.duration = patch.duration;
+        }
+        if patch.log_file.is_some() {
+            self.log_file = patch.log_file;
+        }
+        if patch.post_process_error.is_some() {
+            self.post_process_error = patch.post_process_error;
+        }
+    }
+}
+
+/// 查询条件，均为可选
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryQuery {
+    /// 只返回这些状态的记录
+    pub status: Option<Vec<HistoryStatus>>,
+    /// 创建时间下限（RFC 3339，含）
+    pub from: Option<String>,
+    /// 创建时间上限（RFC 3339，不含）
+    pub to: Option<String>,
+    /// 标题关键字（不区分大小写）
+    pub title: Option<String>,
+    /// 全文搜索：以空白分隔的每个词都须出现在标题或下载地址中（不区分大小写）
+    pub search: Option<String>,
+    #[serde(default)]
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+/// 分页查询结果
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryPage {
+    pub records: Vec<HistoryRecord>,
+    /// 符合条件的记录总数
+    pub total: usize,
+}
+
+/// 历史变更事件（`history-changed`），各窗口据此刷新
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct HistoryChanged {
+    action: &'static str,
+    ids: Vec<String>,
+}
+
+fn emit_changed(app: &tauri::AppHandle, action: &'static str, ids: Vec<String>) {
+    let _ = app.emit("history-changed", HistoryChanged { action, ids });
+}
+
+/// 按 RFC 3339 时间比较（格式不合法的时间视为不满足条件）
+fn time_before(a: &str, b: &str) -> bool {
+    match (DateTime::parse_from_rfc3339(a), DateTime::parse_from_rfc3339(b)) {
+        (Ok(a), Ok(b)) => a < b,
+        _ => false,
+    }
+}
+
+impl HistoryQuery {
+    fn matches(&self, record: &HistoryRecord) -> bool {
+        if let Some(status) = &self.status {
+            if !status.contains(&record.status) {
+                return false;
+            }
+        }
+        if let Some(from) = &self.from {
+            if time_before(&record.created_at, from) {
+                return false;
+            }
+        }
+        if let Some(to) = &self.to {
+            if !time_before(&record.created_at, to) {
+                return false;
+            }
+        }
+        if let Some(title) = &self.title {
+            if !record.title.to_lowercase().contains(&title.to_lowercase()) {
+                return false;
+            }
+        }
+        if let Some(search) = &self.search {
+            let text = format!("{}\n{}", record.title, record.mpd_url).to_lowercase();
+            if !search.to_lowercase().split_whitespace().all(|term| text.contains(term)) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// 加载历史记录；文件损坏时另存为 `download_history.corrupt-<时间>.json` 后从空记录开始。
+/// 文件无法读取或无法备份时返回错误，本次运行中的保存也会返回该错误
+pub fn init(app_data_dir: PathBuf) -> AppResult<()> {
+    let path = app_data_dir.join(HISTORY_FILE);
+    let backup = path.with_extension(format!("corrupt-{}.json", Local::now().format("%Y%m%d%H%M%S")));
+    let (records, save_error) = load(&path, &backup);
+    let mut store = STORE.lock().unwrap();
+    store.records = records;
+    store.path = Some(path);
+    store.save_error = save_error.clone();
+    log::info!("已加载 {} 条下载历史", store.records.len());
+    match save_error {
+        Some(reason) => Err(AppError::new(ErrorCode::Io, format!("下载历史无法保存: {}", reason))),
+        None => Ok(()),
+    }
+}
+
+/// 读取历史文件，返回记录及无法保存的原因；无法解析的文件改名为 `backup` 保留
+fn load(path: &Path, backup: &Path) -> (Vec<HistoryRecord>, Option<String>) {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return (Vec::new(), None),
+        Err(e) => {
+            log::error!("读取历史记录失败: {}", e);
+            return (Vec::new(), Some(format!("读取 {:?} 失败: {}", path, e)));
+        }
+    };
+    let error = match serde_json::from_str(&content) {
+        Ok(records) => return (records, None),
+        Err(e) => e,
+    };
+    match std::fs::rename(path, backup) {
+        Ok(()) => {
+            log::error!("解析历史记录失败，已另存为 {:?} 并重新开始记录: {}", backup, error);
+            (Vec::new(), None)
+        }
+        Err(e) => {
+            log::error!("解析历史记录失败且无法备份 ({}): {}", e, error);
+            (Vec::new(), Some(format!("{:?} 已损坏且无法备份: {}", path, e)))
+        }
+    }
+}
+
+/// 插入记录（同 ID 的记录被替换），返回保存的记录
+pub fn insert(app: &tauri::AppHandle, mut record: HistoryRecord) -> AppResult<HistoryRecord> {
+    if record.id.is_empty() {
+        record.id = crate::jobs::next_job_id();
+    }
+    if record.created_at.is_empty() {
+        record.created_at = chrono::Utc::now().to_rfc3339();
+    }
+    {
+        let mut store = STORE.lock().unwrap();
+        store.records.retain(|r| r.id != record.id);
+        store.records.insert(0, record.clone());
+        store.save()?;
+    }
+    emit_changed(app, "insert", vec![record.id.clone()]);
+    Ok(record)
+}
+
+/// 更新记录，返回更新后的记录
+pub fn update(app: &tauri::AppHandle, id: &str, patch: HistoryPatch) -> AppResult<HistoryRecord> {
+    let record = {
+        let mut store = STORE.lock().unwrap();
+        let record = store.find_mut(id)
+            .ok_or_else(|| AppError::new(ErrorCode::NotFound, format!("历史记录不存在: {}", id)))?;
+        record.apply(patch);
+        let record = record.clone();
+        store.save()?;
+        record
+    };
+    emit_changed(app, "update", vec![id.to_string()]);
+    Ok(record)
+}
+
+/// 全部历史记录（新记录在前）
+pub fn records() -> Vec<HistoryRecord> {
+    STORE.lock().unwrap().records.clone()
+}
+
+/// 导入历史记录：ID 已存在时按 `overwrite` 覆盖或跳过，返回（导入数量，跳过数量）
+pub fn import(app: &tauri::AppHandle, records: Vec<HistoryRecord>, overwrite: bool) -> AppResult<(usize, usize)> {
+    let (mut imported, mut skipped) = (0, 0);
+    {
+        let mut store = STORE.lock().unwrap();
+        for record in records {
+            match store.records.iter().position(|r| r.id == record.id) {
+                Some(index) if overwrite => {
+                    store.records[index] = record;
+                    imported += 1;
+                }
+                Some(_) => skipped += 1,
+                None => {
+                    store.records.push(record);
+                    imported += 1;
+                }
+            }
+        }
+        // 保持新记录在前
+        store.records.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        store.save()?;
+    }
+    if imported > 0 {
+        emit_changed(app, "insert", Vec::new());
+    }
+    Ok((imported, skipped))
+}
+
+/// 在保存目录中查找以该保存名称输出的文件（`<保存名称>[.<流后缀>].<扩展名>`）及其总大小
+fn find_outputs(save_dir: &str, save_name: &str) -> (Vec<String>, u64) {
+    let mut files = Vec::new();
+    let mut size = 0;
+    if let Ok(entries) = std::fs::read_dir(save_dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let matches = path.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| crate::job_store::is_output_name(n, save_name));
+            if let (true, Ok(meta)) = (matches, entry.metadata()) {
+                if meta.is_file() {
+                    size += meta.len();
+                    files.push(path.to_string_lossy().to_string());
+                }
+            }
+        }
+    }
+    files.sort();
+    (files, size)
+}
+
+// ==================== 任务生命周期 ====================
+
+/// 下载任务启动（或恢复）时记录
+pub fn job_started(app: &tauri::AppHandle, job_id: &str, args: &[String]) {
+    let existing = STORE.lock().unwrap().records.iter().any(|r| r.id == job_id);
+    let log_file = crate::logger::job_log_path(job_id).map(|p| p.to_string_lossy().to_string());
+    let result = if existing {
+        // 恢复的任务沿用原记录
+        let patch = HistoryPatch {
+            status: Some(HistoryStatus::Downloading),
+            log_file,
+            ..Default::default()
+        };
+        update(app, job_id, patch).map(|_| ())
+    } else {
+        let url = args.first().filter(|a| !a.starts_with('-')).cloned().unwrap_or_default();
+        let save_name = crate::job_store::arg_value(args, "--save-name");
+        insert(app, HistoryRecord {
+            id: job_id.to_string(),
+            title: save_name.unwrap_or_else(|| url.clone()),
+            mpd_url: url,
+            status: HistoryStatus::Downloading,
+            progress: 0.0,
+            created_at: String::new(),
+            completed_at: None,
+            save_dir: crate::job_store::arg_value(args, "--save-dir"),
+            files: None,
+            error_message: None,
+            error_code: None,
+            file_size: None,
+            duration: None,
+            log_file,
+            post_process_error: None,
+        }).map(|_| ())
+    };
+    if let Err(e) = result {
+        log::warn!("记录下载历史失败: {} ({})", job_id, e);
+    }
+}
+
+/// 下载任务结束时更新状态；成功时记录输出文件和大小
+pub fn job_finished(app: &tauri::AppHandle, job_id: &str, status: HistoryStatus, message: Option<&str>, error_code: Option<ErrorCode>) {
+    let target = STORE.lock().unwrap().records.iter()
+        .find(|r| r.id == job_id)
+        .map(|r| (r.save_dir.clone(), r.title.clone()));
+    let (save_dir, title) = match target {
+        Some(target) => target,
+        None => return,
+    };
+
+    let mut patch = HistoryPatch {
+        status: Some(status),
+        completed_at: Some(chrono::Utc::now().to_rfc3339()),
+        error_message: message.map(redact::redact),
+        error_code,
+        ..Default::default()
+    };
+    if status == HistoryStatus::Completed {
+        patch.progress = Some(100.0);
+        if let Some(save_dir) = save_dir {
+            let (files, size) = find_outputs(&save_dir, &title);
+            patch.files = Some(files);
+            patch.file_size = Some(size);
+        }
+    }
+    if let Err(e) = update(app, job_id, patch) {
+        log::warn!("更新下载历史失败: {} ({})", job_id, e);
+    }
+}
+
+/// 后期处理完成后记录最终输出文件
+pub fn job_output(app: &tauri::AppHandle, job_id: &str, output: &Path) {
+    if !STORE.lock().unwrap().records.iter().any(|r| r.id == job_id) {
+        return;
+    }
+    let patch = HistoryPatch {
+        files: Some(vec![output.to_string_lossy().to_string()]),
+        file_size: std::fs::metadata(output).ok().map(|m| m.len()),
+        ..Default::default()
+    };
+    if let Err(e) = update(app, job_id, patch) {
+        log::warn!("更新下载历史失败: {} ({})", job_id, e);
+    }
+}
+
+/// 后期处理失败时记录原因；下载已完成，状态和下载文件保持不变
+pub fn post_process_failed(app: &tauri::AppHandle, job_id: &str, message: &str) {
+    if !STORE.lock().unwrap().records.iter().any(|r| r.id == job_id) {
+        return;
+    }
+    let patch = HistoryPatch {
+        post_process_error: Some(redact::redact(message)),
+        ..Default::default()
+    };
+    if let Err(e) = update(app, job_id, patch) {
+        log::warn!("更新下载历史失败: {} ({})", job_id, e);
+    }
+}
+
+// ==================== 统计 ====================
+
+/// 某一时间段（日或周）的汇总
+#[derive(Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PeriodStats {
+    /// `YYYY-MM-DD` 或 ISO 周 `YYYY-Www`（本地时间）
+    pub period: String,
+    pub total: usize,
+    pub completed: usize,
+    pub failed: usize,
+    pub bytes: u64,
+}
+
+/// 某一错误码的失败次数
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FailureStats {
+    /// 未识别原因的失败为 null
+    pub code: Option<ErrorCode>,
+    pub count: usize,
+    /// 占已结束任务（完成 + 失败）的比例
+    pub rate: f64,
+}
+
+/// 来源主机的下载次数
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HostStats {
+    pub host: String,
+    pub count: usize,
+    pub bytes: u64,
+}
+
+/// 下载历史统计
+#[derive(Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryStats {
+    pub total: usize,
+    pub completed: usize,
+    pub failed: usize,
+    pub cancelled: usize,
+    /// 已完成下载的输出总大小（字节）
+    pub bytes_downloaded: u64,
+    /// 已完成下载的平均速度（字节/秒，按开始到结束的时间计算）
+    pub average_speed: Option<f64>,
+    /// 失败数占已结束任务（完成 + 失败）的比例
+    pub failure_rate: f64,
+    pub failures_by_code: Vec<FailureStats>,
+    /// 按日汇总（时间升序）
+    pub per_day: Vec<PeriodStats>,
+    /// 按周汇总（时间升序）
+    pub per_week: Vec<PeriodStats>,
+    /// 下载次数最多的来源主机
+    pub top_hosts: Vec<HostStats>,
+}
+
+fn local_time(rfc3339: &str) -> Option<DateTime<Local>> {
+    DateTime::parse_from_rfc3339(rfc3339).ok().map(|t| t.with_timezone(&Local))
+}
+
+fn add_to_period(periods: &mut BTreeMap<String, PeriodStats>, period: String, record: &HistoryRecord) {
+    let stats = periods.entry(period.clone()).or_insert_with(|| PeriodStats { period, ..Default::default() });
+    stats.total += 1;
+    match record.status {
+        HistoryStatus::Completed => {
+            stats.completed += 1;
+            stats.bytes += record.file_size.unwrap_or(0);
+        }
+        HistoryStatus::Failed => stats.failed += 1,
+        _ => {}
+    }
+}
+
+/// 汇总符合条件的记录
+fn compute_stats<'a>(records: impl Iterator<Item = &'a HistoryRecord>) -> HistoryStats {
+    let mut stats = HistoryStats::default();
+    let mut failures: Vec<(Option<ErrorCode>, usize)> = Vec::new();
+    let mut per_day = BTreeMap::new();
+    let mut per_week = BTreeMap::new();
+    let mut hosts: BTreeMap<String, HostStats> = BTreeMap::new();
+    let (mut timed_bytes, mut timed_seconds) = (0u64, 0f64);
+
+    for record in records {
+        stats.total += 1;
+        match record.status {
+            HistoryStatus::Completed => {
+                stats.completed += 1;
+                let bytes = record.file_size.unwrap_or(0);
+                stats.bytes_downloaded += bytes;
+                let started = local_time(&record.created_at);
+                let finished = record.completed_at.as_deref().and_then(local_time);
+                if let (Some(started), Some(finished), true) = (started, finished, bytes > 0) {
+                    let seconds = (finished - started).num_milliseconds() as f64 / 1000.0;
+                    if seconds > 0.0 {
+                        timed_bytes += bytes;
+                        timed_seconds += seconds;
+                    }
+                }
+            }
+            HistoryStatus::Failed => {
+                stats.failed += 1;
+                match failures.iter_mut().find(|(code, _)| *code == record.error_code) {
+                    Some((_, count)) => *count += 1,
+                    None => failures.push((record.error_code, 1)),
+                }
+            }
+            HistoryStatus::Cancelled => stats.cancelled += 1,
+            HistoryStatus::Downloading => {}
+        }
+
+        if let Some(created) = local_time(&record.created_at) {
+            add_to_period(&mut per_day, created.format("%Y-%m-%d").to_string(), record);
+            let week = created.iso_week();
+            add_to_period(&mut per_week, format!("{}-W{:02}", week.year(), week.week()), record);
+        }
+
+        let host = tauri::Url::parse(&record.mpd_url).ok()
+            .and_then(|url| url.host_str().map(|h| h.to_lowercase()));
+        if let Some(host) = host {
+            let entry = hosts.entry(host.clone()).or_insert(HostStats { host, count: 0, bytes: 0 });
+            entry.count += 1;
+            if record.status == HistoryStatus::Completed {
+                entry.bytes += record.file_size.unwrap_or(0);
+            }
+        }
+    }
+
+    let finished = stats.completed + stats.failed;
+    let rate = |count: usize| if finished > 0 { count as f64 / finished as f64 } else { 0.0 };
+    stats.failure_rate = rate(stats.failed);
+    failures.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    stats.failures_by_code = failures.into_iter()
+        .map(|(code, count)| FailureStats { code, count, rate: rate(count) })
+        .collect();
+    if timed_seconds > 0.0 {
+        stats.average_speed = Some(timed_bytes as f64 / timed_seconds);
+    }
+    stats.per_day = per_day.into_values().collect();
+    stats.per_week = per_week.into_values().collect();
+    let mut hosts: Vec<HostStats> = hosts.into_values().collect();
+    hosts.sort_by(|a, b| b.count.cmp(&a.count).then(b.bytes.cmp(&a.bytes)));
+    hosts.truncate(TOP_HOSTS);
+    stats.top_hosts = hosts;
+    stats
+}
+
+// ==================== 下载历史命令 ====================
+
+/// 添加历史记录；未指定 ID 和创建时间时自动生成
+#[command]
+pub async fn add_history_record(app: tauri::AppHandle, record: HistoryRecord) -> AppResult<HistoryRecord> {
+    insert(&app, record)
+}
+
+/// 更新历史记录
+#[command]
+pub async fn update_history_record(app: tauri::AppHandle, id: String, patch: HistoryPatch) -> AppResult<HistoryRecord> {
+    update(&app, &id, patch)
+}
+
+/// 按条件分页查询历史记录（新记录在前）
+#[command]
+pub async fn query_history(query: Option<HistoryQuery>) -> AppResult<HistoryPage> {
+    let query = query.unwrap_or_default();
+    let store = STORE.lock().unwrap();
+    let matched: Vec<&HistoryRecord> = store.records.iter().filter(|r| query.matches(r)).collect();
+    let records = matched.iter()
+        .skip(query.offset)
+        .take(query.limit.unwrap_or(DEFAULT_PAGE_SIZE))
+        .map(|r| (*r).clone())
+        .collect();
+    Ok(HistoryPage { records, total: matched.len() })
+}
+
+/// 删除指定的历史记录，返回删除数量
+#[command]
+pub async fn delete_history_records(app: tauri::AppHandle, ids: Vec<String>) -> AppResult<usize> {
+    let removed = {
+        let mut store = STORE.lock().unwrap();
+        let before = store.records.len();
+        store.records.retain(|r| !ids.contains(&r.id));
+        let removed = before - store.records.len();
+        if removed > 0 {
+            store.save()?;
+        }
+        removed
+    };
+    if removed > 0 {
+        emit_changed(&app, "delete", ids);
+    }
+    Ok(removed)
+}
+
+/// 清空历史记录，返回删除数量
+#[command]
+pub async fn clear_history(app: tauri::AppHandle) -> AppResult<usize> {
+    let removed = {
+        let mut store = STORE.lock().unwrap();
+        let removed = store.records.len();
+        store.records.clear();
+        store.save()?;
+        removed
+    };
+    emit_changed(&app, "clear", Vec::new());
+    Ok(removed)
+}
+
+/// 统计下载历史（可按查询条件过滤，分页参数被忽略）
+#[command]
+pub async fn history_stats(query: Option<HistoryQuery>) -> AppResult<HistoryStats> {
+    let query = query.unwrap_or_default();
+    let store = STORE.lock().unwrap();
+    Ok(compute_stats(store.records.iter().filter(|r| query.matches(r))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("history-test-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn missing_file_starts_empty() {
+        let dir = temp_dir("missing");
+        let (records, save_error) = load(&dir.join(HISTORY_FILE), &dir.join("backup.json"));
+        assert!(records.is_empty());
+        assert!(save_error.is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn corrupt_file_is_moved_aside() {
+        let dir = temp_dir("corrupt");
+        let path = dir.join(HISTORY_FILE);
+        let backup = dir.join("backup.json");
+        std::fs::write(&path, "[{not json").unwrap();
+
+        let (records, save_error) = load(&path, &backup);
+        assert!(records.is_empty());
+        assert!(save_error.is_none());
+        assert!(!path.exists());
+        assert_eq!(std::fs::read_to_string(&backup).unwrap(), "[{not json");
+
+        // 之后的保存写入新文件
+        let store = Store { path: Some(path.clone()), records, save_error };
+        store.save().unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "[]");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn corrupt_file_that_cannot_be_renamed_refuses_to_save() {
+        let dir = temp_dir("unrenamable");
+        let path = dir.join(HISTORY_FILE);
+        std::fs::write(&path, "[{not json").unwrap();
+        // 备份位置是非空目录，改名必然失败
+        let backup = dir.join("backup.json");
+        std::fs::create_dir_all(backup.join("occupied")).unwrap();
+
+        let (records, save_error) = load(&path, &backup);
+        assert!(records.is_empty());
+        assert!(save_error.is_some());
+
+        let store = Store { path: Some(path.clone()), records, save_error };
+        let error = store.save().unwrap_err();
+        assert_eq!(error.code, ErrorCode::Io);
+        // 原文件保持不变
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "[{not json");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn finds_only_outputs_of_the_save_name() {
+        let dir = temp_dir("outputs");
+        for name in ["Movie.mp4", "Movie.en.m4a", "Movie 2.mp4", "Movie (director's cut).mp4", "Movie.txt"] {
+            std::fs::write(dir.join(name), "12345").unwrap();
+        }
+        let (files, size) = find_outputs(&dir.to_string_lossy(), "Movie");
+        let names: Vec<String> = files.iter()
+            .map(|f| Path::new(f).file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(names, ["Movie.en.m4a", "Movie.mp4"]);
+        assert_eq!(size, 10);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn save_without_init_is_an_error() {
+        let store = Store { path: None, records: Vec::new(), save_error: None };
+        assert!(store.save().is_err());
+    }
+}
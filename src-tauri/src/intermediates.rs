@@ -0,0 +1,362 @@
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use chrono::Local;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use tauri::command;
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::job_store::{self, TEMP_DIR_PREFIX};
+use crate::logger;
+use crate::settings;
+
+/// 中间文件记录文件名（位于应用数据目录）
+const STORE_FILE: &str = "intermediates.json";
+
+/// 回收站目录名（位于应用数据目录）
+const TRASH_DIR: &str = "trash";
+
+/// 回收站条目目录名中的时间格式
+const TRASH_TIME_FORMAT: &str = "%Y%m%d%H%M%S";
+
+static STATE: Lazy<Mutex<State>> = Lazy::new(|| Mutex::new(State {
+    store: None,
+    trash_dir: None,
+    files: Vec::new(),
+}));
+
+/// 回收站条目序号，避免同一秒内移入的同名文件冲突
+static TRASH_SEQ: AtomicU64 = AtomicU64::new(1);
+
+struct State {
+    store: Option<PathBuf>,
+    trash_dir: Option<PathBuf>,
+    files: Vec<Intermediate>,
+}
+
+impl State {
+    fn save(&self) {
+        let path = match &self.store {
+            Some(path) => path,
+            None => return,
+        };
+        let result = serde_json::to_string_pretty(&self.files)
+            .map_err(|e| e.to_string())
+            .and_then(|content| std::fs::write(path, content).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            log::error!("保存中间文件记录失败: {}", e);
+        }
+    }
+
+    /// 查找记录；`.temp_*` 临时目录中的文件按所在目录匹配
+    fn find(&self, path: &Path) -> Option<usize> {
+        self.files.iter().position(|f| {
+            let recorded = Path::new(&f.path);
+            path == recorded || (f.kind == IntermediateKind::TempDir && is_temp_dir_name(recorded) && path.starts_with(recorded))
+        })
+    }
+}
+
+/// 中间文件类型
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum IntermediateKind {
+    /// N_m3u8DL-RE 临时分片目录
+    TempDir,
+    /// 下载输出的视频流
+    Video,
+    /// 下载输出的音频流
+    Audio,
+    /// 音视频合并结果
+    Merged,
+    /// 下载输出的字幕
+    Subtitle,
+}
+
+/// 任务产生的中间文件
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Intermediate {
+    pub job_id: String,
+    pub path: String,
+    pub kind: IntermediateKind,
+    pub recorded_at: String,
+}
+
+/// 规范化上级目录并拼接文件名（不跟随最后一级的符号链接）；临时目录在下载器启动前记录，
+/// 保存目录可能尚未创建，此时按最近的已存在上级目录规范化后拼接剩余部分
+fn canonical(path: &Path) -> Option<PathBuf> {
+    let name = path.file_name()?;
+    let mut existing = path.parent()?;
+    let mut rest = Vec::new();
+    while !existing.exists() {
+        match existing.components().next_back() {
+            Some(Component::Normal(dir)) => rest.push(dir),
+            _ => return None,
+        }
+        existing = existing.parent()?;
+    }
+    let mut canonical = existing.canonicalize().ok()?;
+    for dir in rest.iter().rev() {
+        canonical.push(dir);
+    }
+    canonical.push(name);
+    Some(canonical)
+}
+
+/// 目录名是否为 N_m3u8DL-RE 临时目录（`.temp_*`）
+fn is_temp_dir_name(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(|n| n.starts_with(TEMP_DIR_PREFIX))
+}
+
+/// 下载输出文件按扩展名对应的类型，不处理的文件返回 None
+fn output_kind(path: &Path) -> Option<IntermediateKind> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    match extension.as_str() {
+        "mp4" | "mkv" | "ts" => Some(IntermediateKind::Video),
+        "m4a" | "aac" => Some(IntermediateKind::Audio),
+        "srt" | "vtt" => Some(IntermediateKind::Subtitle),
+        _ => None,
+    }
+}
+
+/// 初始化中间文件记录（丢弃已不存在的文件）并清理过期的回收站条目
+pub fn init(app_data_dir: PathBuf) {
+    let store = app_data_dir.join(STORE_FILE);
+    let files: Vec<Intermediate> = std::fs::read_to_string(&store).ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    {
+        let mut state = STATE.lock().unwrap();
+        state.files = files.into_iter().filter(|f| Path::new(&f.path).exists()).collect();
+        state.store = Some(store);
+        state.trash_dir = Some(app_data_dir.join(TRASH_DIR));
+        state.save();
+    }
+    let purged = purge_trash();
+    if purged > 0 {
+        log::info!("已清理 {} 个过期的回收站条目", purged);
+    }
+}
+
+/// 记录任务产生的中间文件，之后才允许删除
+pub fn record(job_id: &str, path: &Path, kind: IntermediateKind) {
+    let path = match canonical(path) {
+        Some(path) => path,
+        None => {
+            log::warn!("无法记录中间文件: {:?}", path);
+            return;
+        }
+    };
+    let mut state = STATE.lock().unwrap();
+    if state.files.iter().any(|f| Path::new(&f.path) == path) {
+        return;
+    }
+    state.files.push(Intermediate {
+        job_id: job_id.to_string(),
+        path: path.to_string_lossy().to_string(),
+        kind,
+        recorded_at: chrono::Utc::now().to_rfc3339(),
+    });
+    state.save();
+}
+
+/// 记录下载任务的临时目录。只记录 `.temp_*` 目录，且目录须在任务首次启动前不存在
+/// （恢复的任务沿用自己的记录），避免把已有的目录（如下载目录）整个当作可删除的中间文件
+pub fn record_temp_dir(job_id: &str, path: &Path) {
+    if !is_temp_dir_name(path) {
+        log::warn!("临时目录不是 {}* 目录，不记录为中间文件: {:?}", TEMP_DIR_PREFIX, path);
+        return;
+    }
+    if path.exists() {
+        let recorded = canonical(path).is_some_and(|canonical| {
+            STATE.lock().unwrap().files.iter()
+                .any(|f| f.job_id == job_id && Path::new(&f.path) == canonical)
+        });
+        if !recorded {
+            log::warn!("临时目录在任务启动前已存在，不记录为中间文件: {:?}", path);
+        }
+        return;
+    }
+    record(job_id, path, IntermediateKind::TempDir);
+}
+
+/// 下载完成时按保存目录和保存名称记录输出的视频、音频和字幕文件（只匹配该任务的文件名）
+pub fn record_outputs(job_id: &str, save_dir: &Path, save_name: &str) {
+    let entries = match std::fs::read_dir(save_dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            log::warn!("读取保存目录失败，未记录下载输出: {:?} ({})", save_dir, e);
+            return;
+        }
+    };
+    let mut outputs: Vec<PathBuf> = entries.flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .filter(|path| path.file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| job_store::is_output_name(n, save_name)))
+        .collect();
+    outputs.sort();
+    for path in outputs {
+        if let Some(kind) = output_kind(&path) {
+            record(job_id, &path, kind);
+        }
+    }
+}
+
+/// 任务记录过的仍然存在的中间文件（按记录顺序）
+pub fn job_files(job_id: &str) -> Vec<Intermediate> {
+    STATE.lock().unwrap().files.iter()
+        .filter(|f| f.job_id == job_id && Path::new(&f.path).exists())
+        .cloned()
+        .collect()
+}
+
+/// 删除前检查：只允许删除记录过的中间文件（或临时目录中的文件）
+fn check_recorded(path: &Path) -> AppResult<PathBuf> {
+    let canonical = canonical(path)
+        .ok_or_else(|| AppError::invalid_argument(format!("无法解析路径: {:?}", path)))?;
+    if STATE.lock().unwrap().find(&canonical).is_none() {
+        log::warn!("拒绝删除非中间文件: {:?}", canonical);
+        logger::write_audit_log(&format!("拒绝删除: {:?} (不是任务产生的中间文件)", canonical));
+        return Err(AppError::new(ErrorCode::PathNotAllowed,
+            format!("只能删除任务产生的中间文件: {}", path.to_string_lossy())));
+    }
+    Ok(canonical)
+}
+
+/// 删除记录过的中间文件；`trash` 为 None 时按配置 useTrash 决定是否移入回收站。返回文件是否存在
+pub fn delete(path: &Path, trash: Option<bool>) -> AppResult<bool> {
+    let path = check_recorded(path)?;
+    let existed = path.exists();
+    if existed {
+        remove(&path, trash)?;
+    }
+
+    // 记录本身被删除时移除记录；临时目录中的单个文件不影响目录记录
+    let mut state = STATE.lock().unwrap();
+    let before = state.files.len();
+    state.files.retain(|f| Path::new(&f.path) != path);
+    if state.files.len() != before {
+        state.save();
+    }
+    Ok(existed)
+}
+
+/// 删除 N_m3u8DL-RE 临时目录（丢弃的未完成任务或孤立的临时目录，可能没有记录）。
+/// 只接受 `.temp_*` 目录，调用方需先通过 `path_policy::check` 检查删除权限。返回目录是否存在
+pub fn delete_temp_dir(path: &Path) -> AppResult<bool> {
+    if !is_temp_dir_name(path) {
+        logger::write_audit_log(&format!("拒绝删除: {:?} (不是临时目录)", path));
+        return Err(AppError::new(ErrorCode::PathNotAllowed,
+            format!("只能删除临时目录: {}", path.to_string_lossy())));
+    }
+    let existed = path.is_dir();
+    if existed {
+        remove(path, None)?;
+        logger::write_audit_log(&format!("删除临时目录: {:?}", path));
+    }
+
+    let mut state = STATE.lock().unwrap();
+    let before = state.files.len();
+    state.files.retain(|f| !Path::new(&f.path).starts_with(path));
+    if state.files.len() != before {
+        state.save();
+    }
+    Ok(existed)
+}
+
+/// 移入回收站或直接删除；`trash` 为 None 时按配置 useTrash 决定
+fn remove(path: &Path, trash: Option<bool>) -> AppResult<()> {
+    let use_trash = trash.unwrap_or_else(|| settings::get().use_trash);
+    if use_trash {
+        move_to_trash(path)
+    } else if path.is_dir() {
+        std::fs::remove_dir_all(path)
+            .map_err(|e| AppError::io(&format!("删除目录失败 ({:?})", path), &e))
+    } else {
+        std::fs::remove_file(path)
+            .map_err(|e| AppError::io(&format!("删除文件失败 ({:?})", path), &e))
+    }
+}
+
+/// 移入回收站：`trash/<时间>_<序号>/<文件名>`；跨磁盘无法移动时复制后删除
+fn move_to_trash(path: &Path) -> AppResult<()> {
+    let trash_dir = STATE.lock().unwrap().trash_dir.clone()
+        .ok_or_else(|| AppError::internal("回收站未初始化"))?;
+    let seq = TRASH_SEQ.fetch_add(1, Ordering::SeqCst);
+    let entry = trash_dir.join(format!("{}_{}", Local::now().format(TRASH_TIME_FORMAT), seq));
+    std::fs::create_dir_all(&entry)
+        .map_err(|e| AppError::io("创建回收站目录失败", &e))?;
+    let name = path.file_name()
+        .ok_or_else(|| AppError::invalid_argument(format!("无效的文件路径: {:?}", path)))?;
+    let target = entry.join(name);
+
+    if std::fs::rename(path, &target).is_ok() {
+        log::info!("已移入回收站: {:?} -> {:?}", path, target);
+        return Ok(());
+    }
+    if path.is_dir() {
+        // 临时分片目录体积大且可重新下载，不复制到回收站
+        log::warn!("临时目录无法移入回收站，直接删除: {:?}", path);
+        let _ = std::fs::remove_dir(&entry);
+        return std::fs::remove_dir_all(path)
+            .map_err(|e| AppError::io(&format!("删除目录失败 ({:?})", path), &e));
+    }
+    std::fs::copy(path, &target)
+        .map_err(|e| AppError::io(&format!("移入回收站失败 ({:?})", path), &e))?;
+    std::fs::remove_file(path)
+        .map_err(|e| AppError::io(&format!("删除文件失败 ({:?})", path), &e))?;
+    log::info!("已复制到回收站: {:?} -> {:?}", path, target);
+    Ok(())
+}
+
+/// 清理超过保留天数（配置 trashRetentionDays）的回收站条目，返回清理数量
+pub fn purge_trash() -> usize {
+    let trash_dir = STATE.lock().unwrap().trash_dir.clone();
+    let retention_days = settings::get().trash_retention_days;
+    let entries = match trash_dir.and_then(|dir| std::fs::read_dir(dir).ok()) {
+        Some(entries) => entries,
+        None => return 0,
+    };
+    let cutoff = Local::now().naive_local() - chrono::Duration::days(retention_days as i64);
+
+    let mut purged = 0;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let created = path.file_name()
+            .and_then(|n| n.to_str())
+            .and_then(|n| n.split('_').next())
+            .and_then(|t| chrono::NaiveDateTime::parse_from_str(t, TRASH_TIME_FORMAT).ok());
+        if created.is_some_and(|t| t < cutoff) && std::fs::remove_dir_all(&path).is_ok() {
+            purged += 1;
+        }
+    }
+    purged
+}
+
+// ==================== 回收站命令 ====================
+
+/// 清空回收站，返回清理的条目数量
+#[command]
+pub async fn empty_trash() -> AppResult<usize> {
+    let trash_dir = match STATE.lock().unwrap().trash_dir.clone() {
+        Some(dir) => dir,
+        None => return Ok(0),
+    };
+    let entries = match std::fs::read_dir(&trash_dir) {
+        Ok(entries) => entries,
+        Err(_) => return Ok(0),
+    };
+    let mut count = 0;
+    for entry in entries.flatten() {
+        std::fs::remove_dir_all(entry.path())
+            .map_err(|e| AppError::io("清空回收站失败", &e))?;
+        count += 1;
+    }
+    logger::write_audit_log(&format!("清空回收站: {} 个条目", count));
+    Ok(count)
+}
@@ -0,0 +1,391 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use tauri::command;
+use crate::error::{AppError, AppResult};
+use crate::intermediates;
+use crate::path_policy::{self, Access};
+
+/// 任务存储文件名（位于应用数据目录）
+const STORE_FILE: &str = "jobs.json";
+
+/// N_m3u8DL-RE 临时目录前缀（以 `.temp_<时间戳>` 命名）
+pub const TEMP_DIR_PREFIX: &str = ".temp_";
+
+/// 任务存储文件路径；读写均在此锁内完成
+static STORE_PATH: Lazy<Mutex<Option<PathBuf>>> = Lazy::new(|| Mutex::new(None));
+
+/// 启动时发现的未完成任务（上次运行被退出或崩溃中断）
+static INTERRUPTED: Lazy<Mutex<Vec<StoredJob>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+/// 持久化的任务阶段
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum StoredPhase {
+    Queued,
+    Running,
+    Paused,
+}
+
+/// 持久化的下载任务
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredJob {
+    pub id: String,
+    pub args: Vec<String>,
+    pub working_dir: Option<String>,
+    pub save_dir: Option<String>,
+    pub save_name: Option<String>,
+    pub tmp_dir: Option<String>,
+    pub priority: i32,
+    pub phase: StoredPhase,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl StoredJob {
+    pub fn new(id: &str, args: &[String], working_dir: Option<&str>, priority: i32, phase: StoredPhase) -> Self {
+        let now = chrono::Utc::now().to_rfc3339();
+        StoredJob {
+            id: id.to_string(),
+            args: args.to_vec(),
+            working_dir: working_dir.map(|d| d.to_string()),
+            save_dir: arg_value(args, "--save-dir"),
+            save_name: arg_value(args, "--save-name"),
+            tmp_dir: arg_value(args, "--tmp-dir"),
+            priority,
+            phase,
+            created_at: now.clone(),
+            updated_at: now,
+        }
+    }
+}
+
+/// 启动时的恢复报告
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoveryReport {
+    pub jobs: Vec<StoredJob>,
+    pub orphan_temp_dirs: Vec<String>,
+}
+
+impl RecoveryReport {
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty() && self.orphan_temp_dirs.is_empty()
+    }
+}
+
+/// 读取参数中某个选项的值
+pub fn arg_value(args: &[String], flag: &str) -> Option<String> {
+    args.iter().position(|a| a == flag)
+        .and_then(|i| args.get(i + 1))
+        .cloned()
+}
+
+/// 下载器输出文件的扩展名（小写）
+const OUTPUT_EXTENSIONS: &[&str] = &[
+    "mp4", "mkv", "ts", "m4a", "aac", "ac3", "eac3", "mp3", "flac", "srt", "vtt", "ass", "ttml",
+];
+
+/// 文件名是否为以 `save_name` 保存的下载输出：`<保存名称>.<扩展名>` 或带流后缀的
+/// `<保存名称>.<语言等>.<扩展名>`；`<保存名称> 2.mp4` 等同前缀的其他文件不匹配
+pub fn is_output_name(file_name: &str, save_name: &str) -> bool {
+    let rest = match file_name.strip_prefix(save_name).and_then(|r| r.strip_prefix('.')) {
+        Some(rest) => rest,
+        None => return false,
+    };
+    let parts: Vec<&str> = rest.split('.').collect();
+    let (extension, suffixes) = match parts.split_last() {
+        Some(split) => split,
+        None => return false,
+    };
+    OUTPUT_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str())
+        && suffixes.len() <= 2
+        && suffixes.iter().all(|s| {
+            !s.is_empty() && s.len() <= 16 && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        })
+}
+
+fn load(path: &Path) -> Vec<StoredJob> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+            log::warn!("任务存储文件解析失败，已忽略: {}", e);
+            Vec::new()
+        }),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// 先写临时文件再重命名，避免崩溃时留下损坏的存储文件
+fn save(path: &Path, jobs: &[StoredJob]) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(jobs)
+        .map_err(|e| format!("序列化任务存储失败: {}", e))?;
+    let tmp_path = path.with_extension("json.tmp");
+    std::fs::write(&tmp_path, content)
+        .map_err(|e| format!("写入任务存储失败: {}", e))?;
+    std::fs::rename(&tmp_path, path)
+        .map_err(|e| format!("保存任务存储失败: {}", e))
+}
+
+/// 在存储锁内读取、修改并保存任务列表
+fn update<F: FnOnce(&mut Vec<StoredJob>)>(f: F) {
+    let guard = STORE_PATH.lock().unwrap();
+    let path = match guard.as_ref() {
+        Some(path) => path,
+        None => return, // 存储未初始化时静默跳过
+    };
+    let mut jobs = load(path);
+    f(&mut jobs);
+    if let Err(e) = save(path, &jobs) {
+        log::error!("{}", e);
+    }
+}
+
+/// 初始化任务存储，并收集上次运行遗留的未完成任务
+pub fn init(app_data_dir: PathBuf) -> AppResult<RecoveryReport> {
+    std::fs::create_dir_all(&app_data_dir)
+        .map_err(|e| AppError::io("创建应用数据目录失败", &e))?;
+
+    let path = app_data_dir.join(STORE_FILE);
+    let jobs = load(&path);
+    *STORE_PATH.lock().unwrap() = Some(path);
+    *INTERRUPTED.lock().unwrap() = jobs;
+
+    Ok(recovery_report())
+}
+
+/// 记录任务（已存在时更新参数和阶段）
+pub fn record(id: &str, args: &[String], working_dir: Option<&str>, priority: i32, phase: StoredPhase) {
+    update(|jobs| {
+        match jobs.iter_mut().find(|j| j.id == id) {
+            Some(job) => {
+                job.phase = phase;
+                job.updated_at = chrono::Utc::now().to_rfc3339();
+            }
+            None => jobs.push(StoredJob::new(id, args, working_dir, priority, phase)),
+        }
+    });
+}
+
+/// 更新任务阶段
+pub fn set_phase(id: &str, phase: StoredPhase) {
+    update(|jobs| {
+        if let Some(job) = jobs.iter_mut().find(|j| j.id == id) {
+            job.phase = phase;
+            job.updated_at = chrono::Utc::now().to_rfc3339();
+        }
+    });
+}
+
+/// 更新排队任务的优先级
+pub fn set_priority(id: &str, priority: i32) {
+    update(|jobs| {
+        if let Some(job) = jobs.iter_mut().find(|j| j.id == id) {
+            job.priority = priority;
+            job.updated_at = chrono::Utc::now().to_rfc3339();
+        }
+    });
+}
+
+/// 按 `ids` 的顺序重排这些任务在存储中的位置（其他任务位置不变），恢复时按存储顺序排队
+pub fn reorder(ids: &[String]) {
+    update(|jobs| {
+        let slots: Vec<usize> = jobs.iter().enumerate()
+            .filter(|(_, j)| ids.contains(&j.id))
+            .map(|(i, _)| i)
+            .collect();
+        let ordered: Vec<StoredJob> = ids.iter()
+            .filter_map(|id| jobs.iter().find(|j| &j.id == id).cloned())
+            .collect();
+        for (slot, job) in slots.into_iter().zip(ordered) {
+            jobs[slot] = job;
+        }
+    });
+}
+
+/// 任务结束（完成、失败或取消）后移除
+pub fn remove(id: &str) {
+    update(|jobs| jobs.retain(|j| j.id != id));
+}
+
+/// 规范化已存在的路径，便于比较
+fn normalized(path: &str) -> PathBuf {
+    let path = PathBuf::from(path);
+    path.canonicalize().unwrap_or(path)
+}
+
+/// 仍可能使用临时目录的任务参数：存储中的全部任务（含运行中、排队和已恢复的任务）、
+/// 未处理的中断任务、正在运行的任务和队列中的任务
+fn live_args() -> Vec<Vec<String>> {
+    let mut args: Vec<Vec<String>> = match STORE_PATH.lock().unwrap().as_ref() {
+        Some(path) => load(path).into_iter().map(|j| j.args).collect(),
+        None => Vec::new(),
+    };
+    args.extend(INTERRUPTED.lock().unwrap().iter().map(|j| j.args.clone()));
+    args.extend(crate::jobs::running_args());
+    args.extend(crate::queue::all_args());
+    args
+}
+
+/// 查找未被任何任务引用的 `.temp_*` 目录
+fn find_orphan_temp_dirs() -> Vec<PathBuf> {
+    let live = live_args();
+    let known: HashSet<PathBuf> = live.iter()
+        .filter_map(|args| arg_value(args, "--tmp-dir"))
+        .map(|dir| normalized(&dir))
+        .collect();
+    // 未指定临时目录的任务由 N_m3u8DL-RE 在保存目录中自行创建，无法区分，不扫描这些目录
+    let unattributed: HashSet<PathBuf> = live.iter()
+        .filter(|args| arg_value(args, "--tmp-dir").is_none())
+        .filter_map(|args| arg_value(args, "--save-dir"))
+        .map(|dir| normalized(&dir))
+        .collect();
+
+    let mut roots: Vec<PathBuf> = live.iter()
+        .filter_map(|args| arg_value(args, "--save-dir"))
+        .map(|dir| normalized(&dir))
+        .collect();
+    if let Some(downloads) = dirs::download_dir() {
+        roots.push(downloads.canonicalize().unwrap_or(downloads));
+    }
+    roots.sort();
+    roots.dedup();
+
+    let mut orphans = Vec::new();
+    for root in roots.into_iter().filter(|root| !unattributed.contains(root)) {
+        let entries = match std::fs::read_dir(&root) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let is_temp = path.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with(TEMP_DIR_PREFIX));
+            if is_temp && path.is_dir() && !known.contains(&normalized(&path.to_string_lossy())) {
+                orphans.push(path);
+            }
+        }
+    }
+    orphans
+}
+
+fn recovery_report() -> RecoveryReport {
+    let jobs = INTERRUPTED.lock().unwrap().clone();
+    let orphan_temp_dirs = find_orphan_temp_dirs().iter()
+        .map(|p| p.to_string_lossy().to_string())
+        .collect();
+    RecoveryReport { jobs, orphan_temp_dirs }
+}
+
+/// 删除 N_m3u8DL-RE 临时目录：需在允许删除的目录中，且只处理 `.temp_*` 目录
+fn remove_temp_dir(path: &Path) -> bool {
+    let result = path_policy::check(&path.to_string_lossy(), Access::Delete)
+        .and_then(|path| intermediates::delete_temp_dir(&path));
+    match result {
+        Ok(existed) => existed,
+        Err(e) => {
+            log::warn!("删除临时目录失败: {:?} ({})", path, e);
+            false
+        }
+    }
+}
+
+/// 取出选中的未完成任务（`ids` 为空表示全部）
+fn take_interrupted(ids: Option<Vec<String>>) -> Vec<StoredJob> {
+    let mut interrupted = INTERRUPTED.lock().unwrap();
+    let (selected, rest): (Vec<StoredJob>, Vec<StoredJob>) = interrupted.drain(..)
+        .partition(|j| ids.as_ref().map_or(true, |ids| ids.contains(&j.id)));
+    *interrupted = rest;
+    selected
+}
+
+/// 将未完成任务重新加入下载队列（复用原临时目录，已下载的分片会被保留）。
+/// 中断时已启动的任务排在前面，排队中的任务保持保存时的顺序
+pub fn resume_interrupted(app: &tauri::AppHandle, ids: Option<Vec<String>>) -> usize {
+    let mut jobs = take_interrupted(ids);
+    jobs.sort_by_key(|j| j.phase == StoredPhase::Queued);
+    let count = jobs.len();
+    for job in jobs {
+        log::info!("恢复未完成任务: {}", job.id);
+        crate::queue::enqueue_stored(app, job);
+    }
+    count
+}
+
+/// 丢弃未完成任务并清理其临时目录；`clean_orphans` 时同时清理孤立的临时目录
+pub fn discard_interrupted(ids: Option<Vec<String>>, clean_orphans: bool) -> usize {
+    let jobs = take_interrupted(ids);
+    for job in &jobs {
+        remove(&job.id);
+    }
+    // 丢弃的任务已从存储中移除，其临时目录不会被当作仍在使用
+    let orphans = if clean_orphans {
+        find_orphan_temp_dirs()
+    } else {
+        Vec::new()
+    };
+
+    let mut cleaned = 0;
+    for job in &jobs {
+        if let Some(tmp_dir) = &job.tmp_dir {
+            if remove_temp_dir(Path::new(tmp_dir)) {
+                cleaned += 1;
+            }
+        }
+    }
+    for orphan in orphans {
+        if remove_temp_dir(&orphan) {
+            cleaned += 1;
+        }
+    }
+    log::info!("已丢弃 {} 个未完成任务，清理 {} 个临时目录", jobs.len(), cleaned);
+    cleaned
+}
+
+// ==================== 任务恢复命令 ====================
+
+/// 列出上次运行遗留的未完成任务和孤立临时目录
+#[command]
+pub async fn list_recoverable_jobs() -> AppResult<RecoveryReport> {
+    Ok(recovery_report())
+}
+
+/// 恢复未完成任务（`job_ids` 为空表示全部），返回恢复数量
+#[command]
+pub async fn resume_recovered_jobs(app: tauri::AppHandle, job_ids: Option<Vec<String>>) -> AppResult<usize> {
+    Ok(resume_interrupted(&app, job_ids))
+}
+
+/// 丢弃未完成任务并清理其临时目录，返回清理的目录数量；`clean_orphans` 为 true 时同时清理孤立的临时目录
+#[command]
+pub async fn discard_recovered_jobs(job_ids: Option<Vec<String>>, clean_orphans: Option<bool>) -> AppResult<usize> {
+    Ok(discard_interrupted(job_ids, clean_orphans.unwrap_or(false)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_exact_save_name_outputs() {
+        assert!(is_output_name("Movie.mp4", "Movie"));
+        assert!(is_output_name("Movie.MP4", "Movie"));
+        assert!(is_output_name("Movie.zh-Hans.srt", "Movie"));
+        assert!(is_output_name("Movie.audio.en.m4a", "Movie"));
+    }
+
+    #[test]
+    fn rejects_other_files_with_same_prefix() {
+        assert!(!is_output_name("Movie 2.mp4", "Movie"));
+        assert!(!is_output_name("Movie (director's cut).mp4", "Movie"));
+        assert!(!is_output_name("Movie_merged.mp4", "Movie"));
+        assert!(!is_output_name("Movie.mp4.part", "Movie"));
+        assert!(!is_output_name("Movie.txt", "Movie"));
+        assert!(!is_output_name("Movie.a.b.c.mp4", "Movie"));
+        assert!(!is_output_name("Movie..mp4", "Movie"));
+        assert!(!is_output_name("Movie", "Movie"));
+    }
+}
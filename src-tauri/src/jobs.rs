@@ -0,0 +1,383 @@
+use std::collections::HashMap;
+use std::process::{Child, ExitStatus};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+use chrono::Local;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use sysinfo::{Pid, Signal, System};
+use tauri::{command, Emitter};
+use crate::error::{AppError, AppResult};
+use crate::job_store::{self, StoredPhase};
+use crate::phase::{self, DownloadPhase, PhaseChange, PhaseMachine};
+use crate::runner::{ProcessSpec, RunError, Watchdog, WAIT_POLL_INTERVAL};
+
+/// 正在运行的任务表（任务 ID -> 任务）
+static JOBS: Lazy<Mutex<HashMap<String, Arc<Job>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 任务 ID 自增序号，避免同一毫秒内启动的任务冲突
+static JOB_SEQ: AtomicU64 = AtomicU64::new(1);
+
+/// 任务状态
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum JobState {
+    Running,
+    Paused,
+    Cancelling,
+}
+
+/// 任务类型
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum JobKind {
+    /// N_m3u8DL-RE 下载，可暂停
+    Download,
+    /// 后期处理流水线，依次运行多个 ffmpeg 进程
+    PostProcess,
+}
+
+/// 返回给前端的任务信息
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct JobInfo {
+    pub id: String,
+    pub kind: JobKind,
+    pub tool: String,
+    pub pid: u32,
+    pub state: JobState,
+    pub phase: DownloadPhase,
+    pub started_at: String,
+}
+
+/// 任务状态变化事件（`job-state`）
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct JobStateEvent {
+    job_id: String,
+    state: JobState,
+    timestamp: String,
+}
+
+/// 后端托管的外部工具进程
+pub struct Job {
+    pub id: String,
+    pub kind: JobKind,
+    pub tool: String,
+    /// 启动进程所需的信息，暂停后重新启动时复用
+    pub spec: ProcessSpec,
+    pub started_at: String,
+    pid: AtomicU32,
+    /// 当前子进程（流水线任务在步骤之间没有子进程）
+    child: Mutex<Option<Child>>,
+    state: Mutex<JobState>,
+    state_changed: Condvar,
+    /// 暂停时进程是被挂起（true）还是被终止、等待恢复时重启（false）
+    suspended: AtomicBool,
+    phase: Mutex<PhaseMachine>,
+}
+
+impl Job {
+    fn new(id: String, kind: JobKind, spec: ProcessSpec) -> Self {
+        Job {
+            id,
+            kind,
+            tool: spec.tool.clone(),
+            spec,
+            started_at: chrono::Utc::now().to_rfc3339(),
+            pid: AtomicU32::new(0),
+            child: Mutex::new(None),
+            state: Mutex::new(JobState::Running),
+            state_changed: Condvar::new(),
+            suspended: AtomicBool::new(false),
+            phase: Mutex::new(PhaseMachine::new(DownloadPhase::Queued)),
+        }
+    }
+
+    pub fn pid(&self) -> u32 {
+        self.pid.load(Ordering::SeqCst)
+    }
+
+    pub fn state(&self) -> JobState {
+        *self.state.lock().unwrap()
+    }
+
+    /// 任务是否已被用户取消
+    pub fn is_cancelled(&self) -> bool {
+        self.state() == JobState::Cancelling
+    }
+
+    /// 暂停时进程已被终止，需要在恢复时重新启动；`status` 为进程的退出状态。
+    /// 进程在暂停生效前已正常退出（暂停与退出竞争）时下载已完成，不重新启动
+    pub fn needs_relaunch(&self, status: Option<ExitStatus>) -> bool {
+        let exited_normally = status.is_some_and(|s| s.success());
+        self.state() == JobState::Paused && !self.suspended.load(Ordering::SeqCst) && !exited_normally
+    }
+
+    /// 等待进程退出（轮询方式，避免长时间持有子进程锁导致无法取消）；
+    /// 超时时终止进程树并返回 `RunError::Timeout`
+    pub fn wait(&self, watchdog: &mut Watchdog) -> Result<ExitStatus, RunError> {
+        loop {
+            watchdog.set_paused(self.state() == JobState::Paused);
+            // 下载器解密、混流时调用外部工具，期间可能长时间没有输出
+            watchdog.set_quiet(self.kind == JobKind::Download
+                && matches!(self.phase(), DownloadPhase::Decrypting | DownloadPhase::Merging));
+            {
+                let mut guard = self.child.lock().unwrap();
+                let child = guard.as_mut()
+                    .ok_or_else(|| RunError::Wait("任务没有运行中的进程".to_string()))?;
+                if let Some(status) = child.try_wait()
+                    .map_err(|e| RunError::Wait(format!("等待进程完成失败: {}", e)))? {
+                    return Ok(status);
+                }
+                if let Some(kind) = watchdog.check() {
+                    log::error!("任务超时（{}），终止进程: {} (pid {})", kind, self.id, self.pid());
+                    kill_process_tree(self.pid());
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(RunError::Timeout(kind));
+                }
+            }
+            std::thread::sleep(WAIT_POLL_INTERVAL);
+        }
+    }
+
+    /// 阻塞直到任务被恢复或取消；返回 true 表示应重新启动进程
+    pub fn wait_for_resume(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        while *state == JobState::Paused {
+            state = self.state_changed.wait(state).unwrap();
+        }
+        *state == JobState::Running
+    }
+
+    /// 等待指定时间，期间任务被取消时提前返回 false
+    pub fn sleep_unless_cancelled(&self, duration: Duration) -> bool {
+        let state = self.state.lock().unwrap();
+        let (state, _) = self.state_changed
+            .wait_timeout_while(state, duration, |s| *s != JobState::Cancelling)
+            .unwrap();
+        *state != JobState::Cancelling
+    }
+
+    /// 设置任务的子进程（首次启动、暂停后重新启动或流水线下一步）；
+    /// 启动期间任务已被取消时立即终止该进程
+    pub fn replace_child(&self, child: Child) {
+        self.pid.store(child.id(), Ordering::SeqCst);
+        *self.child.lock().unwrap() = Some(child);
+        if self.is_cancelled() {
+            let _ = self.cancel();
+        }
+    }
+
+    /// 暂停任务：优先挂起进程树，不支持时终止进程，恢复时重新启动
+    pub fn pause(&self) -> AppResult<()> {
+        let mut state = self.state.lock().unwrap();
+        if *state != JobState::Running || self.kind != JobKind::Download {
+            return Err(AppError::invalid_state(format!("任务当前不可暂停: {}", self.id)));
+        }
+
+        let suspended = signal_process_tree(self.pid(), Signal::Stop);
+        if !suspended {
+            // 部分进程可能已被挂起，先恢复再终止，避免残留
+            signal_process_tree(self.pid(), Signal::Continue);
+            kill_process_tree(self.pid());
+        }
+        self.suspended.store(suspended, Ordering::SeqCst);
+        *state = JobState::Paused;
+        Ok(())
+    }
+
+    /// 恢复任务：继续被挂起的进程，或唤醒监督线程重新启动进程
+    pub fn resume(&self) -> AppResult<()> {
+        let mut state = self.state.lock().unwrap();
+        if *state != JobState::Paused {
+            return Err(AppError::invalid_state(format!("任务未处于暂停状态: {}", self.id)));
+        }
+
+        if self.suspended.load(Ordering::SeqCst) && !signal_process_tree(self.pid(), Signal::Continue) {
+            return Err(AppError::internal(format!("恢复进程失败: {}", self.pid())));
+        }
+        *state = JobState::Running;
+        self.state_changed.notify_all();
+        Ok(())
+    }
+
+    /// 取消任务：终止整个进程树
+    pub fn cancel(&self) -> AppResult<()> {
+        let mut state = self.state.lock().unwrap();
+        *state = JobState::Cancelling;
+        self.state_changed.notify_all();
+        drop(state);
+
+        let mut guard = self.child.lock().unwrap();
+        let child = match guard.as_mut() {
+            Some(child) => child,
+            None => return Ok(()), // 进程尚未启动（或处于流水线步骤之间），没有需要终止的进程
+        };
+        kill_process_tree(self.pid());
+
+        // 兜底：直接终止根进程（进程已退出时忽略错误）
+        if child.try_wait().map_err(|e| AppError::io("查询进程状态失败", &e))?.is_none() {
+            child.kill().map_err(|e| AppError::io("终止进程失败", &e))?;
+        }
+        Ok(())
+    }
+
+    /// 当前阶段
+    pub fn phase(&self) -> DownloadPhase {
+        self.phase.lock().unwrap().current()
+    }
+
+    /// 推进阶段，不允许的转换返回 None
+    pub fn advance_phase(&self, to: DownloadPhase) -> Option<PhaseChange> {
+        self.phase.lock().unwrap().advance(to)
+    }
+
+    pub fn info(&self) -> JobInfo {
+        JobInfo {
+            id: self.id.clone(),
+            kind: self.kind,
+            tool: self.tool.clone(),
+            pid: self.pid(),
+            state: self.state(),
+            phase: self.phase(),
+            started_at: self.started_at.clone(),
+        }
+    }
+}
+
+/// 生成新的任务 ID
+pub fn next_job_id() -> String {
+    let seq = JOB_SEQ.fetch_add(1, Ordering::SeqCst);
+    format!("{}-{}", Local::now().format("%Y%m%d%H%M%S%3f"), seq)
+}
+
+/// 登记任务，返回任务句柄；子进程启动后通过 `replace_child` 设置
+pub fn register(job_id: String, kind: JobKind, spec: ProcessSpec) -> AppResult<Arc<Job>> {
+    let mut jobs = JOBS.lock().unwrap();
+    if jobs.contains_key(&job_id) {
+        return Err(AppError::invalid_state(format!("任务仍在运行: {}", job_id)));
+    }
+    let job = Arc::new(Job::new(job_id, kind, spec));
+    jobs.insert(job.id.clone(), Arc::clone(&job));
+    Ok(job)
+}
+
+/// 正在运行的任务的启动参数
+pub fn running_args() -> Vec<Vec<String>> {
+    JOBS.lock().unwrap().values().map(|job| job.spec.args.clone()).collect()
+}
+
+/// 任务结束后从任务表移除
+pub fn remove(job_id: &str) {
+    JOBS.lock().unwrap().remove(job_id);
+}
+
+fn find(job_id: &str) -> AppResult<Arc<Job>> {
+    JOBS.lock().unwrap().get(job_id).cloned()
+        .ok_or_else(|| AppError::job_not_found(job_id))
+}
+
+/// 收集进程树（根进程在前，子进程在后）
+fn collect_process_tree(sys: &System, root_pid: u32) -> Vec<Pid> {
+    let mut tree = vec![Pid::from_u32(root_pid)];
+    let mut i = 0;
+    while i < tree.len() {
+        let parent = tree[i];
+        for (pid, process) in sys.processes() {
+            if process.parent() == Some(parent) && !tree.contains(pid) {
+                tree.push(*pid);
+            }
+        }
+        i += 1;
+    }
+    tree
+}
+
+/// 终止进程及其全部子进程（先子后父）
+fn kill_process_tree(root_pid: u32) {
+    let mut sys = System::new();
+    sys.refresh_processes();
+
+    for pid in collect_process_tree(&sys, root_pid).iter().rev() {
+        if let Some(process) = sys.process(*pid) {
+            if !process.kill() {
+                log::warn!("终止进程失败: {}", pid);
+            }
+        }
+    }
+}
+
+/// 向进程树发送信号；平台不支持该信号或发送失败时返回 false
+fn signal_process_tree(root_pid: u32, signal: Signal) -> bool {
+    let mut sys = System::new();
+    sys.refresh_processes();
+
+    let mut ok = true;
+    for pid in collect_process_tree(&sys, root_pid) {
+        if let Some(process) = sys.process(pid) {
+            ok &= process.kill_with(signal).unwrap_or(false);
+        }
+    }
+    ok
+}
+
+/// 通知所有窗口任务状态变化
+fn emit_job_state(app: &tauri::AppHandle, job: &Job) {
+    let _ = app.emit("job-state", JobStateEvent {
+        job_id: job.id.clone(),
+        state: job.state(),
+        timestamp: chrono::Utc::now().to_rfc3339(),
+    });
+}
+
+// ==================== 任务管理命令 ====================
+
+/// 取消正在运行的任务
+#[command]
+pub async fn cancel_job(app: tauri::AppHandle, job_id: String) -> AppResult<()> {
+    let job = find(&job_id)?;
+    log::info!("取消任务: {} (pid {})", job.id, job.pid());
+    job.cancel()?;
+    emit_job_state(&app, &job);
+    Ok(())
+}
+
+/// 暂停正在运行的任务
+#[command]
+pub async fn pause_job(app: tauri::AppHandle, job_id: String) -> AppResult<()> {
+    let job = find(&job_id)?;
+    log::info!("暂停任务: {} (pid {})", job.id, job.pid());
+    job.pause()?;
+    job_store::set_phase(&job.id, StoredPhase::Paused);
+    emit_job_state(&app, &job);
+    if let Some(change) = job.advance_phase(DownloadPhase::Paused) {
+        phase::emit_change(&app, &job.id, change);
+    }
+    Ok(())
+}
+
+/// 恢复已暂停的任务
+#[command]
+pub async fn resume_job(app: tauri::AppHandle, job_id: String) -> AppResult<()> {
+    let job = find(&job_id)?;
+    log::info!("恢复任务: {}", job.id);
+    job.resume()?;
+    job_store::set_phase(&job.id, StoredPhase::Running);
+    emit_job_state(&app, &job);
+    let resumed = job.phase.lock().unwrap().resume();
+    if let Some(change) = resumed {
+        phase::emit_change(&app, &job.id, change);
+    }
+    Ok(())
+}
+
+/// 列出正在运行的任务
+#[command]
+pub async fn list_jobs() -> AppResult<Vec<JobInfo>> {
+    let mut jobs: Vec<JobInfo> = JOBS.lock().unwrap().values().map(|job| job.info()).collect();
+    jobs.sort_by(|a, b| a.started_at.cmp(&b.started_at));
+    Ok(jobs)
+}
@@ -1,9 +1,9 @@
-use std::process::{Command, Stdio};
-use std::io::{BufRead, BufReader};
+use std::process::Command;
 use std::path::{Path, PathBuf};
 use sha2::{Sha256, Digest};
 use tauri::{Manager, Emitter, menu::{MenuBuilder, MenuItemBuilder}, tray::{TrayIconBuilder, TrayIconEvent}};
 use tauri::path::BaseDirectory;
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogResult};
 use serde::{Serialize, Deserialize};
 use once_cell::sync::Lazy;
 use std::collections::HashMap;
@@ -12,6 +12,26 @@ use std::sync::{Arc, Mutex};
 // 引入工具模块
 mod utils;
 mod logger;
+mod jobs;
+mod queue;
+mod job_store;
+mod progress;
+mod phase;
+mod postprocess;
+mod ffmpeg;
+mod runner;
+mod retry;
+mod error;
+mod download_request;
+mod arg_schema;
+mod path_policy;
+mod intermediates;
+mod history;
+mod settings;
+mod profile;
+mod redact;
+
+use error::{AppError, AppResult, ErrorCode};
 
 // ==================== 数据结构定义 ====================
 
@@ -19,10 +39,22 @@ mod logger;
 #[derive(Serialize, Clone)]
 struct LogEvent {
     level: String,
+    #[serde(serialize_with = "redact::serialize")]
     message: String,
     progress: Option<f64>,
     speed: Option<String>,
     timestamp: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    job_id: Option<String>,
+    /// ffmpeg 处理帧率
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fps: Option<f64>,
+    /// 预计剩余时间（秒）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    eta_seconds: Option<u64>,
+    /// 错误日志对应的错误码
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error_code: Option<ErrorCode>,
 }
 
 #[derive(Deserialize, Clone)]
@@ -36,102 +68,29 @@ struct SubtitleStyle {
     back_colour: Option<String>,
 }
 
-/// N_m3u8DL-RE 允许的参数白名单
-const ALLOWED_ARGS: &[&str] = &[
-    // 基础参数
-    "--save-dir", "--save-name", "--thread-count", "--auto-select",
-    "--no-log", "--max-speed", "--live-record-limit",
-    
-    // 内容选择参数
-    "--select-video", "--select-audio", "--select-subtitle",
-    "--drop-video", "--drop-audio", "--drop-subtitle",
-    
-    // 解密相关参数
-    "--key", "--custom-hls-method", "--decryption-key",
-    "--decryption-engine", "--decryption-binary-path", "--mp4-real-time-decryption",
-    
-    // 混流相关参数
-    "--mux-after-done", "--binary-merge",
-    
-    // HTTP 相关参数
-    "-H", "--http-header", "--user-agent", "--referer",
-    
-    // 高级参数
-    "--custom-range", "--date-range", "--append-user-agent",
-    "--log-level", "--tmp-dir", "--del-after-done",
-    
-    // 重试和超时参数
-    "--retry-count", "--download-retry-delay", "--auto-subtitle-fix",
-    
-    // 显示和输出控制参数
-    "--no-ansi-color", "--no-date-info", "--no-log-color",
-    "--use-shaka-packager", "--check-segments-count",
-];
-
 // 工具路径缓存
 static TOOL_PATHS: Lazy<Mutex<HashMap<String, PathBuf>>> = Lazy::new(|| Mutex::new(HashMap::new()));
 
 // ==================== 安全验证函数 ====================
 
 /// 验证路径安全性，防止路径遍历攻击
-fn validate_path_safety(path: &str) -> Result<(), String> {
+fn validate_path_safety(path: &str) -> AppResult<()> {
     if path.contains("..") {
-        return Err("路径包含不安全的父目录引用".to_string());
+        return Err(AppError::unsafe_path("路径包含不安全的父目录引用"));
     }
     
     // Windows 路径分隔符检查
     #[cfg(windows)]
     if path.contains("/") && !path.starts_with("http") {
-        return Err("路径包含不安全的分隔符".to_string());
+        return Err(AppError::unsafe_path("路径包含不安全的分隔符"));
     }
     
     Ok(())
 }
 
-/// 验证 N_m3u8DL-RE 命令参数
-fn validate_n_m3u8dl_args(args: &[String]) -> Result<(), String> {
-    let mut i = 0;
-    while i < args.len() {
-        let arg = &args[i];
-        
-        // 跳过 URL 参数（第一个参数通常是 URL）
-        if i == 0 && (arg.starts_with("http://") || arg.starts_with("https://")) {
-            i += 1;
-            continue;
-        }
-        
-        // 检查参数是否在白名单中
-        if arg.starts_with("--") || arg.starts_with("-") {
-            let arg_name = if arg.starts_with("--") {
-                arg.split('=').next().unwrap_or(arg)
-            } else {
-                arg
-            };
-            
-            if !ALLOWED_ARGS.contains(&arg_name) {
-                return Err(format!("不允许的参数: {}", arg_name));
-            }
-            
-            // 检查路径参数的安全性
-            if (arg_name == "--save-dir" || arg_name == "--save-name" || arg_name == "--tmp-dir") 
-                && i + 1 < args.len() {
-                let value = &args[i + 1];
-                validate_path_safety(value)?;
-            }
-            
-            // 检查密钥参数格式
-            if arg_name == "--key" && i + 1 < args.len() {
-                let key_value = &args[i + 1];
-                // 密钥格式: KID:KEY 或 0x开头的十六进制
-                if !key_value.contains(':') && !key_value.starts_with("0x") {
-                    return Err("密钥格式不正确，应为 KID:KEY 或 0x 开头的十六进制".to_string());
-                }
-            }
-        }
-        
-        i += 1;
-    }
-    Ok(())
+/// 验证 N_m3u8DL-RE 命令参数（参数名、取值类型与范围见 `arg_schema`）
+fn validate_n_m3u8dl_args(args: &[String]) -> AppResult<()> {
+    arg_schema::validate(args)
 }
 
 /// 验证工具路径
@@ -144,7 +103,7 @@ fn validate_tool_path(path: &Path) -> bool {
 // ==================== 日志解析函数 ====================
 
 /// 解析 N_m3u8DL-RE 日志行
-fn parse_n_m3u8dl_log_line(line: &str, last_progress: &Arc<Mutex<i32>>) -> Option<LogEvent> {
+fn parse_n_m3u8dl_log_line(line: &str) -> Option<LogEvent> {
     let timestamp = chrono::Utc::now().to_rfc3339();
     let trimmed = line.trim();
     
@@ -163,21 +122,28 @@ fn parse_n_m3u8dl_log_line(line: &str, last_progress: &Arc<Mutex<i32>>) -> Optio
         return None;
     }
     
-    // 跳过音频流进度（Aud xxx）
+    // 跳过音频流信息（Aud xxx，进度行已由 progress 模块处理）
     if trimmed.starts_with("Aud ") {
         return None;
     }
     
-    // 错误信息（最高优先级）
+    // 错误信息（最高优先级）；已知的失败原因（磁盘已满、403 等）即使以警告输出也按错误上报，
+    // 网络类暂时性错误仍以警告输出时由下载器自行重试，不上报
+    let error_code = error::classify_line(trimmed);
     if trimmed.contains("ERROR") || trimmed.contains("Error") || 
        trimmed.contains("错误") || trimmed.contains("失败") ||
-       trimmed.contains("Permission denied") || trimmed.contains("exception") {
+       trimmed.contains("Permission denied") || trimmed.contains("exception") ||
+       error_code.is_some_and(|code| !code.is_transient()) {
         return Some(LogEvent {
             level: "ERROR".to_string(),
             message: trimmed.to_string(),
             progress: None,
             speed: None,
             timestamp,
+            job_id: None,
+            fps: None,
+            eta_seconds: None,
+            error_code,
         });
     }
     
@@ -186,27 +152,8 @@ fn parse_n_m3u8dl_log_line(line: &str, last_progress: &Arc<Mutex<i32>>) -> Optio
         return None;
     }
     
-    // 解析视频流进度（Vid 1920x1080 | 4300 Kbps）
-    if trimmed.starts_with("Vid ") && trimmed.contains("%") {
-        if let Some(progress) = extract_progress_from_line(trimmed) {
-            // 为了减少日志频率，我们只在进度跨越 5% 边界或达到 100% 时记录日志
-            let progress_int = progress.floor() as i32;
-            let mut last_progress_guard = last_progress.lock().unwrap();
-
-            if progress_int > *last_progress_guard {
-                if (progress_int / 5 > *last_progress_guard / 5) || progress_int == 100 {
-                    *last_progress_guard = progress_int;
-                    let speed = extract_speed_from_line(trimmed);
-                    return Some(LogEvent {
-                        level: "INFO".to_string(),
-                        message: format!("下载进度: {:.0}%", progress),
-                        progress: Some(progress),
-                        speed: if !speed.is_empty() { Some(speed) } else { None },
-                        timestamp,
-                    });
-                }
-            }
-        }
+    // 流进度行由 progress::parse_progress_line 处理
+    if trimmed.starts_with("Vid ") && trimmed.contains('%') {
         return None;
     }
     
@@ -219,6 +166,10 @@ fn parse_n_m3u8dl_log_line(line: &str, last_progress: &Arc<Mutex<i32>>) -> Optio
                 progress: None,
                 speed: None,
                 timestamp,
+                job_id: None,
+                fps: None,
+                eta_seconds: None,
+                error_code: None,
             });
         } else if trimmed.contains("Start downloading") {
             return Some(LogEvent {
@@ -227,6 +178,10 @@ fn parse_n_m3u8dl_log_line(line: &str, last_progress: &Arc<Mutex<i32>>) -> Optio
                 progress: Some(0.0),
                 speed: None,
                 timestamp,
+                job_id: None,
+                fps: None,
+                eta_seconds: None,
+                error_code: None,
             });
         } else if trimmed.contains("Binary merging") {
             return Some(LogEvent {
@@ -235,6 +190,10 @@ fn parse_n_m3u8dl_log_line(line: &str, last_progress: &Arc<Mutex<i32>>) -> Optio
                 progress: None,
                 speed: None,
                 timestamp,
+                job_id: None,
+                fps: None,
+                eta_seconds: None,
+                error_code: None,
             });
         } else if trimmed.contains("Decrypting") {
             return Some(LogEvent {
@@ -243,6 +202,10 @@ fn parse_n_m3u8dl_log_line(line: &str, last_progress: &Arc<Mutex<i32>>) -> Optio
                 progress: None,
                 speed: None,
                 timestamp,
+                job_id: None,
+                fps: None,
+                eta_seconds: None,
+                error_code: None,
             });
         } else if trimmed.contains("Done") || trimmed.contains("完成") {
             return Some(LogEvent {
@@ -251,6 +214,10 @@ fn parse_n_m3u8dl_log_line(line: &str, last_progress: &Arc<Mutex<i32>>) -> Optio
                 progress: Some(100.0),
                 speed: None,
                 timestamp,
+                job_id: None,
+                fps: None,
+                eta_seconds: None,
+                error_code: None,
             });
         }
     }
@@ -258,21 +225,6 @@ fn parse_n_m3u8dl_log_line(line: &str, last_progress: &Arc<Mutex<i32>>) -> Optio
     None
 }
 
-/// 从日志行提取进度
-fn extract_progress_from_line(line: &str) -> Option<f64> {
-    // 尝试匹配百分比
-    if let Some(percent_idx) = line.find('%') {
-        let before_percent = &line[..percent_idx];
-        // 从后往前找数字
-        if let Some(num_start) = before_percent.rfind(|c: char| !c.is_numeric() && c != '.') {
-            if let Ok(progress) = before_percent[num_start + 1..].trim().parse::<f64>() {
-                return Some(progress);
-            }
-        }
-    }
-    None
-}
-
 /// 从日志行提取速度
 fn extract_speed_from_line(line: &str) -> String {
     // 匹配速度格式：1.97MBps, 19.20MBps, 7.81MBps 等
@@ -309,6 +261,15 @@ pub fn run() {
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_log::Builder::new()
             .level(log::LevelFilter::Info)
+            .format(|out, message, record| {
+                out.finish(format_args!(
+                    "{}[{}][{}] {}",
+                    chrono::Local::now().format("[%Y-%m-%d][%H:%M:%S]"),
+                    record.level(),
+                    record.target(),
+                    redact::redact(&message.to_string())
+                ))
+            })
             .build())
         .setup(|app| {
             let window = app.get_webview_window("main").unwrap();
@@ -321,6 +282,39 @@ pub fn run() {
                 }
             }
             
+            // 初始化允许访问的目录
+            if let Ok(app_data_dir) = app.path().app_data_dir() {
+                path_policy::init(app_data_dir);
+            }
+            
+            // 加载配置（依赖目录授权），各子系统直接读取
+            if let Ok(app_data_dir) = app.path().app_data_dir() {
+                if let Err(e) = settings::init(app_data_dir) {
+                    eprintln!("加载配置失败: {}", e);
+                }
+            }
+            
+            // 加载任务中间文件记录，清理过期的回收站条目
+            if let Ok(app_data_dir) = app.path().app_data_dir() {
+                intermediates::init(app_data_dir);
+            }
+            
+            // 加载下载历史
+            if let Ok(app_data_dir) = app.path().app_data_dir() {
+                if let Err(e) = history::init(app_data_dir) {
+                    eprintln!("加载下载历史失败: {}", e);
+                }
+            }
+            
+            // 初始化任务存储，检查上次运行遗留的未完成任务
+            if let Ok(app_data_dir) = app.path().app_data_dir() {
+                match job_store::init(app_data_dir) {
+                    Ok(report) if !report.is_empty() => offer_job_recovery(app.handle(), &report),
+                    Ok(_) => {}
+                    Err(e) => eprintln!("初始化任务存储失败: {}", e),
+                }
+            }
+            
             // 设置窗口图标和标题
             window.set_title("GAGA Client").unwrap();
             
@@ -386,11 +380,82 @@ pub fn run() {
             utils::create_dir,
             list_log_files,
             read_log_file,
+            read_log_records,
+            read_job_log,
             cleanup_old_logs,
-            delete_files
+            delete_files,
+            jobs::cancel_job,
+            jobs::pause_job,
+            jobs::resume_job,
+            jobs::list_jobs,
+            queue::enqueue_download,
+            queue::remove_from_queue,
+            queue::move_queue_item,
+            queue::set_queue_priority,
+            queue::get_queue_state,
+            job_store::list_recoverable_jobs,
+            job_store::resume_recovered_jobs,
+            job_store::discard_recovered_jobs,
+            postprocess::post_process_job,
+            download_request::start_download,
+            path_policy::pick_save_dir,
+            path_policy::allow_save_dir,
+            intermediates::empty_trash,
+            history::add_history_record,
+            history::update_history_record,
+            history::query_history,
+            history::delete_history_records,
+            history::clear_history,
+            history::history_stats,
+            settings::get_settings,
+            settings::update_settings,
+            settings::reset_settings,
+            settings::pick_tool_path,
+            settings::clear_tool_path,
+            profile::export_profile,
+            profile::import_profile
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|_app, event| {
+            // 退出前写完缓冲中的日志
+            if let tauri::RunEvent::Exit = event {
+                logger::shutdown();
+            }
+        });
+}
+
+/// 启动时提示用户恢复或清理上次未完成的任务
+fn offer_job_recovery(app: &tauri::AppHandle, report: &job_store::RecoveryReport) {
+  let message = format!(
+    "检测到上次运行中断的 {} 个下载任务，以及 {} 个未关联的临时目录。\n是否恢复下载？",
+    report.jobs.len(),
+    report.orphan_temp_dirs.len()
+  );
+  let _ = logger::write_log("INFO", &message, None);
+
+  let handle = app.clone();
+  app.dialog()
+    .message(message)
+    .title("恢复下载任务")
+    .buttons(MessageDialogButtons::YesNoCancelCustom(
+      "恢复".to_string(), "清理".to_string(), "稍后处理".to_string()))
+    .show_with_result(move |result| match result {
+      MessageDialogResult::Yes => {
+        job_store::resume_interrupted(&handle, None);
+      }
+      MessageDialogResult::Custom(label) if label == "恢复" => {
+        job_store::resume_interrupted(&handle, None);
+      }
+      MessageDialogResult::No => {
+        job_store::discard_interrupted(None, true);
+      }
+      MessageDialogResult::Custom(label) if label == "清理" => {
+        job_store::discard_interrupted(None, true);
+      }
+      // 稍后处理：前端可通过 list_recoverable_jobs 查看并处理
+      _ => {}
+    });
 }
 
 /// 获取工具路径（内部函数，带缓存）
@@ -441,35 +506,47 @@ fn get_tool_path_internal(tool_name: &str) -> PathBuf {
 
 /// 获取工具路径（Tauri 命令）
 #[tauri::command]
-async fn get_tool_path(tool_name: String) -> Result<String, String> {
+async fn get_tool_path(tool_name: String) -> AppResult<String> {
   let path = get_tool_path_internal(&tool_name);
   Ok(path.to_string_lossy().to_string())
 }
 
 /// 执行下载命令（N_m3u8DL-RE）- 带参数验证和实时日志
+///
+/// 任务加入下载队列（受 maxConcurrentDownloads 限制）后立即返回任务 ID，
+/// 后续进度与结果通过 `n-m3u8dl-log` 事件推送，可通过 `cancel_job` / `pause_job` / `resume_job` 控制。
 #[tauri::command]
 async fn exec_download_command(
   window: tauri::Window,
   _command: String,
   args: Vec<String>,
   working_dir: Option<String>,
-) -> Result<String, String> {
+) -> AppResult<String> {
   // 参数验证
   if args.is_empty() {
-    return Err("参数不能为空".to_string());
+    return Err(AppError::invalid_argument("参数不能为空"));
   }
   
   // 验证参数安全性
   validate_n_m3u8dl_args(&args)?;
-  
-  let tool_path = resolve_tool_path(&window.app_handle(), "N_m3u8DL-RE");
+
+  Ok(queue::enqueue(window.app_handle(), args, working_dir, 0))
+}
+
+/// 启动 N_m3u8DL-RE 下载任务并在后台监督，返回任务 ID（由下载队列调用，新任务需通过 `queue::enqueue` 加入队列）
+fn start_download_job(
+  app: tauri::AppHandle,
+  job_id: String,
+  args: Vec<String>,
+  working_dir: Option<String>,
+) -> AppResult<String> {
+  let tool_path = resolve_tool_path(&app, "N_m3u8DL-RE");
 
   // 检查工具是否存在
   if !tool_path.exists() {
-    return Err(format!("工具不存在: {:?}", tool_path));
+    return Err(AppError::new(ErrorCode::ToolMissing, format!("工具不存在: {:?}", tool_path)));
   }
 
-  // 使用同步 Command 在后台线程中执行，避免阻塞
   let tool_path_str = tool_path.to_string_lossy().to_string();
 
   // 日志：打印命令和参数
@@ -477,150 +554,329 @@ async fn exec_download_command(
   log::info!("参数数量: {}", args.len());
   
   // 写入日志文件
-  let _ = logger::write_tool_log("N_m3u8DL-RE", "INFO", 
+  let _ = logger::write_job_log("N_m3u8DL-RE", "INFO", &job_id, None,
       &format!("开始执行下载命令，参数数量: {}", args.len()));
   
-  for (i, arg) in args.iter().enumerate() {
-    if arg.starts_with("--key") && i + 1 < args.len() {
-      log::debug!("密钥参数: {} [已隐藏]", arg);
+  // 密钥、请求头中的 Cookie 等敏感参数只记录隐藏后的值
+  for (i, arg) in redact::redact_args(&args).iter().enumerate() {
+    log::debug!("参数[{}]: {}", i, arg);
+    let _ = logger::write_job_log("N_m3u8DL-RE", "DEBUG", &job_id, None,
+        &format!("参数[{}]: {}", i, arg));
+  }
+
+  // 持久化任务信息，崩溃或退出后可恢复
+  job_store::record(&job_id, &args, working_dir.as_deref(), 0, job_store::StoredPhase::Running);
+  if let Some(tmp_dir) = job_store::arg_value(&args, "--tmp-dir") {
+    intermediates::record_temp_dir(&job_id, Path::new(&tmp_dir));
+  }
+
+  // 登记任务，保存子进程句柄以便取消和暂停
+  let spec = runner::ProcessSpec {
+    tool: "N_m3u8DL-RE".to_string(),
+    program: tool_path_str,
+    args,
+    working_dir,
+    timeouts: runner::default_timeouts(),
+  };
+  let job = jobs::register(job_id.clone(), jobs::JobKind::Download, spec)
+    .inspect_err(|_| job_store::remove(&job_id))?;
+  let capture = match spawn_download_process(&app, &job) {
+    Ok(capture) => capture,
+    Err(e) => {
+      jobs::remove(&job_id);
+      job_store::remove(&job_id);
+      return Err(e.into());
+    }
+  };
+  let _ = logger::write_job_log("N_m3u8DL-RE", "INFO", &job.id, Some(job.phase()),
+      &format!("任务已启动: {} (pid {})", job.id, job.pid()));
+  history::job_started(&app, &job.id, &job.spec.args);
+  if let Some(change) = job.advance_phase(phase::DownloadPhase::Loading) {
+    phase::emit_change(&app, &job.id, change);
+  }
+
+  // 在后台线程中等待进程结束并发送最终事件
+  std::thread::spawn(move || supervise_download_job(app, job, capture));
+
+  Ok(job_id)
+}
+
+/// 发送任务失败事件（用于进程未能启动的情况）
+fn emit_job_failed(app: &tauri::AppHandle, job_id: &str, error: &AppError) {
+  let _ = logger::write_job_log("N_m3u8DL-RE", "ERROR", job_id, None, &error.message);
+  let _ = app.emit("n-m3u8dl-log", LogEvent {
+    level: "ERROR".to_string(),
+    message: error.message.clone(),
+    progress: None,
+    speed: None,
+    timestamp: chrono::Utc::now().to_rfc3339(),
+    job_id: Some(job_id.to_string()),
+    fps: None,
+    eta_seconds: None,
+    error_code: Some(error.code),
+  });
+  history::job_finished(app, job_id, history::HistoryStatus::Failed, Some(&error.message), Some(error.code));
+}
+
+/// 按任务信息启动下载进程并交给任务表管理
+fn spawn_download_process(app: &tauri::AppHandle, job: &Arc<jobs::Job>) -> Result<runner::OutputCapture, runner::SpawnError> {
+  let parser = Arc::new(DownloadParser {
+    app: app.clone(),
+    job: Arc::clone(job),
+    tracker: Mutex::new(progress::ProgressTracker::new()),
+    last_message: Mutex::new(String::new()),
+  });
+  let channel = runner::EventChannel::new(app, "n-m3u8dl-log");
+  let (child, capture) = runner::spawn(&job.spec, parser, Some(channel))?;
+  job.replace_child(child);
+  Ok(capture)
+}
+
+/// N_m3u8DL-RE 输出解析策略：流进度按频率限制发送，关键日志去重后发送
+struct DownloadParser {
+  app: tauri::AppHandle,
+  job: Arc<jobs::Job>,
+  tracker: Mutex<progress::ProgressTracker>,
+  last_message: Mutex<String>,
+}
+
+impl runner::LineParser for DownloadParser {
+  fn parse(&self, _stream: runner::OutputStream, line: &str) -> Option<LogEvent> {
+    let (app, job) = (&self.app, &self.job);
+
+    // 由输出推动阶段前进（进度行意味着已开始下载）
+    let parsed_progress = progress::parse_progress_line(line);
+    let next_phase = if parsed_progress.is_some() {
+      Some(phase::DownloadPhase::Downloading)
     } else {
-      log::debug!("参数[{}]: {}", i, arg);
-      let _ = logger::write_tool_log("N_m3u8DL-RE", "DEBUG", 
-          &format!("参数[{}]: {}", i, arg));
+      phase::phase_for_line(line)
+    };
+    if let Some(change) = next_phase.and_then(|p| job.advance_phase(p)) {
+      phase::emit_change(app, &job.id, change);
     }
+
+    if let Some(mut stream_progress) = parsed_progress {
+      let decision = self.tracker.lock().unwrap().observe(&stream_progress);
+      stream_progress.job_id = Some(job.id.clone());
+
+      let mut legacy_event = None;
+      if decision.log {
+        let message = format!("{}下载进度: {:.0}%", stream_progress.stream.label(), stream_progress.percent);
+        let _ = logger::LogRecord::new("INFO", &message)
+            .tool("N_m3u8DL-RE")
+            .job(&job.id)
+            .phase(job.phase())
+            .field("stream", stream_progress.stream.label())
+            .field("percent", stream_progress.percent)
+            .write();
+
+        // 兼容按日志事件展示进度的前端：视频流进度同时发送日志事件
+        if stream_progress.stream == progress::StreamKind::Video {
+          let speed = extract_speed_from_line(line);
+          legacy_event = Some(LogEvent {
+            level: "INFO".to_string(),
+            message: format!("下载进度: {:.0}%", stream_progress.percent),
+            progress: Some(stream_progress.percent),
+            speed: if !speed.is_empty() { Some(speed) } else { None },
+            timestamp: stream_progress.timestamp.clone(),
+            job_id: Some(job.id.clone()),
+            fps: None,
+            eta_seconds: None,
+            error_code: None,
+          });
+        }
+      }
+      if decision.emit {
+        let _ = app.emit("download-progress", stream_progress);
+      }
+      return legacy_event;
+    }
+
+    let mut log_event = parse_n_m3u8dl_log_line(line)?;
+    let key = format!("{}:{:?}", log_event.message, log_event.progress);
+    let mut lm = self.last_message.lock().unwrap();
+    if *lm == key {
+      return None;
+    }
+    *lm = key;
+    let _ = logger::write_job_log("N_m3u8DL-RE", &log_event.level, &job.id, Some(job.phase()), &log_event.message);
+    log_event.job_id = Some(job.id.clone());
+    Some(log_event)
   }
 
-  let last_progress = Arc::new(Mutex::new(-1));
-  let last_message = Arc::new(Mutex::new(String::new()));
-  let window_clone = window.clone();
-  let working_dir_clone = working_dir.clone();
-  let (output, stderr_output, status) = tokio::task::spawn_blocking(move || {
-    let mut cmd = Command::new(&tool_path_str);
-    
-    if let Some(dir) = working_dir_clone {
-      cmd.current_dir(dir);
+  /// 进度行按各条流已下载的字节数（或分片数）判断是否仍在下载
+  fn activity(&self, _stream: runner::OutputStream, line: &str) -> runner::Activity {
+    match progress::parse_progress_line(line) {
+      Some(progress) => runner::Activity::Progress(progress.stream.label(), progress.advance_mark()),
+      None => runner::Activity::Output,
     }
+  }
+}
+
+/// 监督下载任务直到结束：处理暂停后的重新启动，并发送最终事件
+fn supervise_download_job(app: tauri::AppHandle, job: Arc<jobs::Job>, capture: runner::OutputCapture) {
+  let policy = retry::policy();
+  let mut retries = 0;
+  let mut capture = capture;
+  let outcome = loop {
+    // 等待进程退出和输出读取完成
+    let mut watchdog = capture.watchdog();
+    let outcome = match job.wait(&mut watchdog) {
+      Ok(status) => Ok(capture.finish(status)),
+      Err(e) => {
+        capture.discard();
+        Err(e)
+      }
+    };
 
-    // 添加参数
-    for arg in args {
-      cmd.arg(arg);
+    if job.needs_relaunch(outcome.as_ref().ok().map(|o| o.status)) {
+      // 暂停时进程已被终止：等待恢复后使用相同的临时目录和保存名称重新启动，
+      // N_m3u8DL-RE 会跳过临时目录中已下载的分片
+      if !job.wait_for_resume() {
+        break outcome;
+      }
+      log::info!("重新启动已暂停的任务: {}", job.id);
+      let _ = logger::write_job_log("N_m3u8DL-RE", "INFO", &job.id, Some(job.phase()), &format!("恢复下载任务: {}", job.id));
+    } else {
+      // 暂时性失败（网络错误、超时）：退避后以相同参数重试，同样复用临时目录中的分片
+      let reason = if job.is_cancelled() || retries >= policy.max_retries {
+        None
+      } else {
+        retry::transient_reason(&outcome)
+      };
+      let reason = match reason {
+        Some(reason) => reason,
+        None => break outcome,
+      };
+      retries += 1;
+      let delay = policy.delay(retries);
+      let message = format!("下载失败（{}），{} 秒后进行第 {}/{} 次重试",
+          reason, delay.as_secs(), retries, policy.max_retries);
+      log::warn!("{}: {}", job.id, message);
+      let _ = logger::write_job_log("N_m3u8DL-RE", "WARN", &job.id, Some(job.phase()), &message);
+      let _ = app.emit("n-m3u8dl-log", LogEvent {
+        level: "WARN".to_string(),
+        message,
+        progress: None,
+        speed: None,
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        job_id: Some(job.id.clone()),
+        fps: None,
+        eta_seconds: None,
+        error_code: None,
+      });
+      retry::emit_retry(&app, &job.id, retries + 1, &policy, delay, &reason);
+
+      // 等待期间可被取消或暂停
+      if !job.sleep_unless_cancelled(delay) || !job.wait_for_resume() {
+        break outcome;
+      }
     }
 
-    // 设置输出
-    cmd.stdout(Stdio::piped());
-    cmd.stderr(Stdio::piped());
-
-    // 执行命令
-    let mut child = cmd.spawn().map_err(|e| {
-      format!("执行命令失败: {}，工具路径: {:?}", e, tool_path_str)
-    })?;
-
-    // 获取stdout和stderr句柄
-    let stdout = child.stdout.take().ok_or("无法获取标准输出")?;
-    let stderr = child.stderr.take();
-
-    // 在后台线程中读取输出，避免阻塞，并实时发送日志事件
-    let window_for_stdout = window_clone.clone();
-    let last_progress_stdout = Arc::clone(&last_progress);
-    let last_message_stdout = Arc::clone(&last_message);
-    let stdout_handle = std::thread::spawn(move || {
-      let reader = BufReader::new(stdout);
-      let mut output = String::new();
-      for line in reader.lines() {
-        if let Ok(line) = line {
-          if let Some(log_event) = parse_n_m3u8dl_log_line(&line, &last_progress_stdout) {
-            let key = format!("{}:{:?}", log_event.message, log_event.progress);
-            let mut lm = last_message_stdout.lock().unwrap();
-            if *lm != key {
-              *lm = key.clone();
-              let _ = logger::write_tool_log("N_m3u8DL-RE", &log_event.level, &log_event.message);
-              let _ = window_for_stdout.emit("n-m3u8dl-log", log_event);
-            }
-          }
-          output.push_str(&line);
-          output.push('\n');
-        }
+    match spawn_download_process(&app, &job) {
+      Ok(new_capture) => capture = new_capture,
+      Err(e) => {
+        log::error!("重新启动任务失败: {}", e);
+        let _ = logger::write_job_log("N_m3u8DL-RE", "ERROR", &job.id, Some(job.phase()), &format!("重新启动任务失败: {}", e));
+        break Err(runner::RunError::Spawn(e));
       }
-      output
-    });
+    }
+  };
+  jobs::remove(&job.id);
+  job_store::remove(&job.id);
 
-    let window_for_stderr = window_clone.clone();
-    let last_progress_stderr = Arc::clone(&last_progress);
-    let last_message_stderr = Arc::clone(&last_message);
-    let stderr_handle = if let Some(stderr) = stderr {
-      Some(std::thread::spawn(move || {
-        let reader = BufReader::new(stderr);
-        let mut stderr_output = String::new();
-        for line in reader.lines() {
-          if let Ok(line) = line {
-            if let Some(log_event) = parse_n_m3u8dl_log_line(&line, &last_progress_stderr) {
-              let key = format!("{}:{:?}", log_event.message, log_event.progress);
-              let mut lm = last_message_stderr.lock().unwrap();
-              if *lm != key {
-                *lm = key.clone();
-                let _ = logger::write_tool_log("N_m3u8DL-RE", &log_event.level, &log_event.message);
-                let _ = window_for_stderr.emit("n-m3u8dl-log", log_event);
-              }
-            }
-            stderr_output.push_str(&line);
-            stderr_output.push('\n');
+  let (level, message, progress, error_code) = if job.is_cancelled() {
+    log::info!("任务已取消: {}", job.id);
+    let _ = logger::write_job_log("N_m3u8DL-RE", "INFO", &job.id, Some(job.phase()), &format!("下载任务已取消: {}", job.id));
+    ("INFO", "下载任务已取消".to_string(), None, Some(ErrorCode::Cancelled))
+  } else {
+    match outcome {
+      Ok(outcome) => match outcome.exit_code {
+        Some(0) => {
+          log::info!("命令执行成功，耗时 {:.1}s", outcome.duration.as_secs_f64());
+          let _ = logger::write_job_log("N_m3u8DL-RE", "INFO", &job.id, Some(job.phase()), "下载任务执行成功");
+          ("INFO", "下载任务完成".to_string(), Some(100.0), None)
+        },
+        Some(code) => {
+          // 按输出中的失败行识别原因（磁盘已满、403、解密失败等）
+          let error = AppError::process_failed("下载任务失败", &outcome);
+          log::error!("命令执行失败，退出码: {} ({:?})", code, error.code);
+          let _ = logger::write_job_log("N_m3u8DL-RE", "ERROR", &job.id, Some(job.phase()), 
+              &format!("下载任务执行失败，退出码: {}", code));
+          ("ERROR", error.message, None, Some(error.code))
+        },
+        None => {
+          // 退出码为 None 通常表示进程被信号终止
+          // 但可能下载已经完成，检查输出中是否有成功信息
+          let output = outcome.stdout_tail.join("\n");
+          if output.contains("完成") || output.contains("100%") || output.contains("Done") {
+            log::info!("命令执行完成（无退出码）");
+            let _ = logger::write_job_log("N_m3u8DL-RE", "INFO", &job.id, Some(job.phase()), "下载任务执行完成（无退出码）");
+            ("INFO", "下载任务完成".to_string(), Some(100.0), None)
+          } else {
+            log::error!("命令被中断，信号: {:?}", outcome.signal);
+            let _ = logger::write_job_log("N_m3u8DL-RE", "ERROR", &job.id, Some(job.phase()), "下载任务被中断");
+            ("ERROR", "下载任务被中断（可能是超时或被终止）".to_string(), None, Some(ErrorCode::ProcessFailed))
           }
         }
-        stderr_output
-      }))
-    } else {
-      None
-    };
+      },
+      Err(runner::RunError::Timeout(kind)) => {
+        log::error!("下载任务超时: {}", kind);
+        let _ = logger::write_job_log("N_m3u8DL-RE", "ERROR", &job.id, Some(job.phase()), &format!("下载任务超时（{}），已终止", kind));
+        ("ERROR", "下载任务超时，已终止".to_string(), None, Some(ErrorCode::Timeout))
+      }
+      Err(e) => {
+        log::error!("等待命令完成失败: {}", e);
+        let _ = logger::write_job_log("N_m3u8DL-RE", "ERROR", &job.id, Some(job.phase()), &format!("等待命令完成失败: {}", e));
+        ("ERROR", "下载任务失败".to_string(), None, Some(AppError::from(e).code))
+      }
+    }
+  };
 
-    // 等待进程完成
-    let status = child.wait().map_err(|e| format!("等待命令完成失败: {}", e))?;
+  let final_phase = if job.is_cancelled() {
+    phase::DownloadPhase::Cancelled
+  } else if level == "ERROR" {
+    phase::DownloadPhase::Failed
+  } else {
+    phase::DownloadPhase::Completed
+  };
 
-    // 等待输出读取完成
-    let output = stdout_handle.join().map_err(|_| "读取标准输出失败")?;
-    let stderr_output = stderr_handle.map(|h| h.join().unwrap_or_default()).unwrap_or_default();
+  // 按任务的保存目录和保存名称记录下载输出，后期处理和清理只处理这些文件
+  if final_phase == phase::DownloadPhase::Completed {
+    let save_dir = job_store::arg_value(&job.spec.args, "--save-dir").or_else(|| job.spec.working_dir.clone());
+    if let (Some(save_dir), Some(save_name)) = (save_dir, job_store::arg_value(&job.spec.args, "--save-name")) {
+      intermediates::record_outputs(&job.id, Path::new(&save_dir), &save_name);
+    }
+  }
+  if let Some(change) = job.advance_phase(final_phase) {
+    phase::emit_change(&app, &job.id, change);
+  }
 
-    Ok::<(String, String, std::process::ExitStatus), String>((output, stderr_output, status))
-  }).await.map_err(|e| format!("执行任务失败: {}", e))??;
+  let history_status = match final_phase {
+    phase::DownloadPhase::Cancelled => history::HistoryStatus::Cancelled,
+    phase::DownloadPhase::Failed => history::HistoryStatus::Failed,
+    _ => history::HistoryStatus::Completed,
+  };
+  let error_message = if level == "ERROR" { Some(message.as_str()) } else { None };
+  history::job_finished(&app, &job.id, history_status, error_message, error_code);
+  logger::close_job_log(&job.id);
 
   // 发送完成事件
   let complete_event = LogEvent {
-    level: if status.success() { "INFO".to_string() } else { "ERROR".to_string() },
-    message: if status.success() { "下载任务完成".to_string() } else { "下载任务失败".to_string() },
-    progress: if status.success() { Some(100.0) } else { None },
+    level: level.to_string(),
+    message,
+    progress,
     speed: None,
     timestamp: chrono::Utc::now().to_rfc3339(),
+    job_id: Some(job.id.clone()),
+    fps: None,
+    eta_seconds: None,
+    error_code,
   };
-  let _ = window.emit("n-m3u8dl-log", complete_event);
-
-  // 检查退出码
-  match status.code() {
-    Some(code) if code == 0 => {
-      log::info!("命令执行成功");
-      let _ = logger::write_tool_log("N_m3u8DL-RE", "INFO", "下载任务执行成功");
-      Ok(output)
-    },
-    Some(code) => {
-      log::error!("命令执行失败，退出码: {}", code);
-      let _ = logger::write_tool_log("N_m3u8DL-RE", "ERROR", 
-          &format!("下载任务执行失败，退出码: {}", code));
-      Err(format!("命令执行失败，退出码: {}\n标准输出: {}\n错误输出: {}",
-        code, output, stderr_output))
-    },
-    None => {
-      // 退出码为 None 通常表示进程被信号终止
-      // 但可能下载已经完成，检查输出中是否有成功信息
-      if output.contains("完成") || output.contains("100%") || output.contains("Done") {
-        log::info!("命令执行完成（无退出码）");
-        let _ = logger::write_tool_log("N_m3u8DL-RE", "INFO", "下载任务执行完成（无退出码）");
-        Ok(output)
-      } else {
-        log::error!("命令被中断");
-        let _ = logger::write_tool_log("N_m3u8DL-RE", "ERROR", "下载任务被中断");
-        Err(format!("命令被中断（可能是超时或被终止）\n标准输出: {}\n错误输出: {}",
-          output, stderr_output))
-      }
-    }
-  }
+  let _ = app.emit("n-m3u8dl-log", complete_event);
+
+  // 释放队列名额
+  queue::on_job_finished(&app, &job.id);
 }
 
 /// 执行混流命令（ffmpeg）
@@ -629,11 +885,12 @@ async fn exec_merge_command(
   app: tauri::AppHandle,
   _command: String,
   args: Vec<String>,
-) -> Result<String, String> {
+) -> AppResult<String> {
   // 参数验证
   if args.is_empty() {
-    return Err("参数不能为空".to_string());
+    return Err(AppError::invalid_argument("参数不能为空"));
   }
+  check_ffmpeg_paths(&args)?;
   
   // ffmpeg 通常需要系统安装，先尝试资源目录，再尝试系统
   let tool_path = resolve_tool_path(&app, "ffmpeg");
@@ -651,96 +908,49 @@ async fn exec_merge_command(
   let _ = logger::write_tool_log("ffmpeg", "INFO", 
       &format!("开始执行混流命令，参数数量: {}", args.len()));
   
-  for (i, arg) in args.iter().enumerate() {
+  for (i, arg) in redact::redact_args(&args).iter().enumerate() {
     log::debug!("参数[{}]: {}", i, arg);
     let _ = logger::write_tool_log("ffmpeg", "DEBUG", 
         &format!("参数[{}]: {}", i, arg));
   }
 
-  let (output, stderr_output, status) = tokio::task::spawn_blocking(move || {
-    let mut cmd = Command::new(&tool_path_str);
-
-    for arg in args {
-      cmd.arg(arg);
-    }
-
-    cmd.stdout(Stdio::piped());
-    cmd.stderr(Stdio::piped());
-
-    let mut child = cmd.spawn().map_err(|e| format!("执行命令失败: {}", e))?;
-
-    // 获取stdout和stderr句柄
-    let stdout = child.stdout.take().ok_or("无法获取标准输出")?;
-    let stderr = child.stderr.take();
-
-    // 在后台线程中读取输出，避免阻塞
-    let stdout_handle = std::thread::spawn(move || {
-      let reader = BufReader::new(stdout);
-      let mut output = String::new();
-      for line in reader.lines() {
-        if let Ok(line) = line {
-          // 使用结构化日志
-          log::debug!("ffmpeg stdout: {}", line);
-          // 写入日志文件
-          let _ = logger::write_tool_log("ffmpeg", "INFO", &line);
-          output.push_str(&line);
-          output.push('\n');
-        }
-      }
-      output
-    });
-
-    let stderr_handle = if let Some(stderr) = stderr {
-      Some(std::thread::spawn(move || {
-        let reader = BufReader::new(stderr);
-        let mut stderr_output = String::new();
-        for line in reader.lines() {
-          if let Ok(line) = line {
-            // 使用结构化日志
-            log::debug!("ffmpeg stderr: {}", line);
-            // 写入日志文件（ffmpeg 的进度信息在 stderr）
-            let _ = logger::write_tool_log("ffmpeg", "INFO", &line);
-            stderr_output.push_str(&line);
-            stderr_output.push('\n');
-          }
-        }
-        stderr_output
-      }))
-    } else {
-      None
-    };
-
-    // 等待进程完成
-    let status = child.wait().map_err(|e| format!("等待命令完成失败: {}", e))?;
-
-    // 等待输出读取完成
-    let output = stdout_handle.join().map_err(|_| "读取标准输出失败")?;
-    let stderr_output = stderr_handle.map(|h| h.join().unwrap_or_default()).unwrap_or_default();
+  let outcome = run_ffmpeg_blocking(&app, tool_path_str, args, "ffmpeg-progress").await?;
 
-    Ok::<(String, String, std::process::ExitStatus), String>((output, stderr_output, status))
-  }).await.map_err(|e| format!("执行任务失败: {}", e))??;
-
-  if status.success() {
+  if outcome.success() {
     log::info!("ffmpeg 命令执行成功");
     let _ = logger::write_tool_log("ffmpeg", "INFO", "混流命令执行成功");
-    Ok(output)
+    Ok(outcome.stderr_tail.join("\n"))
   } else {
-    log::error!("ffmpeg 命令执行失败，退出码: {:?}", status.code());
+    log::error!("ffmpeg 命令执行失败，退出码: {:?}", outcome.exit_code);
     let _ = logger::write_tool_log("ffmpeg", "ERROR", 
-        &format!("混流命令执行失败，退出码: {:?}", status.code()));
-    Err(format!("命令执行失败，退出码: {:?}\n标准输出: {}\n错误输出: {}",
-      status.code(), output, stderr_output))
+        &format!("混流命令执行失败，退出码: {:?}", outcome.exit_code));
+    Err(AppError::process_failed("命令执行失败", &outcome))
   }
 }
 
+/// 检查 ffmpeg 参数中的输入文件（`-i` 之后）和输出文件（最后一个参数）位于允许的目录中
+fn check_ffmpeg_paths(args: &[String]) -> AppResult<()> {
+  for (i, arg) in args.iter().enumerate() {
+    if arg == "-i" {
+      if let Some(input) = args.get(i + 1) {
+        path_policy::check(input, path_policy::Access::Read)?;
+      }
+    }
+  }
+  if let Some(output) = args.last() {
+    path_policy::check(output, path_policy::Access::Write)?;
+  }
+  Ok(())
+}
+
 #[tauri::command]
-async fn exec_ffmpeg_command(app: tauri::AppHandle, args: Vec<String>) -> Result<String, String> {
+async fn exec_ffmpeg_command(app: tauri::AppHandle, args: Vec<String>) -> AppResult<String> {
   exec_merge_command(app, "ffmpeg".to_string(), args).await
 }
 
 /// 检查工具是否可用
 #[tauri::command]
-async fn check_tool_available(tool_name: String) -> Result<bool, String> {
+async fn check_tool_available(tool_name: String) -> AppResult<bool> {
   // 先检查本地工具
   let local_path = get_tool_path_internal(&tool_name);
   if local_path.exists() {
@@ -756,14 +966,14 @@ async fn check_tool_available(tool_name: String) -> Result<bool, String> {
   let output = Command::new(check_cmd)
     .arg(&tool_name)
     .output()
-    .map_err(|e| format!("检查工具失败: {}", e))?;
+    .map_err(|e| AppError::io("检查工具失败", &e))?;
 
   Ok(output.status.success())
 }
 
 /// 获取系统信息用于生成设备ID（跨平台支持）
 #[tauri::command]
-async fn get_system_info() -> Result<serde_json::Value, String> {
+async fn get_system_info() -> AppResult<serde_json::Value> {
   // 跨平台获取主机名
   let hostname = gethostname::gethostname()
     .to_string_lossy()
@@ -815,15 +1025,19 @@ async fn get_system_info() -> Result<serde_json::Value, String> {
 
 /// 字符串哈希函数
 #[tauri::command]
-async fn hash_string(input: String) -> Result<String, String> {
+async fn hash_string(input: String) -> AppResult<String> {
   let mut hasher = Sha256::new();
   hasher.update(input.as_bytes());
   let result = hasher.finalize();
   Ok(format!("{:x}", result))
 }
 
-/// 检测可用的硬件加速编码器
+/// 检测可用的硬件加速编码器（配置关闭硬件编码时使用 libx264）
 fn detect_hardware_encoder() -> String {
+  if !settings::get().hardware_encoding {
+    return "libx264".to_string();
+  }
+
   #[cfg(target_os = "macos")]
   {
     // macOS 使用 VideoToolbox
@@ -861,48 +1075,17 @@ fn detect_hardware_encoder() -> String {
   }
 }
 
-/// 烧录字幕到视频（硬字幕）
-#[tauri::command]
-async fn burn_subtitle(
-  window: tauri::Window,
-  video_path: String,
-  subtitle_path: String,
-  output_path: String,
-  style: Option<SubtitleStyle>,
-) -> Result<String, String> {
-  log::info!("开始烧录字幕");
-  log::info!("视频路径: {}", video_path);
-  log::info!("字幕路径: {}", subtitle_path);
-  log::info!("输出路径: {}", output_path);
-  
-  // 验证路径安全性
-  validate_path_safety(&video_path)?;
-  validate_path_safety(&subtitle_path)?;
-  validate_path_safety(&output_path)?;
-  
-  // 检测硬件加速编码器
-  let encoder = detect_hardware_encoder();
-  log::info!("使用编码器: {}", encoder);
-  // 通知前端编码器选择
-  let _ = window.emit("burn-subtitle-status", LogEvent {
-    level: "INFO".to_string(),
-    message: format!(
-      "字幕烧录开始，编码器: {}{}",
-      encoder,
-      if encoder != "libx264" { "（硬件）" } else { "（软件）" }
-    ),
-    progress: None,
-    speed: None,
-    timestamp: chrono::Utc::now().to_rfc3339(),
-  });
-  
-  // 获取 ffmpeg 路径（资源目录优先）
-  let ffmpeg_path = resolve_tool_path(&window.app_handle(), "ffmpeg");
-  
-  // 构建 ffmpeg 命令
+/// 构建字幕烧录的 ffmpeg 参数
+fn build_burn_subtitle_args(
+  video_path: &str,
+  subtitle_path: &str,
+  output_path: &str,
+  style: Option<&SubtitleStyle>,
+  encoder: &str,
+) -> Vec<String> {
   // 字幕文件路径需要转义（Windows 路径中的反斜杠）
   let style_merged = {
-    let s = style.clone().unwrap_or(SubtitleStyle {
+    let s = style.cloned().unwrap_or(SubtitleStyle {
       fontsize: Some(72),
       primary_colour: Some("&H00FFFFFF".to_string()),
       outline: Some(2.0),
@@ -927,11 +1110,11 @@ async fn burn_subtitle(
   
   let mut args = vec![
     "-i".to_string(),
-    video_path.clone(),
+    video_path.to_string(),
     "-vf".to_string(),
     subtitle_filter,
     "-c:v".to_string(),
-    encoder.clone(),
+    encoder.to_string(),
   ];
   
   // 根据编码器添加特定参数
@@ -976,69 +1159,90 @@ async fn burn_subtitle(
     "-c:a".to_string(),
     "copy".to_string(),
     "-y".to_string(),
-    output_path.clone(),
+    output_path.to_string(),
   ]);
   
+
+  args
+}
+
+/// 在阻塞线程中运行 ffmpeg，进度事件发送到 `event`
+async fn run_ffmpeg_blocking(
+  app: &tauri::AppHandle,
+  program: String,
+  args: Vec<String>,
+  event: &'static str,
+) -> AppResult<runner::ProcessOutcome> {
+  let channel = runner::EventChannel::new(app, event);
+  tokio::task::spawn_blocking(move || {
+    // 探测输入时长，用于计算进度百分比
+    let duration = ffmpeg::first_input(&args)
+      .and_then(|input| ffmpeg::probe_duration(&program, input));
+
+    let spec = runner::ProcessSpec {
+      tool: "ffmpeg".to_string(),
+      program,
+      args: ffmpeg::with_progress_args(&args),
+      working_dir: None,
+      timeouts: runner::default_timeouts(),
+    };
+    runner::run(&spec, Arc::new(ffmpeg::FfmpegParser::new(duration, None)), Some(channel))
+      .map_err(AppError::from)
+  }).await.map_err(|e| AppError::internal(format!("执行任务失败: {}", e)))?
+}
+
+/// 烧录字幕到视频（硬字幕）
+#[tauri::command]
+async fn burn_subtitle(
+  window: tauri::Window,
+  video_path: String,
+  subtitle_path: String,
+  output_path: String,
+  style: Option<SubtitleStyle>,
+) -> AppResult<String> {
+  log::info!("开始烧录字幕");
+  log::info!("视频路径: {}", video_path);
+  log::info!("字幕路径: {}", subtitle_path);
+  log::info!("输出路径: {}", output_path);
+  
+  // 验证路径位于允许的目录中
+  path_policy::check(&video_path, path_policy::Access::Read)?;
+  path_policy::check(&subtitle_path, path_policy::Access::Read)?;
+  path_policy::check(&output_path, path_policy::Access::Write)?;
+  
+  // 检测硬件加速编码器
+  let encoder = detect_hardware_encoder();
+  log::info!("使用编码器: {}", encoder);
+  // 通知前端编码器选择
+  let _ = window.emit("burn-subtitle-status", LogEvent {
+    level: "INFO".to_string(),
+    message: format!(
+      "字幕烧录开始，编码器: {}{}",
+      encoder,
+      if encoder != "libx264" { "（硬件）" } else { "（软件）" }
+    ),
+    progress: None,
+    speed: None,
+    timestamp: chrono::Utc::now().to_rfc3339(),
+    job_id: None,
+    fps: None,
+    eta_seconds: None,
+    error_code: None,
+  });
+  
+  // 获取 ffmpeg 路径（资源目录优先）
+  let ffmpeg_path = resolve_tool_path(&window.app_handle(), "ffmpeg");
+  
+  // 构建 ffmpeg 命令
+  let args = build_burn_subtitle_args(&video_path, &subtitle_path, &output_path, style.as_ref(), &encoder);
+  
   log::info!("ffmpeg 参数: {:?}", args);
   
-  let window_clone = window.clone();
-  let (output, stderr_output, status) = tokio::task::spawn_blocking(move || {
-    let mut cmd = Command::new(&ffmpeg_path);
-    cmd.args(&args);
-    cmd.stdout(Stdio::piped());
-    cmd.stderr(Stdio::piped());
-    
-    let mut child = cmd.spawn().map_err(|e| {
-      format!("执行 ffmpeg 失败: {}", e)
-    })?;
-    
-    let stdout = child.stdout.take().ok_or("无法获取标准输出")?;
-    let stderr = child.stderr.take();
-    
-    // 读取 stdout
-    let stdout_handle = std::thread::spawn(move || {
-      let reader = BufReader::new(stdout);
-      let mut output = String::new();
-      for line in reader.lines() {
-        if let Ok(line) = line {
-          output.push_str(&line);
-          output.push('\n');
-        }
-      }
-      output
-    });
-    
-    // 读取 stderr（ffmpeg 的进度信息在 stderr）
-    let window_for_stderr = window_clone.clone();
-    let stderr_handle = if let Some(stderr) = stderr {
-      Some(std::thread::spawn(move || {
-        let reader = BufReader::new(stderr);
-        let mut stderr_output = String::new();
-        for line in reader.lines() {
-          if let Ok(line) = line {
-            // 解析 ffmpeg 进度
-            if line.contains("time=") {
-              // 发送进度事件
-              let _ = window_for_stderr.emit("burn-subtitle-progress", line.clone());
-            }
-            stderr_output.push_str(&line);
-            stderr_output.push('\n');
-          }
-        }
-        stderr_output
-      }))
-    } else {
-      None
-    };
-    
-    let status = child.wait().map_err(|e| format!("等待命令完成失败: {}", e))?;
-    let output = stdout_handle.join().map_err(|_| "读取标准输出失败")?;
-    let stderr_output = stderr_handle.map(|h| h.join().unwrap_or_default()).unwrap_or_default();
-    
-    Ok::<(String, String, std::process::ExitStatus), String>((output, stderr_output, status))
-  }).await.map_err(|e| format!("执行任务失败: {}", e))??;
+  let app = window.app_handle().clone();
+  let outcome = run_ffmpeg_blocking(&app, ffmpeg_path.to_string_lossy().to_string(), args, "burn-subtitle-progress")
+    .await?;
   
-  if status.success() {
+  if outcome.success() {
     log::info!("字幕烧录成功");
     let _ = window.emit("burn-subtitle-status", LogEvent {
       level: "INFO".to_string(),
@@ -1046,10 +1250,14 @@ async fn burn_subtitle(
       progress: None,
       speed: None,
       timestamp: chrono::Utc::now().to_rfc3339(),
+      job_id: None,
+      fps: None,
+      eta_seconds: None,
+      error_code: None,
     });
     Ok(format!("字幕烧录完成: {}", output_path))
   } else {
-    log::error!("字幕烧录失败，退出码: {:?}", status.code());
+    log::error!("字幕烧录失败，退出码: {:?}", outcome.exit_code);
     // 如果是硬件编码器，尝试回退到软件编码
     if encoder != "libx264" {
       let _ = window.emit("burn-subtitle-status", LogEvent {
@@ -1058,6 +1266,10 @@ async fn burn_subtitle(
         progress: None,
         speed: None,
         timestamp: chrono::Utc::now().to_rfc3339(),
+        job_id: None,
+        fps: None,
+        eta_seconds: None,
+        error_code: None,
       });
 
       // 重新构建软件编码参数
@@ -1084,66 +1296,21 @@ async fn burn_subtitle(
         output_path.clone(),
       ];
 
-      let window_clone_fb = window.clone();
-      let (output_fb, stderr_output_fb, status_fb) = tokio::task::spawn_blocking(move || {
-        let mut cmd = Command::new(&ffmpeg_path_fb);
-        cmd.args(&args_fb);
-        cmd.stdout(Stdio::piped());
-        cmd.stderr(Stdio::piped());
-
-        let mut child = cmd.spawn().map_err(|e| {
-          format!("执行 ffmpeg 失败（回退）: {}", e)
-        })?;
-
-        let stdout = child.stdout.take().ok_or("无法获取标准输出")?;
-        let stderr = child.stderr.take();
-
-        let stdout_handle = std::thread::spawn(move || {
-          let reader = BufReader::new(stdout);
-          let mut output = String::new();
-          for line in reader.lines() {
-            if let Ok(line) = line {
-              output.push_str(&line);
-              output.push('\n');
-            }
-          }
-          output
-        });
-
-        let window_for_stderr = window_clone_fb.clone();
-        let stderr_handle = if let Some(stderr) = stderr {
-          Some(std::thread::spawn(move || {
-            let reader = BufReader::new(stderr);
-            let mut stderr_output = String::new();
-            for line in reader.lines() {
-              if let Ok(line) = line {
-                if line.contains("time=") {
-                  let _ = window_for_stderr.emit("burn-subtitle-progress", line.clone());
-                }
-                stderr_output.push_str(&line);
-                stderr_output.push('\n');
-              }
-            }
-            stderr_output
-          }))
-        } else {
-          None
-        };
+      let outcome_fb = run_ffmpeg_blocking(&app, ffmpeg_path_fb.to_string_lossy().to_string(), args_fb, "burn-subtitle-progress")
+        .await
+        .map_err(|e| AppError { message: format!("{}（回退）", e.message), ..e })?;
 
-        let status = child.wait().map_err(|e| format!("等待命令完成失败: {}", e))?;
-        let output = stdout_handle.join().map_err(|_| "读取标准输出失败")?;
-        let stderr_output = stderr_handle.map(|h| h.join().unwrap_or_default()).unwrap_or_default();
-
-        Ok::<(String, String, std::process::ExitStatus), String>((output, stderr_output, status))
-      }).await.map_err(|e| format!("执行任务失败（回退）: {}", e))??;
-
-      if status_fb.success() {
+      if outcome_fb.success() {
         let _ = window.emit("burn-subtitle-status", LogEvent {
           level: "INFO".to_string(),
           message: "字幕烧录成功（已回退到软件编码 libx264）".to_string(),
           progress: None,
           speed: None,
           timestamp: chrono::Utc::now().to_rfc3339(),
+          job_id: None,
+          fps: None,
+          eta_seconds: None,
+          error_code: None,
         });
         return Ok(format!("字幕烧录完成: {}", output_path));
       } else {
@@ -1153,11 +1320,15 @@ async fn burn_subtitle(
           progress: None,
           speed: None,
           timestamp: chrono::Utc::now().to_rfc3339(),
+          job_id: None,
+          fps: None,
+          eta_seconds: None,
+          error_code: None,
         });
-        return Err(format!(
-          "字幕烧录失败（硬件与回退均失败）\n标准输出: {}\n错误输出: {}\n回退标准输出: {}\n回退错误输出: {}",
-          output, stderr_output, output_fb, stderr_output_fb
-        ));
+        let error = AppError::process_failed("字幕烧录失败（硬件与回退均失败）", &outcome_fb);
+        return Err(error.with_details(format!(
+          "{}\n回退: {}", outcome.failure_message(), outcome_fb.failure_message()
+        )));
       }
     } else {
       let _ = window.emit("burn-subtitle-status", LogEvent {
@@ -1166,8 +1337,12 @@ async fn burn_subtitle(
         progress: None,
         speed: None,
         timestamp: chrono::Utc::now().to_rfc3339(),
+        job_id: None,
+        fps: None,
+        eta_seconds: None,
+        error_code: None,
       });
-      Err(format!("字幕烧录失败\n标准输出: {}\n错误输出: {}", output, stderr_output))
+      Err(AppError::process_failed("字幕烧录失败", &outcome))
     }
   }
 }
@@ -1176,9 +1351,9 @@ async fn burn_subtitle(
 
 /// 列出所有日志文件
 #[tauri::command]
-async fn list_log_files(app: tauri::AppHandle) -> Result<Vec<String>, String> {
+async fn list_log_files(app: tauri::AppHandle) -> AppResult<Vec<String>> {
     let app_data_dir = app.path().app_data_dir()
-        .map_err(|e| format!("获取应用数据目录失败: {}", e))?;
+        .map_err(|e| AppError::internal(format!("获取应用数据目录失败: {}", e)))?;
     
     let log_files = logger::list_log_files(app_data_dir)?;
     Ok(log_files.iter()
@@ -1188,34 +1363,55 @@ async fn list_log_files(app: tauri::AppHandle) -> Result<Vec<String>, String> {
 
 /// 读取日志文件内容
 #[tauri::command]
-async fn read_log_file(file_path: String, max_lines: Option<usize>) -> Result<String, String> {
-    logger::read_log_file(PathBuf::from(file_path), max_lines)
+async fn read_log_file(file_path: String, max_lines: Option<usize>) -> AppResult<String> {
+    let path = path_policy::check(&file_path, path_policy::Access::Read)?;
+    logger::read_log_file(path, max_lines)
+}
+
+/// 读取日志文件并解析为结构化记录（兼容文本格式和 JSON 行格式）
+#[tauri::command]
+async fn read_log_records(file_path: String, max_lines: Option<usize>, filter: Option<logger::LogFilter>) -> AppResult<Vec<logger::LogRecord>> {
+    let path = path_policy::check(&file_path, path_policy::Access::Read)?;
+    logger::read_log_records(path, max_lines, &filter.unwrap_or_default())
+}
+
+/// 读取任务的单独日志文件（logs/jobs/<任务 ID>.log）
+#[tauri::command]
+async fn read_job_log(job_id: String, max_lines: Option<usize>) -> AppResult<String> {
+    logger::read_job_log(&job_id, max_lines)
 }
 
 /// 清理旧日志文件
 #[tauri::command]
-async fn cleanup_old_logs(app: tauri::AppHandle, keep_days: u32) -> Result<usize, String> {
+async fn cleanup_old_logs(app: tauri::AppHandle, keep_days: u32) -> AppResult<usize> {
     let app_data_dir = app.path().app_data_dir()
-        .map_err(|e| format!("获取应用数据目录失败: {}", e))?;
+        .map_err(|e| AppError::internal(format!("获取应用数据目录失败: {}", e)))?;
     
     logger::cleanup_old_logs(app_data_dir, keep_days)
 }
 
+/// 删除任务产生的中间文件；`trash` 未指定时按设置决定是否移入回收站
 #[tauri::command]
-async fn delete_files(paths: Vec<String>) -> Result<usize, String> {
+async fn delete_files(paths: Vec<String>, trash: Option<bool>) -> AppResult<usize> {
     let mut count = 0usize;
     for p in paths {
-        validate_path_safety(&p)?;
-        let path = PathBuf::from(&p);
-        if path.exists() {
-            std::fs::remove_file(&path).map_err(|e| format!("删除文件失败: {} ({:?})", e, path))?;
+        let path = path_policy::check(&p, path_policy::Access::Delete)?;
+        if intermediates::delete(&path, trash)? {
             count += 1;
         }
     }
     Ok(count)
 }
-/// 通过资源目录解析工具路径（优先使用打包后的资源目录）
+
+/// 解析工具路径：优先使用配置中指定的路径，其次为打包后的资源目录
 fn resolve_tool_path(handle: &tauri::AppHandle, tool_name: &str) -> PathBuf {
+    if let Some(path) = settings::get().tool_paths.get(tool_name) {
+        match settings::check_tool_path(tool_name, Path::new(path)) {
+            Ok(path) => return path,
+            Err(reason) => log::warn!("配置的工具路径不可用（{}），使用内置工具: {}", reason, path),
+        }
+    }
+
     // 根据操作系统添加可执行文件扩展名
     #[cfg(windows)]
     let tool_name_with_ext = if tool_name.ends_with(".exe") {
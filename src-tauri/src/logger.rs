@@ -1,23 +1,440 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::{File, OpenOptions};
-use std::io::{Write};
-use std::path::PathBuf;
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError};
 use std::sync::Mutex;
-use chrono::Local;
-use once_cell::sync::Lazy;
+use std::time::{Duration, Instant};
+use chrono::{Local, NaiveDate};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use once_cell::sync::{Lazy, OnceCell};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::phase::DownloadPhase;
+use crate::redact;
 
-/// 日志文件路径缓存
-static LOG_FILE_PATH: Lazy<Mutex<Option<PathBuf>>> = Lazy::new(|| Mutex::new(None));
+/// 日志文件名前缀：当天的日志为 `gaga-client_日期.log`，
+/// 按大小轮转后为 `gaga-client_日期.序号.log.gz`
+const LOG_FILE_PREFIX: &str = "gaga-client_";
+
+/// 压缩后的日志文件后缀
+const GZ_SUFFIX: &str = ".gz";
+
+/// 当前写入的日志文件（由日志线程写入，按大小清理时读取路径）
+static ACTIVE_LOG: Lazy<Mutex<Option<ActiveLog>>> = Lazy::new(|| Mutex::new(None));
+
+/// 发送到日志线程的通道；初始化前写入的日志被忽略
+static LOG_SENDER: OnceCell<SyncSender<WriterMessage>> = OnceCell::new();
+
+/// 通道容量（行）；写满时丢弃新日志而不阻塞输出读取线程
+const CHANNEL_CAPACITY: usize = 8192;
+
+/// 日志线程定时刷新的间隔
+const FLUSH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 退出时等待日志线程写完的最长时间
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// 尚未报告的丢弃行数
+static DROPPED_PENDING: AtomicU64 = AtomicU64::new(0);
+
+/// 本次运行累计丢弃的行数
+static DROPPED_TOTAL: AtomicU64 = AtomicU64::new(0);
+
+/// 同时打开的任务日志文件上限，超过时全部关闭后重新打开
+const MAX_OPEN_JOB_LOGS: usize = 32;
+
+/// 任务日志目录（位于日志目录下）
+const JOB_LOG_DIR: &str = "jobs";
+
+/// 日志线程正在写入的任务日志（任务 ID），按总大小清理时跳过
+static OPEN_JOB_LOGS: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+/// 定时检查日志目录总大小的间隔（不按大小轮转时没有压缩任务触发清理）
+const SIZE_CHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// 为每个任务单独写一份日志（`logs/jobs/<任务 ID>.log`）
+static JOB_LOG_FILES: AtomicBool = AtomicBool::new(true);
+
+enum WriterMessage {
+    /// 一行日志；带任务 ID 时同时写入该任务的日志文件
+    Line { line: String, job_id: Option<String> },
+    /// 任务结束，关闭其日志文件
+    CloseJob(String),
+    /// 写入缓冲区中的内容后回复
+    Flush(SyncSender<()>),
+}
+
+/// 日志配置，由配置模块在加载或修改配置时设置
+pub struct LogOptions {
+    pub json: bool,
+    /// 单个日志文件大小上限（字节），0 表示不按大小轮转
+    pub max_file_size: u64,
+    /// 日志目录总大小上限（字节），0 表示不限制
+    pub max_total_size: u64,
+    pub job_files: bool,
+}
+
+/// 单个日志文件大小上限（字节），0 表示不按大小轮转
+static MAX_FILE_SIZE: AtomicU64 = AtomicU64::new(10 * 1024 * 1024);
+
+/// 日志目录总大小上限（字节），0 表示不限制
+static MAX_TOTAL_SIZE: AtomicU64 = AtomicU64::new(200 * 1024 * 1024);
+
+struct ActiveLog {
+    logs_dir: PathBuf,
+    date: NaiveDate,
+    path: PathBuf,
+    /// 已写入的字节数，避免每次写入都查询文件大小
+    size: u64,
+    writer: Option<BufWriter<File>>,
+}
+
+impl ActiveLog {
+    fn open(logs_dir: PathBuf, date: NaiveDate) -> Self {
+        let path = logs_dir.join(format!("{}{}.log", LOG_FILE_PREFIX, date.format("%Y-%m-%d")));
+        let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        ActiveLog { logs_dir, date, path, size, writer: None }
+    }
+
+    /// 写入缓冲区，必要时先轮转文件，返回需要压缩的旧文件
+    fn write_line(&mut self, line: &str) -> AppResult<Option<PathBuf>> {
+        let finished = self.rotate_if_needed(line.len() as u64);
+        if self.writer.is_none() {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)
+                .map_err(|e| AppError::io("打开日志文件失败", &e))?;
+            self.writer = Some(BufWriter::new(file));
+        }
+        if let Some(writer) = self.writer.as_mut() {
+            writer.write_all(line.as_bytes())
+                .map_err(|e| AppError::io("写入日志失败", &e))?;
+        }
+        self.size += line.len() as u64;
+        Ok(finished)
+    }
+
+    fn flush(&mut self) {
+        if let Some(writer) = self.writer.as_mut() {
+            if let Err(e) = writer.flush() {
+                log::error!("刷新日志文件失败: {}", e);
+            }
+        }
+    }
+
+    /// 写完缓冲区并关闭文件（重命名前需要关闭）
+    fn close(&mut self) {
+        self.flush();
+        self.writer = None;
+    }
+
+    /// 日期变化或文件超过大小上限时换用新文件，返回需要压缩的旧文件
+    fn rotate_if_needed(&mut self, incoming: u64) -> Option<PathBuf> {
+        let today = Local::now().date_naive();
+        if today != self.date {
+            self.close();
+            let finished = self.path.clone();
+            *self = ActiveLog::open(self.logs_dir.clone(), today);
+            return Some(finished);
+        }
+        let max_size = MAX_FILE_SIZE.load(Ordering::Relaxed);
+        if max_size == 0 || self.size == 0 || self.size + incoming <= max_size {
+            return None;
+        }
+        self.close();
+        let index = next_rotation_index(&self.logs_dir, self.date);
+        let rotated = self.logs_dir.join(format!("{}{}.{}.log", LOG_FILE_PREFIX, self.date.format("%Y-%m-%d"), index));
+        match std::fs::rename(&self.path, &rotated) {
+            Ok(()) => {
+                self.size = 0;
+                Some(rotated)
+            }
+            Err(e) => {
+                log::warn!("轮转日志文件失败: {}", e);
+                None
+            }
+        }
+    }
+}
+
+/// 已打开的任务日志文件（只在日志线程中使用）
+struct JobLogs {
+    writers: HashMap<String, BufWriter<File>>,
+}
+
+impl JobLogs {
+    fn write_line(&mut self, logs_dir: &Path, job_id: &str, line: &str) -> AppResult<()> {
+        if !self.writers.contains_key(job_id) {
+            if self.writers.len() >= MAX_OPEN_JOB_LOGS {
+                self.close_all();
+            }
+            // 先登记再打开，避免按总大小清理时删除即将写入的文件
+            OPEN_JOB_LOGS.lock().unwrap().insert(job_id.to_string());
+            let dir = logs_dir.join(JOB_LOG_DIR);
+            std::fs::create_dir_all(&dir)
+                .map_err(|e| AppError::io("创建任务日志目录失败", &e))?;
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(dir.join(format!("{}.log", job_id)))
+                .map_err(|e| AppError::io("打开任务日志文件失败", &e))?;
+            self.writers.insert(job_id.to_string(), BufWriter::new(file));
+        }
+        if let Some(writer) = self.writers.get_mut(job_id) {
+            writer.write_all(line.as_bytes())
+                .map_err(|e| AppError::io("写入任务日志失败", &e))?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) {
+        for writer in self.writers.values_mut() {
+            let _ = writer.flush();
+        }
+    }
+
+    fn close(&mut self, job_id: &str) {
+        if let Some(mut writer) = self.writers.remove(job_id) {
+            let _ = writer.flush();
+        }
+        OPEN_JOB_LOGS.lock().unwrap().remove(job_id);
+    }
+
+    fn close_all(&mut self) {
+        self.flush();
+        self.writers.clear();
+        OPEN_JOB_LOGS.lock().unwrap().clear();
+    }
+}
+
+/// 审计日志文件名（记录被拒绝的路径访问等安全事件，不按日期拆分）
+const AUDIT_LOG_FILE: &str = "audit.log";
+
+/// 轮转后的审计日志文件名前缀（`audit.<序号>.log`）
+const AUDIT_ROTATED_PREFIX: &str = "audit.";
+
+/// 审计日志路径缓存
+static AUDIT_LOG_PATH: Lazy<Mutex<Option<PathBuf>>> = Lazy::new(|| Mutex::new(None));
+
+/// 以 JSON 行格式写入日志（对应配置 logFormat = "json"）
+static JSON_FORMAT: AtomicBool = AtomicBool::new(false);
+
+/// 文本格式的时间戳
+const TEXT_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.3f";
+
+/// 一条日志记录；JSON 行格式中每行为一条记录
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct LogRecord {
+    pub timestamp: String,
+    pub level: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub job_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phase: Option<DownloadPhase>,
+    pub message: String,
+    /// 结构化字段，如进度、退出码
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, Value>,
+}
+
+impl LogRecord {
+    pub fn new(level: &str, message: &str) -> Self {
+        LogRecord {
+            level: level.to_string(),
+            message: message.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn tool(mut self, tool: &str) -> Self {
+        self.tool = Some(tool.to_string());
+        self
+    }
+
+    pub fn job(mut self, job_id: &str) -> Self {
+        self.job_id = Some(job_id.to_string());
+        self
+    }
+
+    pub fn phase(mut self, phase: DownloadPhase) -> Self {
+        self.phase = Some(phase);
+        self
+    }
+
+    pub fn field(mut self, key: &str, value: impl Into<Value>) -> Self {
+        self.fields.insert(key.to_string(), value.into());
+        self
+    }
+
+    /// 交给日志线程写入文件；队列已满时丢弃并计数
+    pub fn write(self) -> AppResult<()> {
+        let sender = match LOG_SENDER.get() {
+            Some(sender) => sender,
+            None => return Ok(()), // 如果日志文件未初始化，静默失败
+        };
+        let job_id = self.job_id.clone()
+            .filter(|id| JOB_LOG_FILES.load(Ordering::Relaxed) && is_valid_job_id(id));
+        match sender.try_send(WriterMessage::Line { line: self.format_line()?, job_id }) {
+            Ok(()) | Err(TrySendError::Disconnected(_)) => {}
+            Err(TrySendError::Full(_)) => {
+                DROPPED_PENDING.fetch_add(1, Ordering::Relaxed);
+                DROPPED_TOTAL.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        Ok(())
+    }
+
+    /// 按当前格式生成一行日志（含换行符），时间戳取当前时间，敏感内容已隐藏
+    fn format_line(mut self) -> AppResult<String> {
+        self.message = redact::redact(&self.message);
+        for value in self.fields.values_mut() {
+            if let Value::String(text) = value {
+                *text = redact::redact(text);
+            }
+        }
+        let now = Local::now();
+        let line = if JSON_FORMAT.load(Ordering::Relaxed) {
+            self.timestamp = now.to_rfc3339_opts(chrono::SecondsFormat::Millis, false);
+            serde_json::to_string(&self)
+                .map_err(|e| AppError::internal(format!("序列化日志失败: {}", e)))?
+        } else {
+            self.timestamp = now.format(TEXT_TIME_FORMAT).to_string();
+            self.to_text()
+        };
+        Ok(format!("{}\n", line))
+    }
+
+    /// 文本格式：`[时间] [级别] [工具] [job:任务 ID] 消息 key=value ...`
+    fn to_text(&self) -> String {
+        let tool_prefix = self.tool.as_ref().map(|t| format!("[{}] ", t)).unwrap_or_default();
+        let job_prefix = self.job_id.as_ref().map(|id| format!("[job:{}] ", id)).unwrap_or_default();
+        let mut line = format!("[{}] [{}] {}{}{}", self.timestamp, self.level, tool_prefix, job_prefix, self.message);
+        for (key, value) in &self.fields {
+            line.push_str(&format!(" {}={}", key, value));
+        }
+        line
+    }
+
+    /// 解析一行日志：JSON 行直接反序列化，文本行按 `[时间] [级别] [工具] 消息` 拆分
+    pub fn parse(line: &str) -> Option<LogRecord> {
+        let line = line.trim_end();
+        if line.starts_with('{') {
+            if let Ok(record) = serde_json::from_str(line) {
+                return Some(record);
+            }
+        }
+        let (timestamp, rest) = take_bracket(line)?;
+        let (level, rest) = take_bracket(rest)?;
+        // 工具名不含空格；以方括号开头的普通消息不会被误认为工具名
+        let (tool, rest) = match take_bracket(rest) {
+            Some((tool, message)) if !tool.contains(' ') && !tool.starts_with("job:") => (Some(tool.to_string()), message),
+            _ => (None, rest),
+        };
+        let (job_id, message) = match take_bracket(rest) {
+            Some((job, message)) if job.starts_with("job:") && !job.contains(' ') => (Some(job[4..].to_string()), message),
+            _ => (None, rest),
+        };
+        Some(LogRecord {
+            timestamp: timestamp.to_string(),
+            level: level.to_string(),
+            tool,
+            job_id,
+            message: message.to_string(),
+            ..Default::default()
+        })
+    }
+}
+
+/// 取出行首的 `[...]` 及其后的内容
+fn take_bracket(text: &str) -> Option<(&str, &str)> {
+    let rest = text.strip_prefix('[')?;
+    let end = rest.find(']')?;
+    Some((&rest[..end], rest[end + 1..].trim_start()))
+}
+
+/// 日志记录筛选条件，均为可选
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct LogFilter {
+    pub job_id: Option<String>,
+    pub tool: Option<String>,
+    /// 只返回这些级别（如 `["WARN", "ERROR"]`）
+    pub levels: Option<Vec<String>>,
+    /// 消息关键字（不区分大小写）
+    pub search: Option<String>,
+}
+
+impl LogFilter {
+    fn matches(&self, record: &LogRecord) -> bool {
+        if self.job_id.is_some() && record.job_id != self.job_id {
+            return false;
+        }
+        if self.tool.is_some() && record.tool != self.tool {
+            return false;
+        }
+        if let Some(levels) = &self.levels {
+            if !levels.iter().any(|l| l.eq_ignore_ascii_case(&record.level)) {
+                return false;
+            }
+        }
+        if let Some(search) = &self.search {
+            if !record.message.to_lowercase().contains(&search.to_lowercase()) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// 设置日志格式、大小上限和任务日志文件（配置加载或修改时调用）
+pub fn configure(options: &LogOptions) {
+    JSON_FORMAT.store(options.json, Ordering::Relaxed);
+    MAX_FILE_SIZE.store(options.max_file_size, Ordering::Relaxed);
+    MAX_TOTAL_SIZE.store(options.max_total_size, Ordering::Relaxed);
+    JOB_LOG_FILES.store(options.job_files, Ordering::Relaxed);
+}
 
 /// 初始化日志文件路径
-pub fn init_log_file(app_data_dir: PathBuf) -> Result<(), String> {
+pub fn init_log_file(app_data_dir: PathBuf) -> AppResult<()> {
     let logs_dir = app_data_dir.join("logs");
     std::fs::create_dir_all(&logs_dir)
-        .map_err(|e| format!("创建日志目录失败: {}", e))?;
+        .map_err(|e| AppError::io("创建日志目录失败", &e))?;
     
-    let log_file = logs_dir.join(format!("gaga-client_{}.log", 
-        Local::now().format("%Y-%m-%d")));
-    
-    *LOG_FILE_PATH.lock().unwrap() = Some(log_file.clone());
+    let active = ActiveLog::open(logs_dir.clone(), Local::now().date_naive());
+
+    // 之前运行留下的未压缩日志（前几天的日志、压缩中断的轮转文件）
+    let leftovers: Vec<PathBuf> = log_files_in(&logs_dir)?.into_iter()
+        .filter(|p| {
+            let name = file_name(p);
+            *p != active.path && !is_compressed(p) && name != AUDIT_LOG_FILE
+                && (name.starts_with(LOG_FILE_PREFIX) || name.starts_with(AUDIT_ROTATED_PREFIX))
+        })
+        .collect();
+
+    *ACTIVE_LOG.lock().unwrap() = Some(active);
+    *AUDIT_LOG_PATH.lock().unwrap() = Some(logs_dir.join(AUDIT_LOG_FILE));
+
+    if LOG_SENDER.get().is_none() {
+        let (sender, receiver) = mpsc::sync_channel(CHANNEL_CAPACITY);
+        std::thread::Builder::new()
+            .name("log-writer".to_string())
+            .spawn(move || run_writer(receiver))
+            .map_err(|e| AppError::io("启动日志线程失败", &e))?;
+        let _ = LOG_SENDER.set(sender);
+    }
+    if !leftovers.is_empty() {
+        compress_in_background(leftovers);
+    }
     
     // 写入初始日志
     write_log("INFO", "日志系统初始化", None)?;
@@ -25,78 +442,249 @@ pub fn init_log_file(app_data_dir: PathBuf) -> Result<(), String> {
     Ok(())
 }
 
+/// 日志线程：批量写入缓冲区，定时和退出时刷新，并报告被丢弃的行数
+fn run_writer(receiver: Receiver<WriterMessage>) {
+    let mut job_logs = JobLogs { writers: HashMap::new() };
+    let mut last_flush = Instant::now();
+    let mut last_size_check = Instant::now();
+    loop {
+        let message = receiver.recv_timeout(FLUSH_INTERVAL);
+        let disconnected = matches!(message, Err(RecvTimeoutError::Disconnected));
+        let mut acks = Vec::new();
+        let mut finished = Vec::new();
+        {
+            let mut active = ACTIVE_LOG.lock().unwrap();
+            let log = match active.as_mut() {
+                Some(log) => log,
+                None => return,
+            };
+            // 一次取出通道中已有的所有消息
+            for message in message.into_iter().chain(receiver.try_iter()) {
+                match message {
+                    WriterMessage::Line { line, job_id } => {
+                        match log.write_line(&line) {
+                            Ok(rotated) => finished.extend(rotated),
+                            Err(e) => log::error!("{}", e),
+                        }
+                        if let Some(job_id) = job_id {
+                            if let Err(e) = job_logs.write_line(&log.logs_dir, &job_id, &line) {
+                                log::error!("{}", e);
+                            }
+                        }
+                    }
+                    WriterMessage::CloseJob(job_id) => job_logs.close(&job_id),
+                    WriterMessage::Flush(ack) => acks.push(ack),
+                }
+            }
+
+            let dropped = DROPPED_PENDING.swap(0, Ordering::Relaxed);
+            if dropped > 0 {
+                log::warn!("日志队列已满，丢弃了 {} 行日志", dropped);
+                let record = LogRecord::new("WARN", &format!("日志队列已满，丢弃了 {} 行日志", dropped))
+                    .field("dropped", dropped);
+                if let Ok(line) = record.format_line() {
+                    if let Ok(rotated) = log.write_line(&line) {
+                        finished.extend(rotated);
+                    }
+                }
+            }
+
+            if !acks.is_empty() || disconnected || last_flush.elapsed() >= FLUSH_INTERVAL {
+                log.flush();
+                job_logs.flush();
+                last_flush = Instant::now();
+            }
+        }
+        for ack in acks {
+            let _ = ack.send(());
+        }
+        if !finished.is_empty() {
+            compress_in_background(finished);
+            last_size_check = Instant::now();
+        } else if last_size_check.elapsed() >= SIZE_CHECK_INTERVAL {
+            std::thread::spawn(enforce_total_size);
+            last_size_check = Instant::now();
+        }
+        if disconnected {
+            return;
+        }
+    }
+}
+
+/// 等待日志线程写完已提交的日志
+pub fn flush() {
+    let sender = match LOG_SENDER.get() {
+        Some(sender) => sender,
+        None => return,
+    };
+    let (ack, done) = mpsc::sync_channel(1);
+    // 队列已满时同样等待，确保之前的日志都已写入
+    if sender.send(WriterMessage::Flush(ack)).is_ok() {
+        let _ = done.recv_timeout(SHUTDOWN_TIMEOUT);
+    }
+}
+
+/// 任务结束时关闭其日志文件（之后写入的日志会重新打开文件追加）。
+/// 队列已满时等待而不丢弃，否则文件句柄会一直保持打开
+pub fn close_job_log(job_id: &str) {
+    if let Some(sender) = LOG_SENDER.get() {
+        let _ = sender.send(WriterMessage::CloseJob(job_id.to_string()));
+    }
+}
+
+/// 任务 ID 只能包含字母、数字、`-` 和 `_`，用作文件名
+fn is_valid_job_id(job_id: &str) -> bool {
+    !job_id.is_empty()
+        && job_id.len() <= 128
+        && job_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// 任务日志文件路径；未启用任务日志文件或任务 ID 不合法时返回 None
+pub fn job_log_path(job_id: &str) -> Option<PathBuf> {
+    if !JOB_LOG_FILES.load(Ordering::Relaxed) || !is_valid_job_id(job_id) {
+        return None;
+    }
+    let active = ACTIVE_LOG.lock().unwrap();
+    active.as_ref().map(|log| log.logs_dir.join(JOB_LOG_DIR).join(format!("{}.log", job_id)))
+}
+
+/// 读取任务日志文件
+pub fn read_job_log(job_id: &str, max_lines: Option<usize>) -> AppResult<String> {
+    if !is_valid_job_id(job_id) {
+        return Err(AppError::invalid_argument(format!("任务 ID 不合法: {}", job_id)));
+    }
+    let logs_dir = match ACTIVE_LOG.lock().unwrap().as_ref() {
+        Some(log) => log.logs_dir.clone(),
+        None => return Err(AppError::internal("日志系统未初始化")),
+    };
+    let path = logs_dir.join(JOB_LOG_DIR).join(format!("{}.log", job_id));
+    if !path.is_file() {
+        return Err(AppError::new(ErrorCode::NotFound, format!("任务没有单独的日志文件: {}", job_id)));
+    }
+    read_log_file(path, max_lines)
+}
+
+/// 退出前调用：写完所有日志并报告本次运行丢弃的行数
+pub fn shutdown() {
+    let dropped = DROPPED_TOTAL.load(Ordering::Relaxed);
+    if dropped > 0 {
+        let _ = write_log("WARN", &format!("本次运行共丢弃 {} 行日志", dropped), None);
+    }
+    let _ = write_log("INFO", "日志系统关闭", None);
+    flush();
+}
+
 /// 写入日志到文件
-pub fn write_log(level: &str, message: &str, tool: Option<&str>) -> Result<(), String> {
-    let log_path = LOG_FILE_PATH.lock().unwrap();
-    let log_file = match log_path.as_ref() {
-        Some(path) => path.clone(),
-        None => return Ok(()), // 如果日志文件未初始化，静默失败
+pub fn write_log(level: &str, message: &str, tool: Option<&str>) -> AppResult<()> {
+    let record = LogRecord::new(level, message);
+    match tool {
+        Some(tool) => record.tool(tool).write(),
+        None => record.write(),
+    }
+}
+
+/// 写入审计日志（安全事件不经过日志队列，直接写入文件），失败时只记录到控制台。
+/// 与普通日志一样按大小轮转并压缩，旧文件计入日志目录总大小
+pub fn write_audit_log(message: &str) {
+    // 写入完成前持有锁，避免并发写入时重复轮转
+    let audit_path = AUDIT_LOG_PATH.lock().unwrap();
+    let audit_file = match audit_path.as_ref() {
+        Some(path) => path,
+        None => return,
     };
-    drop(log_path);
-    
+    let timestamp = Local::now().format(TEXT_TIME_FORMAT);
+    let log_line = format!("[{}] [AUDIT] {}\n", timestamp, redact::redact(message));
+    let rotated = rotate_audit_log(audit_file, log_line.len() as u64);
+    if let Err(e) = append_line(audit_file, &log_line) {
+        log::error!("写入审计日志失败: {}", e);
+    }
+    drop(audit_path);
+    if let Some(rotated) = rotated {
+        compress_in_background(vec![rotated]);
+    }
+}
+
+/// 审计日志超过单个文件大小上限时改名为 `audit.<序号>.log`，返回需要压缩的旧文件
+fn rotate_audit_log(audit_file: &Path, incoming: u64) -> Option<PathBuf> {
+    let max_size = MAX_FILE_SIZE.load(Ordering::Relaxed);
+    let size = std::fs::metadata(audit_file).map(|m| m.len()).unwrap_or(0);
+    if max_size == 0 || size == 0 || size + incoming <= max_size {
+        return None;
+    }
+    let logs_dir = audit_file.parent()?;
+    let index = next_index(logs_dir, AUDIT_ROTATED_PREFIX);
+    let rotated = logs_dir.join(format!("{}{}.log", AUDIT_ROTATED_PREFIX, index));
+    match std::fs::rename(audit_file, &rotated) {
+        Ok(()) => Some(rotated),
+        Err(e) => {
+            log::warn!("轮转审计日志失败: {}", e);
+            None
+        }
+    }
+}
+
+fn append_line(log_file: &Path, log_line: &str) -> AppResult<()> {
     let mut file = OpenOptions::new()
         .create(true)
         .append(true)
-        .open(&log_file)
-        .map_err(|e| format!("打开日志文件失败: {}", e))?;
-    
-    let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S%.3f");
-    let tool_prefix = tool.map(|t| format!("[{}] ", t)).unwrap_or_default();
-    let log_line = format!("[{}] [{}] {}{}\n", timestamp, level, tool_prefix, message);
+        .open(log_file)
+        .map_err(|e| AppError::io("打开日志文件失败", &e))?;
     
     file.write_all(log_line.as_bytes())
-        .map_err(|e| format!("写入日志失败: {}", e))?;
+        .map_err(|e| AppError::io("写入日志失败", &e))?;
     
     file.flush()
-        .map_err(|e| format!("刷新日志文件失败: {}", e))?;
+        .map_err(|e| AppError::io("刷新日志文件失败", &e))?;
     
     Ok(())
 }
 
 /// 写入工具日志（带工具名称）
-pub fn write_tool_log(tool_name: &str, level: &str, message: &str) -> Result<(), String> {
+pub fn write_tool_log(tool_name: &str, level: &str, message: &str) -> AppResult<()> {
     write_log(level, message, Some(tool_name))
 }
 
+/// 写入任务的工具日志（带任务 ID 和当前阶段）
+pub fn write_job_log(tool_name: &str, level: &str, job_id: &str, phase: Option<DownloadPhase>, message: &str) -> AppResult<()> {
+    let record = LogRecord::new(level, message).tool(tool_name).job(job_id);
+    match phase {
+        Some(phase) => record.phase(phase).write(),
+        None => record.write(),
+    }
+}
+
 
-/// 获取所有日志文件列表
-pub fn list_log_files(app_data_dir: PathBuf) -> Result<Vec<PathBuf>, String> {
+/// 获取所有日志文件列表（含压缩的轮转文件）
+pub fn list_log_files(app_data_dir: PathBuf) -> AppResult<Vec<PathBuf>> {
     let logs_dir = app_data_dir.join("logs");
     
     if !logs_dir.exists() {
         return Ok(vec![]);
     }
     
-    let entries = std::fs::read_dir(&logs_dir)
-        .map_err(|e| format!("读取日志目录失败: {}", e))?;
-    
-    let mut log_files = Vec::new();
-    for entry in entries {
-        if let Ok(entry) = entry {
-            let path = entry.path();
-            if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("log") {
-                log_files.push(path);
-            }
-        }
-    }
-    
-    log_files.sort_by(|a, b| {
-        b.file_name().cmp(&a.file_name()) // 最新的在前
-    });
+    let mut log_files = log_files_in(&logs_dir)?;
+    // 最新的在前（同一天的轮转文件序号可能超过 9，不能按文件名排序）
+    log_files.sort_by_key(|p| Reverse(modified_time(p)));
     
     Ok(log_files)
 }
 
-/// 读取日志文件内容
-pub fn read_log_file(file_path: PathBuf, max_lines: Option<usize>) -> Result<String, String> {
-    use std::io::Read;
-    
-    let mut file = File::open(&file_path)
-        .map_err(|e| format!("打开日志文件失败: {}", e))?;
+/// 读取日志文件内容，压缩文件自动解压
+pub fn read_log_file(file_path: PathBuf, max_lines: Option<usize>) -> AppResult<String> {
+    // 当前日志可能还有未写入的缓冲内容
+    flush();
+
+    let file = File::open(&file_path)
+        .map_err(|e| AppError::io("打开日志文件失败", &e))?;
     
     let mut content = String::new();
-    file.read_to_string(&mut content)
-        .map_err(|e| format!("读取日志文件失败: {}", e))?;
+    let result = if is_compressed(&file_path) {
+        GzDecoder::new(file).read_to_string(&mut content)
+    } else {
+        let mut file = file;
+        file.read_to_string(&mut content)
+    };
+    result.map_err(|e| AppError::io("读取日志文件失败", &e))?;
     
     if let Some(max) = max_lines {
         let lines: Vec<&str> = content.lines().collect();
@@ -111,41 +699,172 @@ pub fn read_log_file(file_path: PathBuf, max_lines: Option<usize>) -> Result<Str
     Ok(content)
 }
 
+/// 读取日志文件并解析为记录，返回符合条件的最后 `max_lines` 条
+pub fn read_log_records(file_path: PathBuf, max_lines: Option<usize>, filter: &LogFilter) -> AppResult<Vec<LogRecord>> {
+    let content = read_log_file(file_path, None)?;
+    let mut records: Vec<LogRecord> = content.lines()
+        .filter_map(LogRecord::parse)
+        .filter(|r| filter.matches(r))
+        .collect();
+    if let Some(max) = max_lines {
+        let start = records.len().saturating_sub(max);
+        records.drain(..start);
+    }
+    Ok(records)
+}
+
 /// 清理旧日志文件（保留最近 N 天）
-pub fn cleanup_old_logs(app_data_dir: PathBuf, keep_days: u32) -> Result<usize, String> {
+pub fn cleanup_old_logs(app_data_dir: PathBuf, keep_days: u32) -> AppResult<usize> {
     let logs_dir = app_data_dir.join("logs");
     
     if !logs_dir.exists() {
         return Ok(0);
     }
     
-    let entries = std::fs::read_dir(&logs_dir)
-        .map_err(|e| format!("读取日志目录失败: {}", e))?;
-    
     let cutoff_date = Local::now().date_naive() - chrono::Duration::days(keep_days as i64);
     let mut deleted_count = 0;
     
-    for entry in entries {
-        if let Ok(entry) = entry {
-            let path = entry.path();
-            if path.is_file() {
-                if let Some(file_name) = path.file_name().and_then(|n| n.to_str()) {
-                    // 尝试从文件名提取日期
-                    if let Some(date_str) = file_name.strip_prefix("gaga-client_")
-                        .and_then(|s| s.strip_suffix(".log")) {
-                        if let Ok(file_date) = chrono::NaiveDate::parse_from_str(date_str, "%Y-%m-%d") {
-                            if file_date < cutoff_date {
-                                if std::fs::remove_file(&path).is_ok() {
-                                    deleted_count += 1;
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+    for path in log_files_in(&logs_dir)? {
+        // 从文件名提取日期（当天、轮转和压缩的文件都以日期开头）
+        let file_date = file_name(&path).strip_prefix(LOG_FILE_PREFIX)
+            .and_then(|s| s.get(..10))
+            .and_then(|s| NaiveDate::parse_from_str(s, "%Y-%m-%d").ok());
+        if file_date.is_some_and(|d| d < cutoff_date) && std::fs::remove_file(&path).is_ok() {
+            deleted_count += 1;
+        }
+    }
+
+    // 任务日志按最后修改日期清理
+    for path in log_files_in(&logs_dir.join(JOB_LOG_DIR)).unwrap_or_default() {
+        let modified = modified_time(&path)
+            .map(|t| chrono::DateTime::<Local>::from(t).date_naive());
+        if modified.is_some_and(|d| d < cutoff_date) && std::fs::remove_file(&path).is_ok() {
+            deleted_count += 1;
         }
     }
     
     Ok(deleted_count)
 }
 
+/// 日志目录超过总大小上限时从最旧的文件开始删除（不删除正在写入的日志、审计日志和任务日志），返回删除的文件数
+pub fn enforce_total_size() -> usize {
+    let max_total = MAX_TOTAL_SIZE.load(Ordering::Relaxed);
+    let (logs_dir, active_path) = match ACTIVE_LOG.lock().unwrap().as_ref() {
+        Some(log) => (log.logs_dir.clone(), log.path.clone()),
+        None => return 0,
+    };
+    if max_total == 0 {
+        return 0;
+    }
+
+    let job_logs = log_files_in(&logs_dir.join(JOB_LOG_DIR)).unwrap_or_default();
+    let mut files: Vec<(PathBuf, u64)> = log_files_in(&logs_dir).unwrap_or_default().into_iter()
+        .chain(job_logs)
+        .filter_map(|p| std::fs::metadata(&p).ok().map(|m| (p, m.len())))
+        .collect();
+    let mut total: u64 = files.iter().map(|(_, size)| size).sum();
+    files.sort_by_key(|(p, _)| modified_time(p));
+
+    // 删除期间持有锁，日志线程不会在此时打开新的任务日志
+    let open_jobs = OPEN_JOB_LOGS.lock().unwrap();
+    let is_open_job_log = |path: &Path| {
+        path.parent().is_some_and(|dir| dir.ends_with(JOB_LOG_DIR))
+            && path.file_stem().and_then(|s| s.to_str()).is_some_and(|id| open_jobs.contains(id))
+    };
+    let mut deleted_count = 0;
+    for (path, size) in files {
+        if total <= max_total {
+            break;
+        }
+        if path == active_path || file_name(&path) == AUDIT_LOG_FILE || is_open_job_log(&path) {
+            continue;
+        }
+        if std::fs::remove_file(&path).is_ok() {
+            total -= size;
+            deleted_count += 1;
+        }
+    }
+    drop(open_jobs);
+    if deleted_count > 0 {
+        log::info!("日志目录超过大小上限，已删除 {} 个旧日志文件", deleted_count);
+    }
+    deleted_count
+}
+
+fn file_name(path: &Path) -> &str {
+    path.file_name().and_then(|n| n.to_str()).unwrap_or("")
+}
+
+fn is_compressed(path: &Path) -> bool {
+    file_name(path).ends_with(GZ_SUFFIX)
+}
+
+fn modified_time(path: &Path) -> Option<std::time::SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// 日志目录中的 `.log` 和 `.log.gz` 文件
+fn log_files_in(logs_dir: &Path) -> AppResult<Vec<PathBuf>> {
+    let entries = std::fs::read_dir(logs_dir)
+        .map_err(|e| AppError::io("读取日志目录失败", &e))?;
+    Ok(entries.flatten()
+        .map(|entry| entry.path())
+        .filter(|p| p.is_file())
+        .filter(|p| {
+            let name = file_name(p);
+            name.ends_with(".log") || name.ends_with(".log.gz")
+        })
+        .collect())
+}
+
+/// 同一天已使用的最大轮转序号加一
+fn next_rotation_index(logs_dir: &Path, date: NaiveDate) -> u32 {
+    next_index(logs_dir, &format!("{}{}.", LOG_FILE_PREFIX, date.format("%Y-%m-%d")))
+}
+
+/// `<prefix><序号>.log(.gz)` 中已使用的最大序号加一
+fn next_index(logs_dir: &Path, prefix: &str) -> u32 {
+    log_files_in(logs_dir).unwrap_or_default().iter()
+        .filter_map(|p| {
+            let name = file_name(p).strip_prefix(prefix)?;
+            let index = name.strip_suffix(".log.gz").or_else(|| name.strip_suffix(".log"))?;
+            index.parse::<u32>().ok()
+        })
+        .max()
+        .map_or(1, |max| max + 1)
+}
+
+/// 在后台压缩已轮转的日志文件，完成后按总大小清理
+fn compress_in_background(files: Vec<PathBuf>) {
+    std::thread::spawn(move || {
+        for path in files {
+            if let Err(e) = compress_file(&path) {
+                log::warn!("压缩日志文件失败 ({:?}): {}", path, e);
+            }
+        }
+        enforce_total_size();
+    });
+}
+
+/// 将日志压缩为 `.gz`（先写入临时文件再重命名），成功后删除原文件
+fn compress_file(path: &Path) -> AppResult<()> {
+    let target = PathBuf::from(format!("{}{}", path.to_string_lossy(), GZ_SUFFIX));
+    let temp = PathBuf::from(format!("{}.tmp", target.to_string_lossy()));
+
+    let mut source = File::open(path)
+        .map_err(|e| AppError::io("打开日志文件失败", &e))?;
+    let output = File::create(&temp)
+        .map_err(|e| AppError::io("创建压缩文件失败", &e))?;
+    let mut encoder = GzEncoder::new(output, Compression::default());
+    std::io::copy(&mut source, &mut encoder)
+        .and_then(|_| encoder.finish())
+        .map_err(|e| {
+            let _ = std::fs::remove_file(&temp);
+            AppError::io("压缩日志文件失败", &e)
+        })?;
+    std::fs::rename(&temp, &target)
+        .map_err(|e| AppError::io("保存压缩文件失败", &e))?;
+    std::fs::remove_file(path)
+        .map_err(|e| AppError::io("删除已压缩的日志文件失败", &e))?;
+    Ok(())
+}
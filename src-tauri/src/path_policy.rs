@@ -0,0 +1,198 @@
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use once_cell::sync::Lazy;
+use tauri::command;
+use tauri_plugin_dialog::DialogExt;
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::logger;
+
+/// 用户选择的保存目录列表文件名（位于应用数据目录）
+const ROOTS_FILE: &str = "allowed_dirs.json";
+
+/// 允许读写的根目录（均已规范化）
+static ROOTS: Lazy<Mutex<Roots>> = Lazy::new(|| Mutex::new(Roots::default()));
+
+#[derive(Default)]
+struct Roots {
+    /// 下载目录、应用数据目录
+    builtin: Vec<PathBuf>,
+    /// 用户选择的保存目录
+    user: Vec<PathBuf>,
+    /// 用户目录列表文件路径
+    store: Option<PathBuf>,
+}
+
+impl Roots {
+    fn contains(&self, path: &Path) -> bool {
+        self.builtin.iter().chain(self.user.iter()).any(|root| path.starts_with(root))
+    }
+
+    fn is_root(&self, path: &Path) -> bool {
+        self.builtin.iter().chain(self.user.iter()).any(|root| path == root)
+    }
+
+    fn save(&self) {
+        let path = match &self.store {
+            Some(path) => path,
+            None => return,
+        };
+        let dirs: Vec<String> = self.user.iter().map(|p| p.to_string_lossy().to_string()).collect();
+        let result = serde_json::to_string_pretty(&dirs)
+            .map_err(|e| e.to_string())
+            .and_then(|content| std::fs::write(path, content).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            log::error!("保存目录列表失败: {}", e);
+        }
+    }
+}
+
+/// 访问类型
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Access {
+    Read,
+    Write,
+    Delete,
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Access::Read => write!(f, "读取"),
+            Access::Write => write!(f, "写入"),
+            Access::Delete => write!(f, "删除"),
+        }
+    }
+}
+
+/// 初始化允许的根目录：下载目录、应用数据目录，以及上次保存的用户目录
+pub fn init(app_data_dir: PathBuf) {
+    let mut roots = ROOTS.lock().unwrap();
+    roots.builtin = [dirs::download_dir(), Some(app_data_dir.clone())]
+        .into_iter()
+        .flatten()
+        .filter_map(|dir| dir.canonicalize().ok())
+        .collect();
+
+    let store = app_data_dir.join(ROOTS_FILE);
+    let saved: Vec<String> = std::fs::read_to_string(&store).ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    roots.user = saved.iter().filter_map(|dir| Path::new(dir).canonicalize().ok()).collect();
+    roots.store = Some(store);
+    log::info!("允许访问的目录: {:?} {:?}", roots.builtin, roots.user);
+}
+
+/// 规范化路径并解析符号链接；尚不存在的路径按最近的已存在上级目录规范化后拼接剩余部分。
+/// 删除时只规范化上级目录，删除的是链接本身而不是链接目标
+fn canonicalize(path: &Path, access: Access) -> Option<PathBuf> {
+    if !path.is_absolute() {
+        return None;
+    }
+    if access == Access::Delete {
+        let parent = path.parent()?.canonicalize().ok()?;
+        return Some(parent.join(path.file_name()?));
+    }
+    if let Ok(canonical) = path.canonicalize() {
+        return Some(canonical);
+    }
+
+    let mut existing = path;
+    let mut rest = Vec::new();
+    while !existing.exists() {
+        match existing.components().next_back() {
+            Some(Component::Normal(name)) => rest.push(name),
+            _ => return None,
+        }
+        existing = existing.parent()?;
+    }
+    let mut canonical = existing.canonicalize().ok()?;
+    for name in rest.iter().rev() {
+        canonical.push(name);
+    }
+    Some(canonical)
+}
+
+/// 拒绝访问：写入审计日志并返回错误
+fn deny(path: &str, access: Access, reason: &str) -> AppError {
+    log::warn!("拒绝{}: {} ({})", access, path, reason);
+    logger::write_audit_log(&format!("拒绝{}: {} ({})", access, path, reason));
+    AppError::new(ErrorCode::PathNotAllowed, format!("不允许{}该路径: {}", access, path))
+        .with_details(reason)
+}
+
+/// 检查路径是否位于允许的根目录下，返回规范化后的路径
+pub fn check(path: &str, access: Access) -> AppResult<PathBuf> {
+    if let Err(e) = crate::validate_path_safety(path) {
+        return Err(deny(path, access, &e.message));
+    }
+    let canonical = match canonicalize(Path::new(path), access) {
+        Some(canonical) => canonical,
+        None => return Err(deny(path, access, "无法解析为绝对路径")),
+    };
+
+    let roots = ROOTS.lock().unwrap();
+    if !roots.contains(&canonical) {
+        return Err(deny(path, access, &format!("{:?} 不在允许的目录中", canonical)));
+    }
+    if access == Access::Delete && roots.is_root(&canonical) {
+        return Err(deny(path, access, "不能删除根目录"));
+    }
+    Ok(canonical)
+}
+
+/// 添加用户选择的保存目录
+pub fn allow_dir(dir: &Path) -> AppResult<PathBuf> {
+    let canonical = dir.canonicalize()
+        .map_err(|e| AppError::io(&format!("目录不可用 ({:?})", dir), &e))?;
+    if !canonical.is_dir() {
+        return Err(AppError::invalid_argument(format!("不是目录: {:?}", dir)));
+    }
+    let mut roots = ROOTS.lock().unwrap();
+    if !roots.user.contains(&canonical) {
+        logger::write_audit_log(&format!("添加保存目录: {:?}", canonical));
+        roots.user.push(canonical.clone());
+        roots.save();
+    }
+    Ok(canonical)
+}
+
+/// 添加未经对话框选择的目录：只能位于用户主目录下，且不能是主目录本身
+pub fn allow_requested_dir(path: &str) -> AppResult<PathBuf> {
+    let home = dirs::home_dir().and_then(|home| home.canonicalize().ok());
+    let canonical = canonicalize(Path::new(path), Access::Read);
+    let permitted = match (&home, &canonical) {
+        (Some(home), Some(dir)) => dir.starts_with(home) && dir != home,
+        _ => false,
+    };
+    if !permitted {
+        return Err(deny(path, Access::Write, "只能添加用户主目录下的目录，其他目录请通过对话框选择"));
+    }
+    allow_dir(Path::new(path))
+}
+
+// ==================== 目录授权命令 ====================
+
+/// 通过系统对话框选择保存目录并允许访问，返回所选目录（取消时返回 None）
+#[command]
+pub async fn pick_save_dir(app: tauri::AppHandle, title: Option<String>) -> AppResult<Option<String>> {
+    let mut dialog = app.dialog().file();
+    if let Some(title) = title {
+        dialog = dialog.set_title(title);
+    }
+    let selected = match dialog.blocking_pick_folder() {
+        Some(selected) => selected,
+        None => return Ok(None),
+    };
+    let dir = selected.into_path()
+        .map_err(|e| AppError::invalid_argument(format!("无法识别所选目录: {}", e)))?;
+    let dir = allow_dir(&dir)?;
+    Ok(Some(dir.to_string_lossy().to_string()))
+}
+
+/// 允许访问以前选择的保存目录（如设置中的默认下载目录）
+#[command]
+pub async fn allow_save_dir(path: String) -> AppResult<String> {
+    let dir = allow_requested_dir(&path)?;
+    Ok(dir.to_string_lossy().to_string())
+}
@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+use tauri::Emitter;
+
+/// 下载任务阶段
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum DownloadPhase {
+    Queued,
+    Loading,
+    Downloading,
+    Decrypting,
+    Merging,
+    /// 后期处理烧录字幕
+    Burning,
+    Paused,
+    Completed,
+    Failed,
+    Cancelled,
+    /// 后期处理失败：下载已完成，下载文件保留
+    #[serde(rename = "postProcessFailed")]
+    PostProcessFailed,
+}
+
+impl DownloadPhase {
+    /// 是否为终止阶段
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, DownloadPhase::Completed | DownloadPhase::Failed | DownloadPhase::Cancelled
+            | DownloadPhase::PostProcessFailed)
+    }
+
+    /// 进行中阶段的先后顺序；下载器输出只能推动阶段前进
+    fn rank(&self) -> Option<u8> {
+        match self {
+            DownloadPhase::Queued => Some(0),
+            DownloadPhase::Loading => Some(1),
+            DownloadPhase::Downloading => Some(2),
+            DownloadPhase::Decrypting => Some(3),
+            DownloadPhase::Merging => Some(4),
+            DownloadPhase::Burning => Some(5),
+            _ => None,
+        }
+    }
+}
+
+/// 阶段变化
+#[derive(Clone, Copy, Debug)]
+pub struct PhaseChange {
+    pub previous: DownloadPhase,
+    pub phase: DownloadPhase,
+}
+
+/// 阶段状态机
+///
+/// 转换规则：
+/// - 终止阶段（完成/失败/取消/后期处理失败）之后不再变化；
+/// - 进行中阶段只能按 排队 → 加载 → 下载 → 解密 → 合并 → 烧录 的顺序前进；
+/// - 进行中阶段可暂停，恢复时回到暂停前的阶段；
+/// - 任意非终止阶段都可进入终止阶段。
+pub struct PhaseMachine {
+    current: DownloadPhase,
+    before_pause: Option<DownloadPhase>,
+}
+
+impl PhaseMachine {
+    pub fn new(initial: DownloadPhase) -> Self {
+        PhaseMachine { current: initial, before_pause: None }
+    }
+
+    pub fn current(&self) -> DownloadPhase {
+        self.current
+    }
+
+    fn can_transition(&self, to: DownloadPhase) -> bool {
+        if self.current.is_terminal() || self.current == to {
+            return false;
+        }
+        if to.is_terminal() {
+            return true;
+        }
+        match (self.current, to) {
+            (DownloadPhase::Paused, _) => false, // 只能通过 resume 离开暂停
+            (_, DownloadPhase::Paused) => true,
+            (from, to) => match (from.rank(), to.rank()) {
+                (Some(a), Some(b)) => b > a,
+                _ => false,
+            },
+        }
+    }
+
+    /// 尝试转换阶段，不允许的转换被忽略并返回 None
+    pub fn advance(&mut self, to: DownloadPhase) -> Option<PhaseChange> {
+        if !self.can_transition(to) {
+            return None;
+        }
+        let previous = self.current;
+        if to == DownloadPhase::Paused {
+            self.before_pause = Some(previous);
+        }
+        self.current = to;
+        Some(PhaseChange { previous, phase: to })
+    }
+
+    /// 从暂停恢复到暂停前的阶段
+    pub fn resume(&mut self) -> Option<PhaseChange> {
+        if self.current != DownloadPhase::Paused {
+            return None;
+        }
+        let phase = self.before_pause.take().unwrap_or(DownloadPhase::Downloading);
+        self.current = phase;
+        Some(PhaseChange { previous: DownloadPhase::Paused, phase })
+    }
+}
+
+/// 根据 N_m3u8DL-RE 输出行推断阶段
+pub fn phase_for_line(line: &str) -> Option<DownloadPhase> {
+    let trimmed = line.trim();
+    if trimmed.contains("Loading URL") {
+        Some(DownloadPhase::Loading)
+    } else if trimmed.contains("Start downloading") {
+        Some(DownloadPhase::Downloading)
+    } else if trimmed.contains("Decrypting") {
+        Some(DownloadPhase::Decrypting)
+    } else if trimmed.contains("Binary merging") || trimmed.contains("Muxing") {
+        Some(DownloadPhase::Merging)
+    } else {
+        None
+    }
+}
+
+/// 阶段变化事件（`job-phase`）
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct JobPhaseEvent {
+    job_id: String,
+    phase: DownloadPhase,
+    previous: Option<DownloadPhase>,
+    timestamp: String,
+}
+
+/// 通知所有窗口任务阶段变化
+pub fn emit(app: &tauri::AppHandle, job_id: &str, phase: DownloadPhase, previous: Option<DownloadPhase>) {
+    log::info!("任务阶段: {} {:?} -> {:?}", job_id, previous, phase);
+    let _ = app.emit("job-phase", JobPhaseEvent {
+        job_id: job_id.to_string(),
+        phase,
+        previous,
+        timestamp: chrono::Utc::now().to_rfc3339(),
+    });
+}
+
+/// 通知阶段变化
+pub fn emit_change(app: &tauri::AppHandle, job_id: &str, change: PhaseChange) {
+    emit(app, job_id, change.phase, Some(change.previous));
+}
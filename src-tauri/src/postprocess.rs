@@ -0,0 +1,417 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use tauri::{command, Emitter};
+use crate::error::{AppError, AppResult};
+use crate::ffmpeg::{self, FfmpegParser};
+use crate::jobs::{self, Job, JobKind};
+use crate::history;
+use crate::intermediates::{self, IntermediateKind};
+use crate::logger;
+use crate::path_policy::{self, Access};
+use crate::phase::{self, DownloadPhase};
+use crate::runner::{self, EventChannel, ProcessSpec};
+use crate::SubtitleStyle;
+
+/// 等待下载输出文件大小稳定的检查次数
+const STABLE_CHECK_ATTEMPTS: u32 = 5;
+
+/// 两次文件大小检查之间的间隔
+const STABLE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 字幕处理方式
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SubtitleMode {
+    /// 不处理字幕，仅输出视频
+    #[default]
+    None,
+    /// 封装为软字幕轨道
+    Mux,
+    /// 烧录为硬字幕
+    Burn,
+}
+
+/// 后期处理请求
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PostProcessRequest {
+    /// 下载输出目录
+    pub output_dir: String,
+    /// 视频标题（即 `--save-name`），用于命名合并和处理后的文件
+    pub title: String,
+    /// 复用下载任务的 ID，处理该任务下载完成时记录的输出文件；未指定时生成新 ID（没有可处理的文件）
+    pub job_id: Option<String>,
+    #[serde(default)]
+    pub subtitle_mode: SubtitleMode,
+    pub subtitle_style: Option<SubtitleStyle>,
+    /// 成功后是否删除中间文件，默认删除
+    pub cleanup: Option<bool>,
+}
+
+/// 后期处理步骤
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum PostProcessStep {
+    Waiting,
+    Merging,
+    Muxing,
+    Burning,
+    Copying,
+    Cleanup,
+    Done,
+    /// 处理失败，下载文件保留
+    Failed,
+}
+
+/// 后期处理进度事件（`post-process-progress`）
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct PostProcessProgress {
+    job_id: String,
+    step: PostProcessStep,
+    step_index: usize,
+    total_steps: usize,
+    message: String,
+    timestamp: String,
+}
+
+/// 下载器为任务生成的文件
+struct DownloadOutputs {
+    video: PathBuf,
+    audio: Option<PathBuf>,
+    subtitle: Option<PathBuf>,
+}
+
+/// 流水线运行状态：步骤计数与本次运行产生的文件
+struct Pipeline<'a> {
+    app: &'a tauri::AppHandle,
+    job: &'a Job,
+    ffmpeg: String,
+    step_index: usize,
+    total_steps: usize,
+    /// 输入视频时长（秒），用于计算 ffmpeg 进度
+    duration: Option<f64>,
+    /// 本次运行创建的文件，失败或取消时删除
+    created: Vec<PathBuf>,
+}
+
+impl Pipeline<'_> {
+    fn report(&mut self, step: PostProcessStep, message: &str) {
+        if !matches!(step, PostProcessStep::Done | PostProcessStep::Failed) {
+            self.step_index = (self.step_index + 1).min(self.total_steps);
+        }
+        log::info!("后期处理 [{}] {}", self.job.id, message);
+        let _ = logger::write_job_log("ffmpeg", "INFO", &self.job.id, Some(self.job.phase()), message);
+        let _ = self.app.emit("post-process-progress", PostProcessProgress {
+            job_id: self.job.id.clone(),
+            step,
+            step_index: self.step_index,
+            total_steps: self.total_steps,
+            message: message.to_string(),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        });
+    }
+
+    fn advance_phase(&self, to: DownloadPhase) {
+        if let Some(change) = self.job.advance_phase(to) {
+            phase::emit_change(self.app, &self.job.id, change);
+        }
+    }
+
+    fn check_cancelled(&self) -> AppResult<()> {
+        if self.job.is_cancelled() {
+            return Err(AppError::cancelled("后期处理已取消"));
+        }
+        Ok(())
+    }
+
+    /// 运行一次 ffmpeg，输出写入 `output` 并登记为本次创建的文件
+    fn run_ffmpeg(&mut self, args: Vec<String>, output: &Path) -> AppResult<()> {
+        self.check_cancelled()?;
+        self.created.push(output.to_path_buf());
+        log::info!("ffmpeg 参数: {:?}", args);
+
+        let spec = ProcessSpec {
+            tool: "ffmpeg".to_string(),
+            program: self.ffmpeg.clone(),
+            args: ffmpeg::with_progress_args(&args),
+            working_dir: None,
+            timeouts: runner::default_timeouts(),
+        };
+        let parser = Arc::new(FfmpegParser::new(self.duration, Some(&self.job.id)));
+        let channel = EventChannel::new(self.app, "ffmpeg-progress");
+        let (child, capture) = runner::spawn(&spec, parser, Some(channel))?;
+        self.job.replace_child(child);
+
+        let mut watchdog = capture.watchdog();
+        let status = match self.job.wait(&mut watchdog) {
+            Ok(status) => status,
+            Err(e) => {
+                capture.discard();
+                return Err(e.into());
+            }
+        };
+        let outcome = capture.finish(status);
+        self.check_cancelled()?;
+
+        if outcome.success() {
+            Ok(())
+        } else {
+            Err(AppError::process_failed("ffmpeg 执行失败", &outcome))
+        }
+    }
+
+    /// 删除本次运行创建的文件，保证失败时不留下半成品
+    fn rollback(&mut self) {
+        for path in self.created.drain(..) {
+            if path.exists() {
+                if let Err(e) = std::fs::remove_file(&path) {
+                    log::warn!("删除未完成的输出失败: {:?} ({})", path, e);
+                }
+            }
+        }
+    }
+}
+
+/// 下载完成时为该任务记录的输出文件
+fn recorded_outputs(job_id: &str) -> Option<DownloadOutputs> {
+    let files = intermediates::job_files(job_id);
+    let first = |kind: IntermediateKind| files.iter().find(|f| f.kind == kind).map(|f| PathBuf::from(&f.path));
+    Some(DownloadOutputs {
+        video: first(IntermediateKind::Video)?,
+        audio: first(IntermediateKind::Audio),
+        subtitle: first(IntermediateKind::Subtitle),
+    })
+}
+
+fn file_sizes(paths: &[&Path]) -> Vec<u64> {
+    paths.iter()
+        .map(|p| std::fs::metadata(p).map(|m| m.len()).unwrap_or(0))
+        .collect()
+}
+
+/// 等待文件大小稳定（下载器可能仍在写入）
+fn wait_stable(job: &Job, paths: &[&Path]) -> AppResult<()> {
+    for _ in 0..STABLE_CHECK_ATTEMPTS {
+        let before = file_sizes(paths);
+        std::thread::sleep(STABLE_CHECK_INTERVAL);
+        if job.is_cancelled() {
+            return Err(AppError::cancelled("后期处理已取消"));
+        }
+        let after = file_sizes(paths);
+        if before.iter().all(|s| *s > 0) && before == after {
+            return Ok(());
+        }
+    }
+    Err(AppError::invalid_state("文件未稳定"))
+}
+
+fn path_arg(path: &Path) -> String {
+    path.to_string_lossy().to_string()
+}
+
+/// 依次执行 合并 → 字幕处理 → 输出 → 清理，返回最终输出路径；
+/// 任务没有记录视频文件时返回 None
+fn run_pipeline(pipeline: &mut Pipeline, request: &PostProcessRequest) -> AppResult<Option<PathBuf>> {
+    let dir = PathBuf::from(&request.output_dir);
+    let job_id = pipeline.job.id.clone();
+    let outputs = match recorded_outputs(&job_id) {
+        Some(outputs) => outputs,
+        None => return Ok(None),
+    };
+    let subtitle = match request.subtitle_mode {
+        SubtitleMode::None => None,
+        _ => outputs.subtitle.as_deref(),
+    };
+    let cleanup = request.cleanup.unwrap_or(true);
+
+    pipeline.total_steps = 2 + usize::from(outputs.audio.is_some()) + usize::from(cleanup);
+
+    let mut inputs: Vec<&Path> = vec![&outputs.video];
+    if let Some(audio) = &outputs.audio {
+        inputs.push(audio);
+    }
+    pipeline.report(PostProcessStep::Waiting, "等待下载文件写入完成...");
+    wait_stable(pipeline.job, &inputs)?;
+    pipeline.duration = ffmpeg::probe_duration(&pipeline.ffmpeg, &path_arg(&outputs.video));
+
+    let merged_path = dir.join(format!("{}_merged.mp4", request.title));
+    let final_path = dir.join(format!("{}_processed.mp4", request.title));
+    // 先写入临时文件，成功后再重命名，避免留下不完整的最终输出
+    let partial_path = dir.join(format!("{}_processed.part.mp4", request.title));
+
+    pipeline.advance_phase(DownloadPhase::Merging);
+    let mut source = outputs.video.clone();
+    if let Some(audio) = &outputs.audio {
+        pipeline.report(PostProcessStep::Merging, "执行音视频合并...");
+        pipeline.run_ffmpeg(vec![
+            "-y".to_string(),
+            "-i".to_string(), path_arg(&outputs.video),
+            "-i".to_string(), path_arg(audio),
+            "-map".to_string(), "0:v:0".to_string(),
+            "-map".to_string(), "1:a:0".to_string(),
+            "-c".to_string(), "copy".to_string(),
+            path_arg(&merged_path),
+        ], &merged_path)?;
+        intermediates::record(&job_id, &merged_path, IntermediateKind::Merged);
+        source = merged_path.clone();
+    }
+
+    match (request.subtitle_mode, subtitle) {
+        (SubtitleMode::Burn, Some(subtitle)) => {
+            pipeline.advance_phase(DownloadPhase::Burning);
+            let encoder = crate::detect_hardware_encoder();
+            pipeline.report(PostProcessStep::Burning, &format!("烧录字幕，编码器: {}", encoder));
+            let style = request.subtitle_style.as_ref();
+            let result = pipeline.run_ffmpeg(
+                crate::build_burn_subtitle_args(
+                    &path_arg(&source), &path_arg(subtitle), &path_arg(&partial_path), style, &encoder),
+                &partial_path,
+            );
+            if let Err(e) = result {
+                pipeline.check_cancelled()?;
+                if encoder == "libx264" {
+                    return Err(e);
+                }
+                log::warn!("硬件编码失败，回退到软件编码: {}", e);
+                pipeline.run_ffmpeg(
+                    crate::build_burn_subtitle_args(
+                        &path_arg(&source), &path_arg(subtitle), &path_arg(&partial_path), style, "libx264"),
+                    &partial_path,
+                )?;
+            }
+        }
+        (SubtitleMode::Mux, Some(subtitle)) => {
+            pipeline.report(PostProcessStep::Muxing, "封装字幕轨道...");
+            pipeline.run_ffmpeg(vec![
+                "-y".to_string(),
+                "-i".to_string(), path_arg(&source),
+                "-i".to_string(), path_arg(subtitle),
+                "-map".to_string(), "0".to_string(),
+                "-map".to_string(), "1".to_string(),
+                "-c".to_string(), "copy".to_string(),
+                "-c:s".to_string(), "mov_text".to_string(),
+                path_arg(&partial_path),
+            ], &partial_path)?;
+        }
+        _ => {
+            pipeline.report(PostProcessStep::Copying, "复制输出...");
+            pipeline.run_ffmpeg(vec![
+                "-y".to_string(),
+                "-i".to_string(), path_arg(&source),
+                "-c".to_string(), "copy".to_string(),
+                path_arg(&partial_path),
+            ], &partial_path)?;
+        }
+    }
+
+    pipeline.check_cancelled()?;
+    std::fs::rename(&partial_path, &final_path)
+        .map_err(|e| AppError::io(&format!("保存输出文件失败 ({:?})", final_path), &e))?;
+    pipeline.created.clear();
+
+    if cleanup {
+        pipeline.report(PostProcessStep::Cleanup, "清理中间文件...");
+        let mut paths = vec![outputs.video.clone()];
+        if let Some(audio) = &outputs.audio {
+            paths.push(audio.clone());
+            paths.push(merged_path);
+        }
+        for path in paths {
+            if let Err(e) = intermediates::delete(&path, None) {
+                log::warn!("清理中间文件失败: {:?} ({})", path, e);
+            }
+        }
+    } else if outputs.audio.is_some() {
+        // 合并产物只是中间结果，保留原始下载文件即可
+        if let Err(e) = intermediates::delete(&merged_path, Some(false)) {
+            log::warn!("删除合并文件失败: {:?} ({})", merged_path, e);
+        }
+    }
+
+    Ok(Some(final_path))
+}
+
+/// 运行流水线并发送最终阶段
+fn supervise_pipeline(app: tauri::AppHandle, job: Arc<Job>, request: PostProcessRequest, ffmpeg: String) {
+    let mut pipeline = Pipeline {
+        app: &app,
+        job: &job,
+        ffmpeg,
+        step_index: 0,
+        total_steps: 0,
+        duration: None,
+        created: Vec::new(),
+    };
+
+    let result = run_pipeline(&mut pipeline, &request);
+    if result.is_err() {
+        pipeline.rollback();
+    }
+
+    let final_phase = match result {
+        Ok(Some(path)) => {
+            pipeline.report(PostProcessStep::Done, &format!("处理完成，输出文件位于: {}", path.to_string_lossy()));
+            history::job_output(&app, &job.id, &path);
+            DownloadPhase::Completed
+        }
+        Ok(None) => {
+            pipeline.report(PostProcessStep::Done, "未找到视频文件，跳过后处理。可能无需合并。");
+            DownloadPhase::Completed
+        }
+        Err(_) if job.is_cancelled() => {
+            log::info!("后期处理已取消: {}", job.id);
+            DownloadPhase::Cancelled
+        }
+        Err(e) => {
+            // 下载已完成，不覆盖下载任务的完成状态，只记录后期处理失败
+            log::error!("后期处理失败: {} ({})", job.id, e);
+            let message = format!("后期处理失败: {}", e.message);
+            pipeline.report(PostProcessStep::Failed, &message);
+            history::post_process_failed(&app, &job.id, &message);
+            DownloadPhase::PostProcessFailed
+        }
+    };
+
+    jobs::remove(&job.id);
+    logger::close_job_log(&job.id);
+    if let Some(change) = job.advance_phase(final_phase) {
+        phase::emit_change(&app, &job.id, change);
+    }
+}
+
+// ==================== 后期处理命令 ====================
+
+/// 对下载输出执行后期处理（合并、字幕、清理），返回任务 ID；
+/// 处理在后台进行，进度通过 `post-process-progress` 和 `job-phase` 事件通知，可用 `cancel_job` 取消
+#[command]
+pub async fn post_process_job(app: tauri::AppHandle, request: PostProcessRequest) -> AppResult<String> {
+    path_policy::check(&request.output_dir, Access::Write)?;
+    if request.title.is_empty() || request.title.contains(['/', '\\']) || request.title.contains("..") {
+        return Err(AppError::invalid_argument("视频标题不合法"));
+    }
+
+    // ffmpeg 通常需要系统安装，先尝试资源目录，再尝试系统
+    let tool_path = crate::resolve_tool_path(&app, "ffmpeg");
+    let ffmpeg = if tool_path.exists() {
+        tool_path.to_string_lossy().to_string()
+    } else {
+        "ffmpeg".to_string()
+    };
+
+    let job_id = request.job_id.clone().unwrap_or_else(jobs::next_job_id);
+    let spec = ProcessSpec {
+        tool: "ffmpeg".to_string(),
+        program: ffmpeg.clone(),
+        args: Vec::new(),
+        working_dir: None,
+        timeouts: runner::default_timeouts(),
+    };
+    let job = jobs::register(job_id.clone(), JobKind::PostProcess, spec)?;
+    log::info!("开始后期处理: {} ({})", job_id, request.output_dir);
+
+    std::thread::spawn(move || supervise_pipeline(app, job, request, ffmpeg));
+    Ok(job_id)
+}
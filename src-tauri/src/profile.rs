@@ -0,0 +1,256 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tauri::command;
+use tauri_plugin_dialog::DialogExt;
+use crate::error::{AppError, AppResult};
+use crate::history::{self, HistoryRecord};
+use crate::path_policy::{self, Access};
+use crate::{logger, queue, settings};
+
+/// 配置包格式标识
+const PROFILE_FORMAT: &str = "n-m3u8dl-profile";
+
+/// 当前的配置包版本
+const PROFILE_VERSION: u32 = 1;
+
+/// 对话框中的默认文件名
+const DEFAULT_FILE_NAME: &str = "n-m3u8dl-profile.json";
+
+/// 配置包中的排队任务（导入时使用新的任务 ID）
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct QueuedJob {
+    pub args: Vec<String>,
+    pub working_dir: Option<String>,
+    #[serde(default)]
+    pub priority: i32,
+}
+
+/// 配置包（单个 JSON 文件）：配置、工具路径、排队任务和可选的下载历史
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileBundle {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: String,
+    pub app_version: String,
+    /// 配置（不含工具路径），导入时按配置版本迁移
+    pub settings: Map<String, Value>,
+    #[serde(default)]
+    pub tool_paths: BTreeMap<String, String>,
+    #[serde(default)]
+    pub queue: Vec<QueuedJob>,
+    pub history: Option<Vec<HistoryRecord>>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// 导入选项：选择导入的部分，以及冲突时是否覆盖。
+/// 工具路径会被执行，只有明确选择时才导入
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportOptions {
+    #[serde(default = "default_true")]
+    pub settings: bool,
+    #[serde(default)]
+    pub tool_paths: bool,
+    #[serde(default = "default_true")]
+    pub queue: bool,
+    #[serde(default = "default_true")]
+    pub history: bool,
+    /// 历史记录 ID 相同、工具已指定其他路径时覆盖本机的值（默认保留本机的值）
+    #[serde(default)]
+    pub overwrite: bool,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        ImportOptions { settings: true, tool_paths: false, queue: true, history: true, overwrite: false }
+    }
+}
+
+/// 导入结果
+#[derive(Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    pub settings_applied: bool,
+    pub tool_paths_imported: usize,
+    pub tool_paths_skipped: usize,
+    pub queue_imported: usize,
+    pub queue_skipped: usize,
+    pub history_imported: usize,
+    pub history_skipped: usize,
+    /// 被跳过的内容及原因
+    pub warnings: Vec<String>,
+}
+
+/// 生成配置包
+fn build_bundle(app: &tauri::AppHandle, include_history: bool) -> AppResult<ProfileBundle> {
+    let current = settings::get();
+    let mut object = match serde_json::to_value(&current) {
+        Ok(Value::Object(object)) => object,
+        _ => return Err(AppError::internal("序列化配置失败")),
+    };
+    object.remove("toolPaths");
+
+    let queue = queue::pending().into_iter()
+        .map(|item| QueuedJob { args: item.args, working_dir: item.working_dir, priority: item.priority })
+        .collect();
+
+    Ok(ProfileBundle {
+        format: PROFILE_FORMAT.to_string(),
+        version: PROFILE_VERSION,
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        app_version: app.package_info().version.to_string(),
+        settings: object,
+        tool_paths: current.tool_paths,
+        queue,
+        history: include_history.then(history::records),
+    })
+}
+
+/// 按选项导入配置包
+fn apply_bundle(app: &tauri::AppHandle, bundle: ProfileBundle, options: &ImportOptions) -> AppResult<ImportReport> {
+    if bundle.format != PROFILE_FORMAT {
+        return Err(AppError::invalid_argument("不是有效的配置包"));
+    }
+    if bundle.version == 0 || bundle.version > PROFILE_VERSION {
+        return Err(AppError::invalid_argument(format!("不支持的配置包版本: {}", bundle.version)));
+    }
+
+    let mut report = ImportReport::default();
+    let current = settings::get();
+    let mut next = current.clone();
+
+    if options.settings {
+        let (mut imported, _) = settings::parse(bundle.settings);
+        // 下载目录在本机不存在时保留本机的设置
+        let dir = &imported.default_download_dir;
+        if !dir.is_empty() && !Path::new(dir).is_dir() {
+            report.warnings.push(format!("默认下载目录在本机不存在，已保留原设置: {}", dir));
+            imported.default_download_dir = current.default_download_dir.clone();
+        }
+        imported.tool_paths = current.tool_paths.clone();
+        next = imported;
+        report.settings_applied = true;
+    }
+
+    if options.tool_paths {
+        for (tool, path) in bundle.tool_paths {
+            let path = match settings::check_tool_path(&tool, Path::new(&path)) {
+                Ok(canonical) => canonical.to_string_lossy().to_string(),
+                Err(reason) => {
+                    report.warnings.push(format!("工具路径在本机不可用（{}）: {} = {}", reason, tool, path));
+                    report.tool_paths_skipped += 1;
+                    continue;
+                }
+            };
+            match next.tool_paths.get(&tool) {
+                Some(existing) if *existing != path && !options.overwrite => {
+                    report.warnings.push(format!("已保留本机的工具路径: {} = {}", tool, existing));
+                    report.tool_paths_skipped += 1;
+                }
+                _ => {
+                    next.tool_paths.insert(tool, path);
+                    report.tool_paths_imported += 1;
+                }
+            }
+        }
+    }
+
+    if next != current {
+        settings::replace(app, next)?;
+    }
+
+    if options.queue {
+        let pending = queue::pending();
+        for job in bundle.queue {
+            if pending.iter().any(|item| item.args == job.args) {
+                report.queue_skipped += 1;
+                continue;
+            }
+            // 保存目录等参数需在本机允许的范围内
+            if let Err(e) = crate::validate_n_m3u8dl_args(&job.args) {
+                report.warnings.push(format!("跳过排队任务（{}）: {}", e.message, job.args.first().map_or("", |u| u.as_str())));
+                report.queue_skipped += 1;
+                continue;
+            }
+            queue::enqueue(app, job.args, job.working_dir, job.priority);
+            report.queue_imported += 1;
+        }
+    }
+
+    if options.history {
+        if let Some(records) = bundle.history {
+            let (imported, skipped) = history::import(app, records, options.overwrite)?;
+            report.history_imported = imported;
+            report.history_skipped = skipped;
+        }
+    }
+
+    logger::write_audit_log(&format!(
+        "导入配置包: 配置 {}，工具路径 {}，排队任务 {}，历史记录 {}",
+        report.settings_applied, report.tool_paths_imported, report.queue_imported, report.history_imported));
+    Ok(report)
+}
+
+// ==================== 配置包命令 ====================
+
+/// 导出配置包，返回保存的路径；未指定路径时弹出保存对话框（取消时返回 None）
+#[command]
+pub async fn export_profile(app: tauri::AppHandle, path: Option<String>, include_history: Option<bool>) -> AppResult<Option<String>> {
+    let path = match path {
+        Some(path) => path_policy::check(&path, Access::Write)?,
+        None => {
+            let selected = app.dialog().file()
+                .set_title("导出配置包")
+                .set_file_name(DEFAULT_FILE_NAME)
+                .add_filter("配置包", &["json"])
+                .blocking_save_file();
+            match selected {
+                Some(selected) => selected.into_path()
+                    .map_err(|e| AppError::invalid_argument(format!("无法识别所选文件: {}", e)))?,
+                None => return Ok(None),
+            }
+        }
+    };
+
+    let bundle = build_bundle(&app, include_history.unwrap_or(false))?;
+    let content = serde_json::to_string_pretty(&bundle)
+        .map_err(|e| AppError::internal(format!("序列化配置包失败: {}", e)))?;
+    std::fs::write(&path, content)
+        .map_err(|e| AppError::io(&format!("写入配置包失败 ({:?})", path), &e))?;
+    log::info!("配置包已导出: {:?}", path);
+    Ok(Some(path.to_string_lossy().to_string()))
+}
+
+/// 导入配置包；未指定路径时弹出打开对话框（取消时返回 None）
+#[command]
+pub async fn import_profile(app: tauri::AppHandle, path: Option<String>, options: Option<ImportOptions>) -> AppResult<Option<ImportReport>> {
+    let path: PathBuf = match path {
+        Some(path) => path_policy::check(&path, Access::Read)?,
+        None => {
+            let selected = app.dialog().file()
+                .set_title("导入配置包")
+                .add_filter("配置包", &["json"])
+                .blocking_pick_file();
+            match selected {
+                Some(selected) => selected.into_path()
+                    .map_err(|e| AppError::invalid_argument(format!("无法识别所选文件: {}", e)))?,
+                None => return Ok(None),
+            }
+        }
+    };
+
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| AppError::io(&format!("读取配置包失败 ({:?})", path), &e))?;
+    let bundle: ProfileBundle = serde_json::from_str(&content)
+        .map_err(|e| AppError::invalid_argument(format!("配置包格式不正确: {}", e)))?;
+    let report = apply_bundle(&app, bundle, &options.unwrap_or_default())?;
+    log::info!("配置包已导入: {:?}", path);
+    Ok(Some(report))
+}
@@ -0,0 +1,313 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use serde::Serialize;
+
+/// 同一条流两次进度事件之间的最小间隔
+const PROGRESS_EMIT_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 写入日志文件的进度粒度（百分比）
+const PROGRESS_LOG_STEP: i32 = 10;
+
+/// 流类型
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum StreamKind {
+    Video,
+    Audio,
+    Subtitle,
+}
+
+impl StreamKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            StreamKind::Video => "视频",
+            StreamKind::Audio => "音频",
+            StreamKind::Subtitle => "字幕",
+        }
+    }
+}
+
+/// 单条流的下载进度（`download-progress` 事件）
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamProgress {
+    pub job_id: Option<String>,
+    pub stream: StreamKind,
+    /// 流描述，如 `1920x1080 | 4300 Kbps`
+    pub name: String,
+    pub percent: f64,
+    pub downloaded_segments: Option<u64>,
+    pub total_segments: Option<u64>,
+    pub downloaded_bytes: Option<u64>,
+    pub total_bytes: Option<u64>,
+    /// 下载速度（字节/秒）
+    pub speed: Option<f64>,
+    /// 预计剩余时间（秒）
+    pub eta_seconds: Option<u64>,
+    pub timestamp: String,
+}
+
+impl StreamProgress {
+    /// 随下载增加的进度值：已下载字节数，没有时为分片数或百分比（万分之一）
+    pub fn advance_mark(&self) -> u64 {
+        self.downloaded_bytes
+            .or(self.downloaded_segments)
+            .unwrap_or((self.percent.max(0.0) * 100.0) as u64)
+    }
+}
+
+/// 解析 N_m3u8DL-RE 的进度行，例如：
+/// `Vid 1920x1080 | 4300 Kbps ━━━━━━━━━━ 60/119 50.42% 12.30MB/24.50MB 1.97MBps 00:00:06`
+pub fn parse_progress_line(line: &str) -> Option<StreamProgress> {
+    let trimmed = line.trim();
+    let (stream, rest) = if let Some(rest) = trimmed.strip_prefix("Vid ") {
+        (StreamKind::Video, rest)
+    } else if let Some(rest) = trimmed.strip_prefix("Aud ") {
+        (StreamKind::Audio, rest)
+    } else if let Some(rest) = trimmed.strip_prefix("Sub ") {
+        (StreamKind::Subtitle, rest)
+    } else {
+        return None;
+    };
+
+    let mut progress = StreamProgress {
+        job_id: None,
+        stream,
+        name: String::new(),
+        percent: -1.0,
+        downloaded_segments: None,
+        total_segments: None,
+        downloaded_bytes: None,
+        total_bytes: None,
+        speed: None,
+        eta_seconds: None,
+        timestamp: chrono::Utc::now().to_rfc3339(),
+    };
+
+    let mut name_parts: Vec<&str> = Vec::new();
+    let mut in_name = true;
+    for token in rest.split_whitespace() {
+        if token.chars().all(|c| c == '━' || c == '-') {
+            in_name = false;
+            continue;
+        }
+        if let Some(percent) = token.strip_suffix('%').and_then(|p| p.parse::<f64>().ok()) {
+            progress.percent = percent;
+        } else if let Some((done, total)) = parse_pair(token, |s| s.parse::<u64>().ok()) {
+            progress.downloaded_segments = Some(done);
+            progress.total_segments = Some(total);
+        } else if let Some((done, total)) = parse_pair(token, parse_size) {
+            progress.downloaded_bytes = Some(done);
+            progress.total_bytes = Some(total);
+        } else if let Some(speed) = parse_speed(token) {
+            progress.speed = Some(speed);
+        } else if let Some(eta) = parse_duration(token) {
+            progress.eta_seconds = Some(eta);
+        } else if in_name {
+            name_parts.push(token);
+            continue;
+        }
+        in_name = false;
+    }
+
+    // 没有百分比的行只是流信息，不是进度
+    if progress.percent < 0.0 {
+        return None;
+    }
+    progress.name = name_parts.join(" ");
+    Some(progress)
+}
+
+/// 解析 `a/b` 形式的成对数值
+fn parse_pair<T, F: Fn(&str) -> Option<T>>(token: &str, parse: F) -> Option<(T, T)> {
+    let (a, b) = token.split_once('/')?;
+    Some((parse(a)?, parse(b)?))
+}
+
+/// 解析带单位的大小，如 `12.30MB`、`512KB`、`1.06GB`
+fn parse_size(value: &str) -> Option<u64> {
+    let units: [(&str, f64); 5] = [
+        ("TB", 1024f64.powi(4)),
+        ("GB", 1024f64.powi(3)),
+        ("MB", 1024f64.powi(2)),
+        ("KB", 1024.0),
+        ("B", 1.0),
+    ];
+    for (unit, factor) in units {
+        if let Some(number) = value.strip_suffix(unit) {
+            return number.parse::<f64>().ok()
+                .filter(|n| *n >= 0.0)
+                .map(|n| (n * factor) as u64);
+        }
+    }
+    None
+}
+
+/// 解析速度，如 `1.97MBps`、`512KB/s`，返回字节/秒
+fn parse_speed(value: &str) -> Option<f64> {
+    let size = value.strip_suffix("ps").or_else(|| value.strip_suffix("/s"))?;
+    parse_size(size).map(|bytes| bytes as f64)
+}
+
+/// 解析 `hh:mm:ss` 形式的时长，返回秒数
+pub fn parse_duration(value: &str) -> Option<u64> {
+    let parts: Vec<&str> = value.split(':').collect();
+    if parts.len() != 3 {
+        return None;
+    }
+    let h = parts[0].parse::<u64>().ok()?;
+    let m = parts[1].parse::<u64>().ok()?;
+    let s = parts[2].split('.').next()?.parse::<u64>().ok()?;
+    Some(h * 3600 + m * 60 + s)
+}
+
+/// 按流限制进度事件频率
+#[derive(Default)]
+pub struct ProgressTracker {
+    streams: HashMap<(StreamKind, String), TrackedStream>,
+}
+
+struct TrackedStream {
+    last_emit: Instant,
+    last_percent: f64,
+    last_logged_step: i32,
+}
+
+/// 进度更新的处理决定
+pub struct ProgressDecision {
+    /// 是否发送事件
+    pub emit: bool,
+    /// 是否写入日志文件
+    pub log: bool,
+}
+
+impl ProgressTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 首次出现、达到 100% 或距上次发送超过间隔时发送事件；
+    /// 每跨越一个日志粒度写一次日志文件
+    pub fn observe(&mut self, progress: &StreamProgress) -> ProgressDecision {
+        let now = Instant::now();
+        let step = progress.percent.floor() as i32 / PROGRESS_LOG_STEP;
+        let key = (progress.stream, progress.name.clone());
+
+        match self.streams.get_mut(&key) {
+            None => {
+                self.streams.insert(key, TrackedStream {
+                    last_emit: now,
+                    last_percent: progress.percent,
+                    last_logged_step: step,
+                });
+                ProgressDecision { emit: true, log: true }
+            }
+            Some(tracked) => {
+                let finished = progress.percent >= 100.0 && tracked.last_percent < 100.0;
+                let emit = finished || now.duration_since(tracked.last_emit) >= PROGRESS_EMIT_INTERVAL;
+                let log = emit && step > tracked.last_logged_step;
+                if emit {
+                    tracked.last_emit = now;
+                    tracked.last_percent = progress.percent;
+                }
+                if log {
+                    tracked.last_logged_step = step;
+                }
+                ProgressDecision { emit, log }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VIDEO_LINE: &str =
+        "Vid 1920x1080 | 4300 Kbps ━━━━━━━━━━ 60/119 50.42% 12.30MB/24.50MB 1.97MBps 00:00:06";
+
+    #[test]
+    fn parses_video_progress_line() {
+        let progress = parse_progress_line(VIDEO_LINE).unwrap();
+        assert_eq!(progress.stream, StreamKind::Video);
+        assert_eq!(progress.name, "1920x1080 | 4300 Kbps");
+        assert_eq!(progress.percent, 50.42);
+        assert_eq!(progress.downloaded_segments, Some(60));
+        assert_eq!(progress.total_segments, Some(119));
+        assert_eq!(progress.downloaded_bytes, Some((12.30 * 1024.0 * 1024.0) as u64));
+        assert_eq!(progress.total_bytes, Some((24.50 * 1024.0 * 1024.0) as u64));
+        assert_eq!(progress.speed, Some((1.97 * 1024.0 * 1024.0) as u64 as f64));
+        assert_eq!(progress.eta_seconds, Some(6));
+    }
+
+    #[test]
+    fn parses_audio_progress_without_sizes() {
+        let progress = parse_progress_line("Aud 128 Kbps | en ━━━━━━━━━━ 30/60 50.00% 00:01:05").unwrap();
+        assert_eq!(progress.stream, StreamKind::Audio);
+        assert_eq!(progress.name, "128 Kbps | en");
+        assert_eq!(progress.downloaded_segments, Some(30));
+        assert_eq!(progress.downloaded_bytes, None);
+        assert_eq!(progress.eta_seconds, Some(65));
+    }
+
+    #[test]
+    fn ignores_stream_info_and_other_lines() {
+        assert!(parse_progress_line("Vid 1920x1080 | 4300 Kbps | avc1.640028").is_none());
+        assert!(parse_progress_line("12:00:00.000 INFO : Loading URL: https://example.com/a.mpd").is_none());
+        assert!(parse_progress_line("").is_none());
+    }
+
+    #[test]
+    fn parses_sizes_and_speeds() {
+        assert_eq!(parse_size("512KB"), Some(512 * 1024));
+        assert_eq!(parse_size("1.5GB"), Some(3 * 512 * 1024 * 1024));
+        assert_eq!(parse_size("100B"), Some(100));
+        assert_eq!(parse_size("-1MB"), None);
+        assert_eq!(parse_size("12.3"), None);
+        assert_eq!(parse_speed("512KB/s"), Some(512.0 * 1024.0));
+        assert_eq!(parse_speed("2MBps"), Some(2.0 * 1024.0 * 1024.0));
+        assert_eq!(parse_speed("2MB"), None);
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("00:01:05"), Some(65));
+        assert_eq!(parse_duration("01:00:00.500"), Some(3600));
+        assert_eq!(parse_duration("01:05"), None);
+        assert_eq!(parse_duration("aa:00:00"), None);
+    }
+
+    #[test]
+    fn advance_mark_prefers_bytes_then_segments() {
+        let mut progress = parse_progress_line(VIDEO_LINE).unwrap();
+        assert_eq!(progress.advance_mark(), (12.30 * 1024.0 * 1024.0) as u64);
+        progress.downloaded_bytes = None;
+        assert_eq!(progress.advance_mark(), 60);
+        progress.downloaded_segments = None;
+        progress.percent = 50.0;
+        assert_eq!(progress.advance_mark(), 5000);
+    }
+
+    #[test]
+    fn tracker_limits_emits_and_logs_each_step() {
+        let mut tracker = ProgressTracker::new();
+        let mut progress = parse_progress_line(VIDEO_LINE).unwrap();
+
+        let first = tracker.observe(&progress);
+        assert!(first.emit && first.log);
+
+        // 间隔内的更新不发送
+        progress.percent = 55.0;
+        let second = tracker.observe(&progress);
+        assert!(!second.emit && !second.log);
+
+        // 达到 100% 时立即发送并写日志
+        progress.percent = 100.0;
+        let finished = tracker.observe(&progress);
+        assert!(finished.emit && finished.log);
+
+        // 其他流单独计算
+        let audio = parse_progress_line("Aud 128 Kbps | en ━━━━━━━━━━ 30/60 50.00% 00:01:05").unwrap();
+        assert!(tracker.observe(&audio).emit);
+    }
+}
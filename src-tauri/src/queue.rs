@@ -0,0 +1,243 @@
+use std::sync::Mutex;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use tauri::{command, Emitter};
+use crate::download_request::DownloadRequest;
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::job_store::{self, StoredJob, StoredPhase};
+use crate::phase::{self, DownloadPhase};
+use crate::settings;
+
+/// 最大并行下载数上限
+pub const MAX_CONCURRENT_LIMIT: usize = 16;
+
+/// 下载队列
+static QUEUE: Lazy<Mutex<DownloadQueue>> = Lazy::new(|| Mutex::new(DownloadQueue {
+    pending: Vec::new(),
+    running: Vec::new(),
+}));
+
+/// 队列中的下载任务（队列 ID 即启动后的任务 ID）
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueItem {
+    pub id: String,
+    pub args: Vec<String>,
+    pub working_dir: Option<String>,
+    pub priority: i32,
+    pub enqueued_at: String,
+}
+
+struct DownloadQueue {
+    /// 等待中的任务，按启动顺序排列
+    pending: Vec<QueueItem>,
+    /// 已由队列启动、尚未结束的任务
+    running: Vec<QueueItem>,
+}
+
+impl DownloadQueue {
+    /// 按优先级插入：排在所有优先级不低于它的任务之后
+    fn insert_by_priority(&mut self, item: QueueItem) {
+        let index = self.pending.iter()
+            .position(|p| p.priority < item.priority)
+            .unwrap_or(self.pending.len());
+        self.pending.insert(index, item);
+    }
+
+    fn take_pending(&mut self, id: &str) -> Option<QueueItem> {
+        let index = self.pending.iter().position(|p| p.id == id)?;
+        Some(self.pending.remove(index))
+    }
+}
+
+/// 队列状态事件（`queue-state`）
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueSnapshot {
+    pub max_concurrent: usize,
+    pub running: Vec<QueueItem>,
+    pub pending: Vec<QueueItem>,
+}
+
+fn snapshot() -> QueueSnapshot {
+    let queue = QUEUE.lock().unwrap();
+    QueueSnapshot {
+        max_concurrent: max_concurrent(),
+        running: queue.running.clone(),
+        pending: queue.pending.clone(),
+    }
+}
+
+/// 通知所有窗口队列状态变化
+fn emit_queue_state(app: &tauri::AppHandle) {
+    let _ = app.emit("queue-state", snapshot());
+}
+
+/// 最大并行下载数（取自配置 maxConcurrentDownloads）
+fn max_concurrent() -> usize {
+    settings::get().max_concurrent_downloads.clamp(1, MAX_CONCURRENT_LIMIT)
+}
+
+/// 保存等待中任务的顺序，重启后按该顺序恢复
+fn save_order() {
+    let ids: Vec<String> = QUEUE.lock().unwrap().pending.iter().map(|p| p.id.clone()).collect();
+    job_store::reorder(&ids);
+}
+
+/// 在并行数允许的范围内启动等待中的任务（并行数配置变化后也需调用）
+pub fn pump(app: &tauri::AppHandle) {
+    loop {
+        let max = max_concurrent();
+        let item = {
+            let mut queue = QUEUE.lock().unwrap();
+            if queue.running.len() >= max || queue.pending.is_empty() {
+                break;
+            }
+            let item = queue.pending.remove(0);
+            queue.running.push(item.clone());
+            item
+        };
+
+        log::info!("队列启动任务: {}", item.id);
+        if let Err(e) = crate::start_download_job(
+            app.clone(), item.id.clone(), item.args.clone(), item.working_dir.clone()) {
+            log::error!("队列任务启动失败: {} ({})", item.id, e);
+            QUEUE.lock().unwrap().running.retain(|r| r.id != item.id);
+            job_store::remove(&item.id);
+            phase::emit(app, &item.id, DownloadPhase::Failed, Some(DownloadPhase::Queued));
+            crate::emit_job_failed(app, &item.id, &AppError { message: format!("下载启动失败: {}", e.message), ..e });
+        }
+    }
+    emit_queue_state(app);
+}
+
+/// 任务结束时释放并行名额并启动下一个任务
+pub fn on_job_finished(app: &tauri::AppHandle, job_id: &str) {
+    let was_queued = {
+        let mut queue = QUEUE.lock().unwrap();
+        let before = queue.running.len();
+        queue.running.retain(|r| r.id != job_id);
+        queue.running.len() != before
+    };
+    if was_queued {
+        pump(app);
+    }
+}
+
+/// 将持久化的未完成任务重新加入队列（保留原任务 ID 和优先级）；
+/// 按调用顺序排在队尾，以保留保存时的顺序（包括手动调整过的顺序）
+pub fn enqueue_stored(app: &tauri::AppHandle, job: StoredJob) {
+    job_store::set_phase(&job.id, StoredPhase::Queued);
+    phase::emit(app, &job.id, DownloadPhase::Queued, None);
+    QUEUE.lock().unwrap().pending.push(QueueItem {
+        id: job.id,
+        args: job.args,
+        working_dir: job.working_dir,
+        priority: job.priority,
+        enqueued_at: chrono::Utc::now().to_rfc3339(),
+    });
+    save_order();
+    pump(app);
+}
+
+/// 将已校验的下载参数加入队列，返回任务 ID
+pub fn enqueue(app: &tauri::AppHandle, args: Vec<String>, working_dir: Option<String>, priority: i32) -> String {
+    let item = QueueItem {
+        id: crate::jobs::next_job_id(),
+        args,
+        working_dir,
+        priority,
+        enqueued_at: chrono::Utc::now().to_rfc3339(),
+    };
+    let id = item.id.clone();
+    job_store::record(&item.id, &item.args, item.working_dir.as_deref(), item.priority, StoredPhase::Queued);
+    QUEUE.lock().unwrap().insert_by_priority(item);
+    save_order();
+    log::info!("任务已加入队列: {}", id);
+    phase::emit(app, &id, DownloadPhase::Queued, None);
+
+    pump(app);
+    id
+}
+
+/// 队列中等待和已启动任务的参数
+pub fn all_args() -> Vec<Vec<String>> {
+    let queue = QUEUE.lock().unwrap();
+    queue.pending.iter().chain(queue.running.iter()).map(|item| item.args.clone()).collect()
+}
+
+/// 等待中的任务（按启动顺序）
+pub fn pending() -> Vec<QueueItem> {
+    QUEUE.lock().unwrap().pending.clone()
+}
+
+// ==================== 队列管理命令 ====================
+
+/// 将下载任务加入队列，返回任务 ID；优先使用 `request`，`args` 为原始参数（需通过白名单校验）
+#[command]
+pub async fn enqueue_download(
+    app: tauri::AppHandle,
+    request: Option<DownloadRequest>,
+    args: Option<Vec<String>>,
+    working_dir: Option<String>,
+    priority: Option<i32>,
+) -> AppResult<String> {
+    let (args, working_dir) = match (request, args) {
+        (Some(request), _) => (request.to_args()?, working_dir.or(Some(request.save_dir))),
+        (None, Some(args)) if !args.is_empty() => {
+            crate::validate_n_m3u8dl_args(&args)?;
+            (args, working_dir)
+        }
+        _ => return Err(AppError::invalid_argument("参数不能为空")),
+    };
+
+    Ok(enqueue(&app, args, working_dir, priority.unwrap_or(0)))
+}
+
+/// 从队列中移除尚未启动的任务
+#[command]
+pub async fn remove_from_queue(app: tauri::AppHandle, job_id: String) -> AppResult<()> {
+    QUEUE.lock().unwrap().take_pending(&job_id)
+        .ok_or_else(|| AppError::new(ErrorCode::JobNotFound, format!("队列中不存在等待中的任务: {}", job_id)))?;
+    job_store::remove(&job_id);
+    phase::emit(&app, &job_id, DownloadPhase::Cancelled, Some(DownloadPhase::Queued));
+    emit_queue_state(&app);
+    Ok(())
+}
+
+/// 将等待中的任务移动到指定位置（0 为最先启动）
+#[command]
+pub async fn move_queue_item(app: tauri::AppHandle, job_id: String, position: usize) -> AppResult<()> {
+    {
+        let mut queue = QUEUE.lock().unwrap();
+        let item = queue.take_pending(&job_id)
+            .ok_or_else(|| AppError::new(ErrorCode::JobNotFound, format!("队列中不存在等待中的任务: {}", job_id)))?;
+        let position = position.min(queue.pending.len());
+        queue.pending.insert(position, item);
+    }
+    save_order();
+    emit_queue_state(&app);
+    Ok(())
+}
+
+/// 修改等待中任务的优先级（数值越大越先启动）
+#[command]
+pub async fn set_queue_priority(app: tauri::AppHandle, job_id: String, priority: i32) -> AppResult<()> {
+    {
+        let mut queue = QUEUE.lock().unwrap();
+        let mut item = queue.take_pending(&job_id)
+            .ok_or_else(|| AppError::new(ErrorCode::JobNotFound, format!("队列中不存在等待中的任务: {}", job_id)))?;
+        item.priority = priority;
+        queue.insert_by_priority(item);
+    }
+    job_store::set_priority(&job_id, priority);
+    save_order();
+    emit_queue_state(&app);
+    Ok(())
+}
+
+/// 获取当前队列状态
+#[command]
+pub async fn get_queue_state() -> AppResult<QueueSnapshot> {
+    Ok(snapshot())
+}
@@ -0,0 +1,363 @@
+use serde::Serializer;
+
+/// 替换敏感值的占位符
+pub const MASK: &str = "***";
+
+/// 值需要隐藏的请求头（小写）
+const SENSITIVE_HEADERS: &[&str] = &[
+    "cookie",
+    "set-cookie",
+    "authorization",
+    "proxy-authorization",
+    "x-api-key",
+    "x-auth-token",
+];
+
+/// 值需要隐藏的查询参数（小写）
+const SENSITIVE_PARAMS: &[&str] = &[
+    "token",
+    "access_token",
+    "auth",
+    "auth_key",
+    "key",
+    "sig",
+    "signature",
+    "policy",
+    "key-pair-id",
+    "hdnts",
+    "license",
+    "license_code",
+    "licensecode",
+    "x-amz-signature",
+    "x-amz-security-token",
+    "x-goog-signature",
+    "password",
+    "secret",
+];
+
+/// 值需要隐藏的 JSON 字段（小写）
+const SENSITIVE_FIELDS: &[&str] = &[
+    "license_code",
+    "licensecode",
+    "token",
+    "access_token",
+    "password",
+    "key",
+];
+
+/// `KID:KEY` 中 KID 和 KEY 的十六进制长度
+const HEX_KEY_LEN: usize = 32;
+
+/// 隐藏文本中的密钥、Cookie/Authorization 请求头、授权码和查询参数中的令牌
+pub fn redact(text: &str) -> String {
+    // 只转换 ASCII 字母，字节位置与原文一致
+    let lower = text.to_ascii_lowercase();
+    let mut ranges = Vec::new();
+    key_option_ranges(&lower, &mut ranges);
+    header_ranges(&lower, &mut ranges);
+    query_param_ranges(&lower, &mut ranges);
+    json_field_ranges(&lower, &mut ranges);
+    hex_key_ranges(&lower, &mut ranges);
+    if ranges.is_empty() {
+        return text.to_string();
+    }
+
+    ranges.sort_unstable();
+    let mut redacted = String::with_capacity(text.len());
+    let mut pos = 0;
+    for (start, end) in ranges {
+        if end <= pos {
+            continue;
+        }
+        redacted.push_str(&text[pos..start.max(pos)]);
+        redacted.push_str(MASK);
+        pos = end;
+    }
+    redacted.push_str(&text[pos..]);
+    redacted
+}
+
+/// 隐藏命令行参数：`--key` 的值整体隐藏，其余参数（请求头、地址）按 `redact` 处理
+pub fn redact_args(args: &[String]) -> Vec<String> {
+    let mut previous: Option<&str> = None;
+    args.iter()
+        .map(|arg| {
+            let redacted = if previous == Some("--key") {
+                MASK.to_string()
+            } else {
+                redact(arg)
+            };
+            previous = Some(arg.as_str());
+            redacted
+        })
+        .collect()
+}
+
+/// 用于 `#[serde(serialize_with)]`：序列化时隐藏敏感内容
+pub fn serialize<T: AsRef<str>, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&redact(value.as_ref()))
+}
+
+/// 用于可选字段的 `#[serde(serialize_with)]`
+pub fn serialize_opt<S: Serializer>(value: &Option<String>, serializer: S) -> Result<S::Ok, S::Error> {
+    match value {
+        Some(value) => serializer.serialize_some(&redact(value)),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// 所有出现位置（字节偏移）
+fn find_all<'a>(haystack: &'a str, needle: &'a str) -> impl Iterator<Item = usize> + 'a {
+    haystack.match_indices(needle).map(|(i, _)| i)
+}
+
+/// 从 `start` 开始的值的结束位置：遇到空白或 `stops` 中的字符为止
+fn value_end(bytes: &[u8], start: usize, stops: &[u8]) -> usize {
+    bytes[start..].iter()
+        .position(|b| b.is_ascii_whitespace() || stops.contains(b))
+        .map_or(bytes.len(), |offset| start + offset)
+}
+
+fn skip_spaces(bytes: &[u8], mut pos: usize) -> usize {
+    while pos < bytes.len() && (bytes[pos] == b' ' || bytes[pos] == b'\t') {
+        pos += 1;
+    }
+    pos
+}
+
+/// 名称前是否为边界（避免 `monkey=` 匹配 `key=`）
+fn is_boundary(bytes: &[u8], pos: usize) -> bool {
+    pos == 0 || !(bytes[pos - 1].is_ascii_alphanumeric() || bytes[pos - 1] == b'-' || bytes[pos - 1] == b'_')
+}
+
+/// `--key VALUE`、`--key=VALUE`、`--key "VALUE"`
+fn key_option_ranges(lower: &str, ranges: &mut Vec<(usize, usize)>) {
+    let bytes = lower.as_bytes();
+    for index in find_all(lower, "--key") {
+        let mut pos = index + "--key".len();
+        match bytes.get(pos) {
+            Some(b' ') | Some(b'\t') | Some(b'=') => pos += 1,
+            _ => continue, // --key-text-file 等其他参数
+        }
+        pos = skip_spaces(bytes, pos);
+        let end = match bytes.get(pos) {
+            Some(&quote) if quote == b'"' || quote == b'\'' => {
+                pos += 1;
+                bytes[pos..].iter().position(|b| *b == quote).map_or(bytes.len(), |offset| pos + offset)
+            }
+            _ => value_end(bytes, pos, &[]),
+        };
+        if end > pos {
+            ranges.push((pos, end));
+        }
+    }
+}
+
+/// `Cookie: ...`、`Authorization: Bearer ...`，隐藏到行尾或引号为止
+fn header_ranges(lower: &str, ranges: &mut Vec<(usize, usize)>) {
+    let bytes = lower.as_bytes();
+    for name in SENSITIVE_HEADERS {
+        for index in find_all(lower, name) {
+            if !is_boundary(bytes, index) {
+                continue;
+            }
+            let pos = skip_spaces(bytes, index + name.len());
+            if bytes.get(pos) != Some(&b':') {
+                continue;
+            }
+            let start = skip_spaces(bytes, pos + 1);
+            let end = bytes[start..].iter()
+                .position(|b| matches!(b, b'\n' | b'\r' | b'"' | b'\''))
+                .map_or(bytes.len(), |offset| start + offset);
+            if end > start {
+                ranges.push((start, end));
+            }
+        }
+    }
+}
+
+/// 地址中的 `?token=...`、`&sig=...`
+fn query_param_ranges(lower: &str, ranges: &mut Vec<(usize, usize)>) {
+    let bytes = lower.as_bytes();
+    for (index, _) in lower.match_indices(['?', '&']) {
+        let name_start = index + 1;
+        let name_end = bytes[name_start..].iter()
+            .position(|b| !(b.is_ascii_alphanumeric() || matches!(b, b'_' | b'-' | b'.')))
+            .map_or(bytes.len(), |offset| name_start + offset);
+        if bytes.get(name_end) != Some(&b'=') || !SENSITIVE_PARAMS.contains(&&lower[name_start..name_end]) {
+            continue;
+        }
+        let start = name_end + 1;
+        let end = value_end(bytes, start, b"&#\"'<>");
+        if end > start {
+            ranges.push((start, end));
+        }
+    }
+}
+
+/// JSON 中的 `"license_code": "..."`
+fn json_field_ranges(lower: &str, ranges: &mut Vec<(usize, usize)>) {
+    let bytes = lower.as_bytes();
+    for name in SENSITIVE_FIELDS {
+        let quoted = format!("\"{}\"", name);
+        for index in find_all(lower, &quoted) {
+            let pos = skip_spaces(bytes, index + quoted.len());
+            if bytes.get(pos) != Some(&b':') {
+                continue;
+            }
+            let pos = skip_spaces(bytes, pos + 1);
+            if bytes.get(pos) != Some(&b'"') {
+                continue;
+            }
+            let start = pos + 1;
+            let end = bytes[start..].iter().position(|b| *b == b'"').map_or(bytes.len(), |offset| start + offset);
+            if end > start {
+                ranges.push((start, end));
+            }
+        }
+    }
+}
+
+/// 工具输出中的 `KID:KEY`（各 32 位十六进制）
+fn hex_key_ranges(lower: &str, ranges: &mut Vec<(usize, usize)>) {
+    let bytes = lower.as_bytes();
+    let total = HEX_KEY_LEN * 2 + 1;
+    let is_hex_run = |start: usize| bytes[start..start + HEX_KEY_LEN].iter().all(u8::is_ascii_hexdigit);
+    let mut pos = 0;
+    while pos + total <= bytes.len() {
+        let matched = (pos == 0 || !bytes[pos - 1].is_ascii_hexdigit())
+            && bytes[pos + HEX_KEY_LEN] == b':'
+            && is_hex_run(pos)
+            && is_hex_run(pos + HEX_KEY_LEN + 1)
+            && bytes.get(pos + total).map_or(true, |b| !b.is_ascii_hexdigit());
+        if matched {
+            ranges.push((pos, pos + total));
+            pos += total;
+        } else {
+            pos += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KID: &str = "0123456789abcdef0123456789abcdef";
+    const KEY: &str = "00112233445566778899aabbccddeeff";
+
+    fn args(values: &[&str]) -> Vec<String> {
+        values.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn redacts_key_option_forms() {
+        assert_eq!(redact("N_m3u8DL-RE url --key abc123 --auto-select"), "N_m3u8DL-RE url --key *** --auto-select");
+        assert_eq!(redact("--key=abc123"), "--key=***");
+        assert_eq!(redact("--KEY  abc123"), "--KEY  ***");
+        assert_eq!(redact("--key \"abc 123\" --no-log"), "--key \"***\" --no-log");
+        assert_eq!(redact("--key 'abc 123'"), "--key '***'");
+    }
+
+    #[test]
+    fn keeps_other_key_options() {
+        let text = "--key-text-file keys.txt --keyframe 5";
+        assert_eq!(redact(text), text);
+        assert_eq!(redact("--key"), "--key");
+    }
+
+    #[test]
+    fn redacts_sensitive_headers() {
+        assert_eq!(redact("-H \"Cookie: a=1; b=2\" --no-log"), "-H \"Cookie: ***\" --no-log");
+        assert_eq!(redact("Authorization: Bearer abc\nnext line"), "Authorization: ***\nnext line");
+        assert_eq!(redact("x-api-key : secret"), "x-api-key : ***");
+        let text = "User-Agent: Mozilla/5.0";
+        assert_eq!(redact(text), text);
+    }
+
+    #[test]
+    fn header_names_need_boundary() {
+        let text = "X-Set-Cookie-Policy: strict";
+        assert_eq!(redact(text), text);
+        let text = "mycookie: value";
+        assert_eq!(redact(text), text);
+    }
+
+    #[test]
+    fn redacts_sensitive_query_params() {
+        assert_eq!(
+            redact("https://cdn.example.com/a.m3u8?token=abc&sig=def&quality=hd"),
+            "https://cdn.example.com/a.m3u8?token=***&sig=***&quality=hd",
+        );
+        assert_eq!(redact("url?X-Amz-Signature=abc#frag"), "url?X-Amz-Signature=***#frag");
+        assert_eq!(redact("\"https://a/b?key=abc\""), "\"https://a/b?key=***\"");
+    }
+
+    #[test]
+    fn query_param_names_must_match_exactly() {
+        let text = "https://a/b?monkey=1&tokens=2&keyframe=3";
+        assert_eq!(redact(text), text);
+        // 空值不处理
+        let text = "https://a/b?token=&x=1";
+        assert_eq!(redact(text), text);
+    }
+
+    #[test]
+    fn redacts_json_fields() {
+        assert_eq!(
+            redact(r#"{"license_code": "abc", "name": "video", "token":"t"}"#),
+            r#"{"license_code": "***", "name": "video", "token":"***"}"#,
+        );
+        let text = r#"{"monkey": "banana"}"#;
+        assert_eq!(redact(text), text);
+    }
+
+    #[test]
+    fn redacts_hex_kid_key_pairs() {
+        let line = format!("INFO: Found key {}:{} for track", KID, KEY);
+        assert_eq!(redact(&line), "INFO: Found key *** for track");
+        assert_eq!(redact(&KID.to_uppercase()), KID.to_uppercase());
+        // 过长的十六进制串不是 KID:KEY
+        let text = format!("a{}:{}", KID, KEY);
+        assert_eq!(redact(&text), text);
+        let text = format!("{}:{}0", KID, KEY);
+        assert_eq!(redact(&text), text);
+    }
+
+    #[test]
+    fn redacts_overlapping_ranges_once() {
+        let text = format!("--key {}:{}", KID, KEY);
+        assert_eq!(redact(&text), "--key ***");
+    }
+
+    #[test]
+    fn preserves_non_ascii_text() {
+        assert_eq!(redact("下载地址 https://a/b?token=密钥 完成"), "下载地址 https://a/b?token=*** 完成");
+    }
+
+    #[test]
+    fn redacts_args() {
+        let redacted = redact_args(&args(&[
+            "https://a/b?token=abc",
+            "--key",
+            "abc:def",
+            "-H",
+            "Cookie: a=1",
+            "--key-text-file",
+            "keys.txt",
+            "--save-name",
+            "video",
+        ]));
+        assert_eq!(redacted, args(&[
+            "https://a/b?token=***",
+            "--key",
+            MASK,
+            "-H",
+            "Cookie: ***",
+            "--key-text-file",
+            "keys.txt",
+            "--save-name",
+            "video",
+        ]));
+    }
+}
@@ -0,0 +1,80 @@
+use std::time::Duration;
+use serde::Serialize;
+use tauri::Emitter;
+use crate::error;
+use crate::runner::{ProcessOutcome, RunError};
+use crate::settings;
+
+/// 重试次数上限
+pub const MAX_RETRY_ATTEMPTS: u32 = 10;
+
+/// 第一次重试前的等待时间，之后每次翻倍
+const BASE_DELAY: Duration = Duration::from_secs(2);
+
+/// 重试等待时间上限
+const MAX_DELAY: Duration = Duration::from_secs(60);
+
+/// 重试策略
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    /// 失败后最多重试的次数（不含首次运行）
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// 第 `retry` 次重试（从 1 开始）前的等待时间：指数退避
+    pub fn delay(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.base_delay.saturating_mul(factor).min(self.max_delay)
+    }
+}
+
+/// 当前的重试策略（重试次数取自配置 retryAttempts，0 表示不重试）
+pub fn policy() -> RetryPolicy {
+    RetryPolicy {
+        max_retries: settings::get().retry_attempts.min(MAX_RETRY_ATTEMPTS),
+        base_delay: BASE_DELAY,
+        max_delay: MAX_DELAY,
+    }
+}
+
+/// 判断失败是否为暂时性的，返回原因；进程超时和网络错误可重试，其他失败不重试
+pub fn transient_reason(result: &Result<ProcessOutcome, RunError>) -> Option<String> {
+    let outcome = match result {
+        Ok(outcome) if outcome.success() => return None,
+        Ok(outcome) => outcome,
+        Err(RunError::Timeout(kind)) => return Some(format!("进程超时（{}）", kind)),
+        Err(_) => return None,
+    };
+
+    error::classify_outcome(outcome)
+        .filter(|(code, _)| code.is_transient())
+        .map(|(_, line)| line)
+}
+
+/// 重试事件（`job-retry`）
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct JobRetryEvent {
+    job_id: String,
+    /// 即将开始的是第几次运行（首次运行为 1）
+    attempt: u32,
+    max_attempts: u32,
+    delay_ms: u64,
+    reason: String,
+    timestamp: String,
+}
+
+/// 通知前端任务即将重试
+pub fn emit_retry(app: &tauri::AppHandle, job_id: &str, attempt: u32, policy: &RetryPolicy, delay: Duration, reason: &str) {
+    let _ = app.emit("job-retry", JobRetryEvent {
+        job_id: job_id.to_string(),
+        attempt,
+        max_attempts: policy.max_retries + 1,
+        delay_ms: delay.as_millis() as u64,
+        reason: reason.to_string(),
+        timestamp: chrono::Utc::now().to_rfc3339(),
+    });
+}
@@ -0,0 +1,387 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::io::{BufRead, BufReader, Read};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use tauri::Emitter;
+use crate::settings;
+use crate::LogEvent;
+
+/// 每个输出流保留的尾部行数
+const TAIL_LINES: usize = 50;
+
+/// 轮询进程退出状态和超时的间隔
+pub const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// 进程超时设置（None 表示不限制）
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Timeouts {
+    /// 总运行时间上限（不含暂停时间）
+    pub wall_clock: Option<Duration>,
+    /// 无新输出的时间上限
+    pub inactivity: Option<Duration>,
+}
+
+/// 新启动进程使用的超时设置（取自配置 inactivityTimeout / maxRunTime，单位毫秒，0 表示不限制）
+pub fn default_timeouts() -> Timeouts {
+    let settings = settings::get();
+    let to_duration = |ms: u64| (ms > 0).then(|| Duration::from_millis(ms));
+    Timeouts {
+        wall_clock: to_duration(settings.max_run_time),
+        inactivity: to_duration(settings.inactivity_timeout),
+    }
+}
+
+/// 超时类型
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeoutKind {
+    WallClock(Duration),
+    Inactivity(Duration),
+}
+
+impl fmt::Display for TimeoutKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeoutKind::WallClock(limit) => write!(f, "运行时间超过 {} 秒", limit.as_secs()),
+            TimeoutKind::Inactivity(limit) => write!(f, "{} 秒内没有进展", limit.as_secs()),
+        }
+    }
+}
+
+/// 启动进程失败（保留 IO 错误类型，用于区分工具不存在和其他原因）
+#[derive(Debug)]
+pub struct SpawnError {
+    pub kind: std::io::ErrorKind,
+    pub message: String,
+}
+
+impl fmt::Display for SpawnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// 运行进程失败的原因
+#[derive(Debug)]
+pub enum RunError {
+    Spawn(SpawnError),
+    Wait(String),
+    /// 超时，进程已被终止
+    Timeout(TimeoutKind),
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunError::Spawn(e) => write!(f, "{}", e),
+            RunError::Wait(e) => write!(f, "{}", e),
+            RunError::Timeout(kind) => write!(f, "进程超时（{}），已终止", kind),
+        }
+    }
+}
+
+impl From<RunError> for String {
+    fn from(e: RunError) -> Self {
+        e.to_string()
+    }
+}
+
+/// 输出流
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// 输出行对无进展超时的意义
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Activity {
+    /// 普通输出：与最近的输出都不同时视为有进展
+    Output,
+    /// 进度值（键区分不同的流或计数）：比上次增加时才视为有进展
+    Progress(&'static str, u64),
+    /// 速度、帧率等状态行：不视为进展
+    Status,
+}
+
+/// 输出行解析策略，stdout 和 stderr 的读取线程共用同一个实例；
+/// 返回的事件发送到运行器的事件通道
+pub trait LineParser: Send + Sync {
+    fn parse(&self, stream: OutputStream, line: &str) -> Option<LogEvent>;
+
+    /// 判断输出行是否表示进程仍有进展；进度条在卡住时仍会刷新速度等内容，需按进度值判断
+    fn activity(&self, _stream: OutputStream, _line: &str) -> Activity {
+        Activity::Output
+    }
+}
+
+/// 解析出的事件发往的前端事件
+#[derive(Clone)]
+pub struct EventChannel {
+    app: tauri::AppHandle,
+    event: &'static str,
+}
+
+impl EventChannel {
+    pub fn new(app: &tauri::AppHandle, event: &'static str) -> Self {
+        EventChannel { app: app.clone(), event }
+    }
+
+    fn send(&self, event: LogEvent) {
+        let _ = self.app.emit(self.event, event);
+    }
+}
+
+/// 外部工具的启动参数
+#[derive(Clone)]
+pub struct ProcessSpec {
+    /// 工具名称，用于日志
+    pub tool: String,
+    pub program: String,
+    pub args: Vec<String>,
+    pub working_dir: Option<String>,
+    pub timeouts: Timeouts,
+}
+
+/// 进程结束后的结果
+#[derive(Debug)]
+pub struct ProcessOutcome {
+    pub status: ExitStatus,
+    pub exit_code: Option<i32>,
+    /// 终止进程的信号（仅 Unix）
+    pub signal: Option<i32>,
+    pub duration: Duration,
+    pub stdout_tail: Vec<String>,
+    pub stderr_tail: Vec<String>,
+}
+
+impl ProcessOutcome {
+    pub fn success(&self) -> bool {
+        self.status.success()
+    }
+
+    /// 失败描述：退出码或信号，以及尾部输出
+    pub fn failure_message(&self) -> String {
+        let reason = match (self.exit_code, self.signal) {
+            (Some(code), _) => format!("退出码: {}", code),
+            (None, Some(signal)) => format!("被信号 {} 终止", signal),
+            (None, None) => "被终止".to_string(),
+        };
+        format!("{}\n标准输出: {}\n错误输出: {}",
+            reason, self.stdout_tail.join("\n"), self.stderr_tail.join("\n"))
+    }
+}
+
+/// 超时监视：按总运行时间和最近一次输出时间判断是否超时，暂停期间不计时
+pub struct Watchdog {
+    timeouts: Timeouts,
+    started: Instant,
+    last_activity: Arc<Mutex<Instant>>,
+    paused_since: Option<Instant>,
+    paused_total: Duration,
+    /// 进程处于不输出日志的阶段（如下载器调用外部工具解密、混流）时不检查无输出超时
+    quiet: bool,
+}
+
+impl Watchdog {
+    /// 标记进程是否处于暂停状态
+    pub fn set_paused(&mut self, paused: bool) {
+        match (paused, self.paused_since) {
+            (true, None) => self.paused_since = Some(Instant::now()),
+            (false, Some(since)) => {
+                self.paused_total += since.elapsed();
+                self.paused_since = None;
+                // 恢复后重新开始计算无输出时间
+                *self.last_activity.lock().unwrap() = Instant::now();
+            }
+            _ => {}
+        }
+    }
+
+    /// 标记进程是否处于不输出日志的阶段
+    pub fn set_quiet(&mut self, quiet: bool) {
+        if self.quiet && !quiet {
+            *self.last_activity.lock().unwrap() = Instant::now();
+        }
+        self.quiet = quiet;
+    }
+
+    /// 检查是否超时
+    pub fn check(&self) -> Option<TimeoutKind> {
+        if self.paused_since.is_some() {
+            return None;
+        }
+        if let Some(limit) = self.timeouts.wall_clock {
+            if self.started.elapsed().saturating_sub(self.paused_total) > limit {
+                return Some(TimeoutKind::WallClock(limit));
+            }
+        }
+        if let Some(limit) = self.timeouts.inactivity.filter(|_| !self.quiet) {
+            if self.last_activity.lock().unwrap().elapsed() > limit {
+                return Some(TimeoutKind::Inactivity(limit));
+            }
+        }
+        None
+    }
+}
+
+/// 正在读取的进程输出
+pub struct OutputCapture {
+    started: Instant,
+    timeouts: Timeouts,
+    /// 最近一次出现新输出的时间
+    last_activity: Arc<Mutex<Instant>>,
+    stdout: Option<JoinHandle<Vec<String>>>,
+    stderr: Option<JoinHandle<Vec<String>>>,
+}
+
+impl OutputCapture {
+    /// 创建该进程的超时监视
+    pub fn watchdog(&self) -> Watchdog {
+        Watchdog {
+            timeouts: self.timeouts,
+            started: self.started,
+            last_activity: Arc::clone(&self.last_activity),
+            paused_since: None,
+            paused_total: Duration::ZERO,
+            quiet: false,
+        }
+    }
+
+    /// 进程退出后等待输出读取完成，生成结果
+    pub fn finish(self, status: ExitStatus) -> ProcessOutcome {
+        let duration = self.started.elapsed();
+        let stdout_tail = self.stdout.map(|h| h.join().unwrap_or_default()).unwrap_or_default();
+        let stderr_tail = self.stderr.map(|h| h.join().unwrap_or_default()).unwrap_or_default();
+        ProcessOutcome {
+            status,
+            exit_code: status.code(),
+            signal: exit_signal(&status),
+            duration,
+            stdout_tail,
+            stderr_tail,
+        }
+    }
+
+    /// 等待输出读取完成并丢弃（进程未正常结束时）
+    pub fn discard(self) {
+        if let Some(h) = self.stdout {
+            let _ = h.join();
+        }
+        if let Some(h) = self.stderr {
+            let _ = h.join();
+        }
+    }
+}
+
+#[cfg(unix)]
+fn exit_signal(status: &ExitStatus) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal()
+}
+
+#[cfg(not(unix))]
+fn exit_signal(_status: &ExitStatus) -> Option<i32> {
+    None
+}
+
+/// 在后台线程中逐行读取输出，交给解析策略并保留尾部；
+/// 进度值增加或出现新的普通输出时视为进程仍有进展
+fn read_lines<R: Read + Send + 'static>(
+    source: R,
+    stream: OutputStream,
+    parser: Arc<dyn LineParser>,
+    channel: Option<EventChannel>,
+    last_activity: Arc<Mutex<Instant>>,
+) -> JoinHandle<Vec<String>> {
+    std::thread::spawn(move || {
+        let mut tail: VecDeque<String> = VecDeque::with_capacity(TAIL_LINES);
+        let mut marks: HashMap<&'static str, u64> = HashMap::new();
+        for line in BufReader::new(source).lines().map_while(Result::ok) {
+            let advanced = match parser.activity(stream, &line) {
+                Activity::Output => !tail.contains(&line),
+                Activity::Progress(key, value) => marks.insert(key, value).map_or(true, |previous| value > previous),
+                Activity::Status => false,
+            };
+            if advanced {
+                *last_activity.lock().unwrap() = Instant::now();
+            }
+            if let Some(event) = parser.parse(stream, &line) {
+                if let Some(channel) = &channel {
+                    channel.send(event);
+                }
+            }
+            if tail.len() == TAIL_LINES {
+                tail.pop_front();
+            }
+            tail.push_back(line);
+        }
+        tail.into()
+    })
+}
+
+/// 启动进程并开始读取输出；调用方负责等待进程（可交给任务表管理以支持取消和暂停）
+pub fn spawn(
+    spec: &ProcessSpec,
+    parser: Arc<dyn LineParser>,
+    channel: Option<EventChannel>,
+) -> Result<(Child, OutputCapture), SpawnError> {
+    let mut cmd = Command::new(&spec.program);
+    if let Some(dir) = &spec.working_dir {
+        cmd.current_dir(dir);
+    }
+    cmd.args(&spec.args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    let started = Instant::now();
+    let mut child = cmd.spawn()
+        .map_err(|e| SpawnError {
+            kind: e.kind(),
+            message: format!("执行 {} 失败: {}，工具路径: {:?}", spec.tool, e, spec.program),
+        })?;
+    log::info!("已启动 {} (pid {})", spec.tool, child.id());
+
+    let last_activity = Arc::new(Mutex::new(started));
+    let stdout = child.stdout.take().map(|out| read_lines(
+        out, OutputStream::Stdout, Arc::clone(&parser), channel.clone(), Arc::clone(&last_activity)));
+    let stderr = child.stderr.take().map(|err| read_lines(
+        err, OutputStream::Stderr, parser, channel, Arc::clone(&last_activity)));
+
+    Ok((child, OutputCapture { started, timeouts: spec.timeouts, last_activity, stdout, stderr }))
+}
+
+/// 启动进程并阻塞等待结束，超时时终止进程
+pub fn run(
+    spec: &ProcessSpec,
+    parser: Arc<dyn LineParser>,
+    channel: Option<EventChannel>,
+) -> Result<ProcessOutcome, RunError> {
+    let (mut child, capture) = spawn(spec, parser, channel).map_err(RunError::Spawn)?;
+    let watchdog = capture.watchdog();
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) => {}
+            Err(e) => {
+                capture.discard();
+                return Err(RunError::Wait(format!("等待 {} 完成失败: {}", spec.tool, e)));
+            }
+        }
+        if let Some(kind) = watchdog.check() {
+            log::error!("{} 超时（{}），终止进程 {}", spec.tool, kind, child.id());
+            let _ = child.kill();
+            let _ = child.wait();
+            capture.discard();
+            return Err(RunError::Timeout(kind));
+        }
+        std::thread::sleep(WAIT_POLL_INTERVAL);
+    };
+
+    let outcome = capture.finish(status);
+    log::info!("{} 已退出: {:?}，耗时 {:.1}s", spec.tool, outcome.exit_code, outcome.duration.as_secs_f64());
+    Ok(outcome)
+}
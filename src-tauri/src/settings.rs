@@ -0,0 +1,623 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tauri::{command, Emitter};
+use tauri_plugin_dialog::DialogExt;
+use crate::error::{AppError, AppResult};
+use crate::{intermediates, logger, path_policy, queue, retry};
+
+/// 配置文件名（位于应用数据目录，与旧版前端保存的文件相同）
+const SETTINGS_FILE: &str = "app_settings.json";
+
+/// 配置迁移：`MIGRATIONS[i]` 将版本 i 的配置迁移到版本 i + 1
+type Migration = fn(&mut Map<String, Value>);
+const MIGRATIONS: &[Migration] = &[migrate_v0_to_v1];
+
+/// 当前的配置格式版本
+pub const SETTINGS_VERSION: u32 = MIGRATIONS.len() as u32;
+
+const PREFER_QUALITIES: &[&str] = &["highest", "medium", "lowest"];
+const SUBTITLE_LANGUAGES: &[&str] = &["zh", "en", "all"];
+const THEMES: &[&str] = &["light", "dark", "auto"];
+const LOG_FORMATS: &[&str] = &["text", "json"];
+
+/// 可以指定路径的外部工具
+pub const OVERRIDABLE_TOOLS: &[&str] = &["N_m3u8DL-RE", "ffmpeg"];
+
+/// 超时设置上限（毫秒）：24 小时
+const MAX_TIMEOUT_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// 保留天数上限
+const MAX_RETENTION_DAYS: u32 = 365;
+
+/// 单个日志文件大小上限的取值上限（MB）
+const MAX_LOG_FILE_SIZE_MB: u32 = 1024;
+
+/// 日志目录总大小上限的取值上限（MB）
+const MAX_LOG_TOTAL_SIZE_MB: u32 = 100 * 1024;
+
+static STORE: Lazy<Mutex<Store>> = Lazy::new(|| Mutex::new(Store {
+    path: None,
+    settings: AppSettings::default(),
+}));
+
+struct Store {
+    path: Option<PathBuf>,
+    settings: AppSettings,
+}
+
+impl Store {
+    fn save(&self) -> AppResult<()> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let content = serde_json::to_string_pretty(&self.settings)
+            .map_err(|e| AppError::internal(format!("序列化配置失败: {}", e)))?;
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, content).map_err(|e| AppError::io("保存配置失败", &e))?;
+        std::fs::rename(&tmp, path).map_err(|e| AppError::io("保存配置失败", &e))
+    }
+}
+
+/// 应用配置（对应前端 `AppSettings`），缺失的字段使用默认值
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AppSettings {
+    pub version: u32,
+
+    // 下载设置
+    pub default_download_dir: String,
+    pub auto_select_quality: bool,
+    /// highest / medium / lowest
+    pub prefer_quality: String,
+
+    // 字幕设置
+    pub burn_subtitles: bool,
+    /// zh / en / all
+    pub subtitle_language: String,
+    /// 烧录字幕时使用硬件编码器（关闭时使用 libx264）
+    pub hardware_encoding: bool,
+
+    // 界面设置
+    /// light / dark / auto
+    pub theme: String,
+
+    // 高级设置
+    pub max_concurrent_downloads: usize,
+    pub retry_attempts: u32,
+    /// 网络请求超时（毫秒）
+    pub timeout: u64,
+    /// 外部工具无进展超时（毫秒）：进度值不再增加、也没有新的输出超过该时间时终止进程，0 表示不限制
+    pub inactivity_timeout: u64,
+    /// 外部工具总运行时间上限（毫秒），0 表示不限制
+    pub max_run_time: u64,
+    /// 删除中间文件时移入回收站
+    pub use_trash: bool,
+    pub trash_retention_days: u32,
+    /// 启动时清理超过天数的日志，0 表示不清理
+    pub log_retention_days: u32,
+    /// 日志文件格式：text（每行一条文本）或 json（每行一个 JSON 对象）
+    pub log_format: String,
+    /// 单个日志文件超过该大小（MB）时轮转并压缩
+    pub log_max_file_size_mb: u32,
+    /// 日志目录总大小上限（MB），超过时删除最旧的日志，0 表示不限制
+    pub log_max_total_size_mb: u32,
+    /// 为每个任务单独写一份日志（logs/jobs/<任务 ID>.log）
+    pub job_log_files: bool,
+    /// 外部工具路径（工具名 -> 可执行文件绝对路径），优先于内置工具。
+    /// 只能通过 `pick_tool_path` 对话框设置，`update_settings` 忽略该字段
+    pub tool_paths: BTreeMap<String, String>,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        AppSettings {
+            version: SETTINGS_VERSION,
+            default_download_dir: String::new(),
+            auto_select_quality: true,
+            prefer_quality: "highest".to_string(),
+            burn_subtitles: true,
+            subtitle_language: "zh".to_string(),
+            hardware_encoding: true,
+            theme: "light".to_string(),
+            max_concurrent_downloads: 1,
+            retry_attempts: 3,
+            timeout: 30_000,
+            inactivity_timeout: 10 * 60 * 1000,
+            max_run_time: 0,
+            use_trash: true,
+            trash_retention_days: 7,
+            log_retention_days: 0,
+            log_format: "text".to_string(),
+            log_max_file_size_mb: 10,
+            log_max_total_size_mb: 200,
+            job_log_files: true,
+            tool_paths: BTreeMap::new(),
+        }
+    }
+}
+
+/// 取值不合法时记录问题并恢复默认值
+fn fix<T>(field: &mut T, valid: bool, default: T, problem: String, problems: &mut Vec<String>) {
+    if !valid {
+        problems.push(problem);
+        *field = default;
+    }
+}
+
+impl AppSettings {
+    /// 校验取值：`strict` 时返回错误，否则将不合法的字段恢复为默认值
+    fn normalize(&mut self, strict: bool) -> AppResult<()> {
+        let d = AppSettings::default();
+        let mut problems = Vec::new();
+
+        let valid = PREFER_QUALITIES.contains(&self.prefer_quality.as_str());
+        let problem = format!("preferQuality 只能为 {}: {}", PREFER_QUALITIES.join(" / "), self.prefer_quality);
+        fix(&mut self.prefer_quality, valid, d.prefer_quality, problem, &mut problems);
+
+        let valid = SUBTITLE_LANGUAGES.contains(&self.subtitle_language.as_str());
+        let problem = format!("subtitleLanguage 只能为 {}: {}", SUBTITLE_LANGUAGES.join(" / "), self.subtitle_language);
+        fix(&mut self.subtitle_language, valid, d.subtitle_language, problem, &mut problems);
+
+        let valid = THEMES.contains(&self.theme.as_str());
+        let problem = format!("theme 只能为 {}: {}", THEMES.join(" / "), self.theme);
+        fix(&mut self.theme, valid, d.theme, problem, &mut problems);
+
+        let valid = (1..=queue::MAX_CONCURRENT_LIMIT).contains(&self.max_concurrent_downloads);
+        let problem = format!("maxConcurrentDownloads 应为 1 到 {}: {}", queue::MAX_CONCURRENT_LIMIT, self.max_concurrent_downloads);
+        fix(&mut self.max_concurrent_downloads, valid, d.max_concurrent_downloads, problem, &mut problems);
+
+        let valid = self.retry_attempts <= retry::MAX_RETRY_ATTEMPTS;
+        let problem = format!("retryAttempts 应为 0 到 {}: {}", retry::MAX_RETRY_ATTEMPTS, self.retry_attempts);
+        fix(&mut self.retry_attempts, valid, d.retry_attempts, problem, &mut problems);
+
+        let valid = self.timeout <= MAX_TIMEOUT_MS;
+        let problem = format!("timeout 不能超过 {} 毫秒: {}", MAX_TIMEOUT_MS, self.timeout);
+        fix(&mut self.timeout, valid, d.timeout, problem, &mut problems);
+
+        let valid = self.inactivity_timeout <= MAX_TIMEOUT_MS;
+        let problem = format!("inactivityTimeout 不能超过 {} 毫秒: {}", MAX_TIMEOUT_MS, self.inactivity_timeout);
+        fix(&mut self.inactivity_timeout, valid, d.inactivity_timeout, problem, &mut problems);
+
+        let valid = self.max_run_time <= MAX_TIMEOUT_MS;
+        let problem = format!("maxRunTime 不能超过 {} 毫秒: {}", MAX_TIMEOUT_MS, self.max_run_time);
+        fix(&mut self.max_run_time, valid, d.max_run_time, problem, &mut problems);
+
+        let valid = (1..=MAX_RETENTION_DAYS).contains(&self.trash_retention_days);
+        let problem = format!("trashRetentionDays 应为 1 到 {}: {}", MAX_RETENTION_DAYS, self.trash_retention_days);
+        fix(&mut self.trash_retention_days, valid, d.trash_retention_days, problem, &mut problems);
+
+        let valid = self.log_retention_days <= MAX_RETENTION_DAYS;
+        let problem = format!("logRetentionDays 应为 0 到 {}: {}", MAX_RETENTION_DAYS, self.log_retention_days);
+        fix(&mut self.log_retention_days, valid, d.log_retention_days, problem, &mut problems);
+
+        let valid = LOG_FORMATS.contains(&self.log_format.as_str());
+        let problem = format!("logFormat 只能为 {}: {}", LOG_FORMATS.join(" / "), self.log_format);
+        fix(&mut self.log_format, valid, d.log_format, problem, &mut problems);
+
+        let valid = (1..=MAX_LOG_FILE_SIZE_MB).contains(&self.log_max_file_size_mb);
+        let problem = format!("logMaxFileSizeMb 应为 1 到 {}: {}", MAX_LOG_FILE_SIZE_MB, self.log_max_file_size_mb);
+        fix(&mut self.log_max_file_size_mb, valid, d.log_max_file_size_mb, problem, &mut problems);
+
+        let valid = self.log_max_total_size_mb <= MAX_LOG_TOTAL_SIZE_MB;
+        let problem = format!("logMaxTotalSizeMb 应为 0 到 {}: {}", MAX_LOG_TOTAL_SIZE_MB, self.log_max_total_size_mb);
+        fix(&mut self.log_max_total_size_mb, valid, d.log_max_total_size_mb, problem, &mut problems);
+
+        self.tool_paths.retain(|tool, path| match check_tool_path(tool, Path::new(path)) {
+            Ok(_) => true,
+            Err(reason) => {
+                problems.push(format!("toolPaths.{} 不可用（{}）: {}", tool, reason, path));
+                false
+            }
+        });
+
+        if problems.is_empty() {
+            return Ok(());
+        }
+        if strict {
+            return Err(AppError::invalid_argument(format!("配置不合法: {}", problems.join("；"))));
+        }
+        log::warn!("配置中的不合法取值已恢复默认: {}", problems.join("；"));
+        Ok(())
+    }
+}
+
+/// 工具可执行文件的文件名（Windows 下带 .exe）
+fn tool_file_name(tool: &str) -> String {
+    if cfg!(windows) {
+        format!("{}.exe", tool)
+    } else {
+        tool.to_string()
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &std::fs::Metadata) -> bool {
+    true
+}
+
+/// 检查工具路径：解析符号链接后的文件名须与工具名一致，且为可执行文件。返回规范化后的路径
+pub fn check_tool_path(tool: &str, path: &Path) -> Result<PathBuf, String> {
+    if !OVERRIDABLE_TOOLS.contains(&tool) {
+        return Err(format!("不支持指定路径的工具: {}", tool));
+    }
+    if !path.is_absolute() || crate::validate_path_safety(&path.to_string_lossy()).is_err() {
+        return Err("不是有效的绝对路径".to_string());
+    }
+    let canonical = path.canonicalize().map_err(|e| format!("文件不可用: {}", e))?;
+    let expected = tool_file_name(tool);
+    let name_matches = canonical.file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(|n| if cfg!(windows) { n.eq_ignore_ascii_case(&expected) } else { n == expected });
+    if !name_matches {
+        return Err(format!("文件名应为 {}", expected));
+    }
+    let metadata = std::fs::metadata(&canonical).map_err(|e| format!("文件不可用: {}", e))?;
+    if !metadata.is_file() || !is_executable(&metadata) {
+        return Err("不是可执行文件".to_string());
+    }
+    Ok(canonical)
+}
+
+/// v0：旧版前端保存的配置（无版本号）。前端可能写入 null，去掉后由默认值补齐
+fn migrate_v0_to_v1(settings: &mut Map<String, Value>) {
+    settings.retain(|_, value| !value.is_null());
+}
+
+/// 由 JSON 对象构造配置；类型不符的字段逐个忽略，保留其余字段
+fn from_object(object: Map<String, Value>) -> AppSettings {
+    if let Ok(settings) = serde_json::from_value(Value::Object(object.clone())) {
+        return settings;
+    }
+    let mut merged = match serde_json::to_value(AppSettings::default()) {
+        Ok(Value::Object(merged)) => merged,
+        _ => return AppSettings::default(),
+    };
+    for (key, value) in object {
+        let previous = merged.insert(key.clone(), value);
+        if serde_json::from_value::<AppSettings>(Value::Object(merged.clone())).is_err() {
+            log::warn!("忽略类型不符的配置项: {}", key);
+            match previous {
+                Some(previous) => merged.insert(key, previous),
+                None => merged.remove(&key),
+            };
+        }
+    }
+    serde_json::from_value(Value::Object(merged)).unwrap_or_default()
+}
+
+/// 读取配置文件并迁移到当前版本，返回配置及是否需要重新保存
+fn load(path: &Path) -> AppResult<(AppSettings, bool)> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((AppSettings::default(), true)),
+        Err(e) => return Err(AppError::io("读取配置失败", &e)),
+    };
+    let object = match serde_json::from_str(&content) {
+        Ok(Value::Object(object)) => object,
+        _ => {
+            log::warn!("配置文件格式不正确，使用默认配置");
+            return Ok((AppSettings::default(), true));
+        }
+    };
+
+    let version = object.get("version").and_then(Value::as_u64).unwrap_or(0) as u32;
+    if version < SETTINGS_VERSION {
+        // 迁移前备份原文件，便于回退到旧版本
+        let backup = path.with_extension(format!("v{}.bak.json", version));
+        if let Err(e) = std::fs::copy(path, &backup) {
+            log::warn!("备份配置文件失败: {}", e);
+        }
+    }
+    Ok(parse(object))
+}
+
+/// 将任意版本的配置对象迁移到当前版本并校验（不合法的取值恢复默认），返回配置及是否有变化
+pub fn parse(mut object: Map<String, Value>) -> (AppSettings, bool) {
+    let version = object.get("version").and_then(Value::as_u64).unwrap_or(0) as u32;
+    let migrated = version < SETTINGS_VERSION;
+    if version > SETTINGS_VERSION {
+        log::warn!("配置版本 {} 高于当前支持的版本 {}，未知字段将被忽略", version, SETTINGS_VERSION);
+    } else if migrated {
+        for migration in &MIGRATIONS[version as usize..] {
+            migration(&mut object);
+        }
+        log::info!("配置已从版本 {} 迁移到版本 {}", version, SETTINGS_VERSION);
+    }
+
+    let mut settings = from_object(object);
+    settings.version = SETTINGS_VERSION;
+    let before = settings.clone();
+    // 非严格模式只会恢复默认值，不会返回错误
+    let _ = settings.normalize(false);
+    let changed = migrated || settings != before;
+    (settings, changed)
+}
+
+/// 加载配置（需在目录授权初始化之后调用），并执行启动时的日志清理
+pub fn init(app_data_dir: PathBuf) -> AppResult<()> {
+    let path = app_data_dir.join(SETTINGS_FILE);
+    let (settings, changed) = load(&path)?;
+    {
+        let mut store = STORE.lock().unwrap();
+        store.settings = settings.clone();
+        store.path = Some(path);
+        if changed {
+            store.save()?;
+        }
+    }
+
+    configure_logger(&settings);
+
+    // 配置文件位于应用数据目录，其中的下载目录视为已授权
+    if !settings.default_download_dir.is_empty() {
+        if let Err(e) = path_policy::allow_dir(Path::new(&settings.default_download_dir)) {
+            log::warn!("默认下载目录不可用: {}", e);
+        }
+    }
+    if settings.log_retention_days > 0 {
+        match logger::cleanup_old_logs(app_data_dir, settings.log_retention_days) {
+            Ok(count) if count > 0 => log::info!("已清理 {} 个过期日志文件", count),
+            Ok(_) => {}
+            Err(e) => log::warn!("清理过期日志失败: {}", e),
+        }
+    }
+    logger::enforce_total_size();
+    Ok(())
+}
+
+/// 将日志配置同步给日志模块
+fn configure_logger(settings: &AppSettings) {
+    const MB: u64 = 1024 * 1024;
+    logger::configure(&logger::LogOptions {
+        json: settings.log_format == "json",
+        max_file_size: settings.log_max_file_size_mb as u64 * MB,
+        max_total_size: settings.log_max_total_size_mb as u64 * MB,
+        job_files: settings.job_log_files,
+    });
+}
+
+/// 当前配置
+pub fn get() -> AppSettings {
+    STORE.lock().unwrap().settings.clone()
+}
+
+/// 保存新配置，通知所有窗口并让需要立即生效的子系统响应变化
+pub fn replace(app: &tauri::AppHandle, mut settings: AppSettings) -> AppResult<AppSettings> {
+    settings.version = SETTINGS_VERSION;
+    settings.normalize(true)?;
+
+    let previous = get();
+    if settings.default_download_dir != previous.default_download_dir && !settings.default_download_dir.is_empty() {
+        // 通过对话框选择的目录已授权；其他目录与 allow_save_dir 相同，只能位于用户主目录下
+        if path_policy::check(&settings.default_download_dir, path_policy::Access::Write).is_err() {
+            path_policy::allow_requested_dir(&settings.default_download_dir)?;
+        }
+    }
+    {
+        let mut store = STORE.lock().unwrap();
+        store.settings = settings.clone();
+        store.save()?;
+    }
+    configure_logger(&settings);
+    log::info!("配置已更新");
+
+    if settings.max_concurrent_downloads != previous.max_concurrent_downloads {
+        queue::pump(app);
+    }
+    if settings.trash_retention_days != previous.trash_retention_days {
+        intermediates::purge_trash();
+    }
+    if settings.log_max_total_size_mb != previous.log_max_total_size_mb {
+        logger::enforce_total_size();
+    }
+    let _ = app.emit("settings-changed", settings.clone());
+    Ok(settings)
+}
+
+// ==================== 配置命令 ====================
+
+/// 读取配置
+#[command]
+pub async fn get_settings() -> AppResult<AppSettings> {
+    Ok(get())
+}
+
+/// 部分更新配置（`patch` 中未出现的字段保持不变），返回更新后的配置；
+/// 工具路径会被执行，只能通过 `pick_tool_path` 设置，`patch` 中的 toolPaths 被忽略
+#[command]
+pub async fn update_settings(app: tauri::AppHandle, patch: Map<String, Value>) -> AppResult<AppSettings> {
+    let mut object = match serde_json::to_value(get()) {
+        Ok(Value::Object(object)) => object,
+        _ => return Err(AppError::internal("序列化配置失败")),
+    };
+    for (key, value) in patch {
+        if key != "version" && key != "toolPaths" && !value.is_null() {
+            object.insert(key, value);
+        }
+    }
+    let settings = serde_json::from_value(Value::Object(object))
+        .map_err(|e| AppError::invalid_argument(format!("配置不合法: {}", e)))?;
+    replace(&app, settings)
+}
+
+/// 恢复默认配置
+#[command]
+pub async fn reset_settings(app: tauri::AppHandle) -> AppResult<AppSettings> {
+    replace(&app, AppSettings::default())
+}
+
+/// 通过系统对话框选择外部工具的可执行文件，返回更新后的配置（取消时返回 None）
+#[command]
+pub async fn pick_tool_path(app: tauri::AppHandle, tool: String) -> AppResult<Option<AppSettings>> {
+    if !OVERRIDABLE_TOOLS.contains(&tool.as_str()) {
+        return Err(AppError::invalid_argument(format!("不支持指定路径的工具: {}", tool)));
+    }
+    let selected = app.dialog().file()
+        .set_title(format!("选择 {} 可执行文件", tool))
+        .blocking_pick_file();
+    let path = match selected {
+        Some(selected) => selected.into_path()
+            .map_err(|e| AppError::invalid_argument(format!("无法识别所选文件: {}", e)))?,
+        None => return Ok(None),
+    };
+    let path = check_tool_path(&tool, &path)
+        .map_err(|reason| AppError::invalid_argument(format!("所选文件不能作为 {} 使用: {}", tool, reason)))?;
+
+    let mut settings = get();
+    settings.tool_paths.insert(tool.clone(), path.to_string_lossy().to_string());
+    let settings = replace(&app, settings)?;
+    logger::write_audit_log(&format!("设置工具路径: {} = {:?}", tool, path));
+    Ok(Some(settings))
+}
+
+/// 清除工具路径，恢复使用内置工具
+#[command]
+pub async fn clear_tool_path(app: tauri::AppHandle, tool: String) -> AppResult<AppSettings> {
+    let mut settings = get();
+    if settings.tool_paths.remove(&tool).is_none() {
+        return Ok(settings);
+    }
+    let settings = replace(&app, settings)?;
+    logger::write_audit_log(&format!("清除工具路径: {}", tool));
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn object(value: Value) -> Map<String, Value> {
+        match value {
+            Value::Object(object) => object,
+            _ => panic!("不是 JSON 对象"),
+        }
+    }
+
+    #[test]
+    fn migrates_v0_object_with_nulls() {
+        let (settings, changed) = parse(object(json!({
+            "defaultDownloadDir": "/downloads",
+            "theme": "dark",
+            "maxConcurrentDownloads": null,
+            "burnSubtitles": null,
+            "retryAttempts": 5,
+        })));
+        assert!(changed);
+        assert_eq!(settings.version, SETTINGS_VERSION);
+        assert_eq!(settings.default_download_dir, "/downloads");
+        assert_eq!(settings.theme, "dark");
+        assert_eq!(settings.retry_attempts, 5);
+        // null 由默认值补齐
+        let d = AppSettings::default();
+        assert_eq!(settings.max_concurrent_downloads, d.max_concurrent_downloads);
+        assert_eq!(settings.burn_subtitles, d.burn_subtitles);
+    }
+
+    #[test]
+    fn empty_object_is_v0_defaults() {
+        let (settings, changed) = parse(Map::new());
+        assert!(changed);
+        assert_eq!(settings, AppSettings::default());
+    }
+
+    #[test]
+    fn current_version_is_unchanged() {
+        let value = serde_json::to_value(AppSettings::default()).unwrap();
+        let (settings, changed) = parse(object(value));
+        assert!(!changed);
+        assert_eq!(settings, AppSettings::default());
+    }
+
+    #[test]
+    fn invalid_values_are_restored_to_default() {
+        let (settings, changed) = parse(object(json!({
+            "version": SETTINGS_VERSION,
+            "theme": "purple",
+            "preferQuality": "best",
+            "maxConcurrentDownloads": 0,
+            "timeout": MAX_TIMEOUT_MS + 1,
+            "logFormat": "xml",
+            "logMaxFileSizeMb": 0,
+            "subtitleLanguage": "en",
+        })));
+        assert!(changed);
+        let d = AppSettings::default();
+        assert_eq!(settings.theme, d.theme);
+        assert_eq!(settings.prefer_quality, d.prefer_quality);
+        assert_eq!(settings.max_concurrent_downloads, d.max_concurrent_downloads);
+        assert_eq!(settings.timeout, d.timeout);
+        assert_eq!(settings.log_format, d.log_format);
+        assert_eq!(settings.log_max_file_size_mb, d.log_max_file_size_mb);
+        assert_eq!(settings.subtitle_language, "en");
+    }
+
+    #[test]
+    fn wrong_typed_and_unknown_fields_are_ignored() {
+        let (settings, _) = parse(object(json!({
+            "theme": "dark",
+            "retryAttempts": "many",
+            "useTrash": "yes",
+            "autoSelectQuality": false,
+            "removedOption": 42,
+        })));
+        let d = AppSettings::default();
+        assert_eq!(settings.theme, "dark");
+        assert!(!settings.auto_select_quality);
+        assert_eq!(settings.retry_attempts, d.retry_attempts);
+        assert_eq!(settings.use_trash, d.use_trash);
+    }
+
+    #[test]
+    fn unusable_tool_paths_are_dropped() {
+        let (settings, changed) = parse(object(json!({
+            "version": SETTINGS_VERSION,
+            "toolPaths": { "ffmpeg": "/nonexistent/ffmpeg" },
+        })));
+        assert!(changed);
+        assert!(settings.tool_paths.is_empty());
+    }
+
+    #[test]
+    fn newer_version_keeps_known_fields() {
+        let (settings, changed) = parse(object(json!({
+            "version": SETTINGS_VERSION + 1,
+            "theme": "auto",
+            "futureOption": true,
+        })));
+        assert!(!changed);
+        assert_eq!(settings.version, SETTINGS_VERSION);
+        assert_eq!(settings.theme, "auto");
+    }
+
+    #[test]
+    fn load_backs_up_file_before_migration() {
+        let dir = std::env::temp_dir().join(format!("settings-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(SETTINGS_FILE);
+        std::fs::write(&path, r#"{"theme":"dark","timeout":null}"#).unwrap();
+
+        let (settings, changed) = load(&path).unwrap();
+        assert!(changed);
+        assert_eq!(settings.theme, "dark");
+        let backup = path.with_extension("v0.bak.json");
+        assert_eq!(std::fs::read_to_string(&backup).unwrap(), r#"{"theme":"dark","timeout":null}"#);
+
+        // 缺失或损坏的文件使用默认配置
+        std::fs::write(&path, "not json").unwrap();
+        assert_eq!(load(&path).unwrap().0, AppSettings::default());
+        assert_eq!(load(&dir.join("missing.json")).unwrap(), (AppSettings::default(), true));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
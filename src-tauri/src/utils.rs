@@ -3,6 +3,7 @@ use std::env;
 use tauri::command;
 use std::fs;
 use dirs;
+use crate::error::{AppError, AppResult};
 
 /// Get the system's temporary directory
 #[command]
@@ -13,9 +14,10 @@ pub fn get_temp_dir() -> String {
 
 /// Create a directory if it doesn't exist
 #[command]
-pub fn create_dir(path: String) -> Result<(), String> {
+pub fn create_dir(path: String) -> AppResult<()> {
+    crate::path_policy::check(&path, crate::path_policy::Access::Write)?;
     fs::create_dir_all(&path)
-        .map_err(|e| format!("Failed to create directory {}: {}", path, e))?;
+        .map_err(|e| AppError::io(&format!("Failed to create directory {}", path), &e))?;
     Ok(())
 }
 
@@ -2,7 +2,7 @@
  * API 类型和函数测试
  */
 
-import { isSuccessResponse, isErrorResponse } from '../types/api';
+import { isSuccessResponse, isErrorResponse, isAppError, errorMessage } from '../types/api';
 import type { GetKeysResponse, AuthResponse } from '../types/api';
 
 describe('API Type Guards', () => {
@@ -71,4 +71,18 @@ describe('API Type Guards', () => {
       expect(isErrorResponse(response)).toBe(false);
     });
   });
+
+  describe('errorMessage', () => {
+    it('应该识别后端命令错误', () => {
+      const error = { code: 'DISK_FULL', message: '磁盘空间不足', details: 'No space left on device' };
+      expect(isAppError(error)).toBe(true);
+      expect(errorMessage(error)).toBe('磁盘空间不足');
+    });
+
+    it('应该兼容字符串和 Error', () => {
+      expect(isAppError('参数不能为空')).toBe(false);
+      expect(errorMessage('参数不能为空')).toBe('参数不能为空');
+      expect(errorMessage(new Error('网络错误'))).toBe('网络错误');
+    });
+  });
 });
@@ -2,6 +2,7 @@ import { useState, useEffect } from 'react';
 import type { CSSProperties } from 'react';
 import { invoke } from '@tauri-apps/api/core';
 import { downieTheme } from '../styles/downie-theme';
+import { errorMessage } from '../types/api.d';
 
 import { CustomSelect } from './ui/CustomSelect';
 
@@ -49,7 +50,7 @@ export function LogViewer({ style }: LogViewerProps) {
       setLogContent(content);
     } catch (error) {
       console.error('加载日志内容失败:', error);
-      setLogContent(`加载日志内容失败: ${error}`);
+      setLogContent(`加载日志内容失败: ${errorMessage(error)}`);
     } finally {
       setLoading(false);
     }
@@ -75,7 +76,7 @@ export function LogViewer({ style }: LogViewerProps) {
     } catch (error) {
       console.error('清理日志失败:', error);
       const { sendNotification } = await import('@tauri-apps/plugin-notification');
-      sendNotification({ title: 'GAGA Client', body: `清理日志失败: ${error}` });
+      sendNotification({ title: 'GAGA Client', body: `清理日志失败: ${errorMessage(error)}` });
     }
   };
 
@@ -1,10 +1,9 @@
-import { useState, useCallback, useEffect } from 'react';
+import { useState, useCallback, useEffect, useRef } from 'react';
 import { listen, type UnlistenFn } from '@tauri-apps/api/event';
-import { readDir, stat } from '@tauri-apps/plugin-fs';
-import { join } from '@tauri-apps/api/path';
  
-import { isSuccessResponse } from '../types/api.d';
-import type { GetKeysResponse } from '../types/api.d';
+import { isSuccessResponse, errorMessage } from '../types/api.d';
+import type { GetKeysResponse, AppErrorCode } from '../types/api.d';
+import type { DownloadRequest } from '../types/task';
 
 export interface VideoInfo {
   Title: string;
@@ -22,6 +21,54 @@ interface NM3u8dlLogPayload {
   progress?: number;
   speed?: string;
   timestamp: string;
+  job_id?: string;
+  fps?: number;
+  eta_seconds?: number;
+  error_code?: AppErrorCode;
+}
+
+// 按错误码给出的处理建议
+const ERROR_HINTS: Partial<Record<AppErrorCode, string>> = {
+  TOOL_MISSING: '请检查下载工具是否完整',
+  DISK_FULL: '磁盘空间不足，请清理后重试',
+  FORBIDDEN: '服务器拒绝访问，链接可能已过期',
+  NOT_FOUND: '资源不存在，链接可能已失效',
+  DECRYPTION_FAILED: '解密失败，请确认密钥是否正确',
+  PERMISSION_DENIED: '没有写入权限，请更换保存目录',
+  PATH_NOT_ALLOWED: '请在设置中重新选择下载目录',
+};
+
+// 后端任务阶段事件负载
+interface JobPhasePayload {
+  jobId: string;
+  phase: 'queued' | 'loading' | 'downloading' | 'decrypting' | 'merging' | 'burning' | 'paused' | 'completed' | 'failed' | 'cancelled' | 'postProcessFailed';
+  previous?: string;
+  timestamp: string;
+}
+
+// 后端后期处理进度事件负载
+interface PostProcessProgressPayload {
+  jobId: string;
+  step: 'waiting' | 'merging' | 'muxing' | 'burning' | 'copying' | 'cleanup' | 'done' | 'failed';
+  stepIndex: number;
+  totalSteps: number;
+  message: string;
+  timestamp: string;
+}
+
+// 后端按流推送的结构化下载进度
+export interface StreamProgress {
+  jobId?: string;
+  stream: 'video' | 'audio' | 'subtitle';
+  name: string;
+  percent: number;
+  downloadedSegments?: number;
+  totalSegments?: number;
+  downloadedBytes?: number;
+  totalBytes?: number;
+  speed?: number;
+  etaSeconds?: number;
+  timestamp: string;
 }
 
 // 格式化后用于UI展示的日志条目
@@ -31,7 +78,7 @@ export interface LogEntry {
   timestamp: string;
 }
 
-export type DownloadStatus = 'pending' | 'downloading' | 'completed' | 'failed';
+export type DownloadStatus = 'pending' | 'downloading' | 'completed' | 'failed' | 'paused';
 
 export type DownloadPhase = 'pending' | 'downloading' | 'decrypting' | 'merging' | 'burning' | 'completed' | 'failed';
 
@@ -41,10 +88,13 @@ interface UseDownloadReturn {
   error: string;
   logs: LogEntry[];
   downloadSpeed: string;
+  streams: StreamProgress[];
   phase: DownloadPhase;
   currentTask: VideoInfo | null;
   startDownload: (videoInfo: VideoInfo, outputPath: string) => Promise<void>;
   cancelDownload: () => void;
+  pauseDownload: () => Promise<void>;
+  resumeDownload: () => Promise<void>;
   clearError: () => void;
   setError: (message: string) => void;
 }
@@ -59,9 +109,12 @@ export function useDownload(): UseDownloadReturn {
   const [error, setError] = useState('');
   const [logs, setLogs] = useState<LogEntry[]>([]);
   const [downloadSpeed, setDownloadSpeed] = useState('');
+  const [streams, setStreams] = useState<StreamProgress[]>([]);
   const [phase, setPhase] = useState<DownloadPhase>('pending');
     const [currentTask, setCurrentTask] = useState<VideoInfo | null>(null);
   const [downloadInfo, setDownloadInfo] = useState<{ videoInfo: VideoInfo; outputPath: string } | null>(null);
+  // 后端返回的当前下载任务 ID，用于取消
+  const jobIdRef = useRef<string | null>(null);
 
   // 监听后端的下载日志事件
   useEffect(() => {
@@ -69,16 +122,15 @@ export function useDownload(): UseDownloadReturn {
 
     const subscribeToLogs = async () => {
       const u1 = await listen<NM3u8dlLogPayload>('n-m3u8dl-log', (event) => {
-        const { level, message, progress: newProgress, speed, timestamp } = event.payload;
+        const { level, message, progress: newProgress, speed, timestamp, error_code } = event.payload;
 
         // 1. 更新日志
         setLogs(prev => [...prev, { level: level as LogEntry['level'], message, timestamp }].slice(-200));
 
-        // 2. 如果是错误，设置失败状态
+        // 2. 记录错误信息（任务状态由 job-phase 事件决定）
         if (level === 'ERROR') {
-          setError(message);
-          setStatus('failed');
-          setPhase('failed');
+          const hint = error_code ? ERROR_HINTS[error_code] : undefined;
+          setError(hint ? `${message}（${hint}）` : message);
           return;
         }
 
@@ -86,39 +138,107 @@ export function useDownload(): UseDownloadReturn {
         if (typeof newProgress === 'number') {
           const normalized = Math.max(0, Math.min(100, Math.round(newProgress)));
           setProgress(normalized);
-          setStatus(prevStatus => {
-            if (prevStatus === 'failed') return prevStatus;
-            return 'downloading';
-          });
         }
         if (speed) {
           setDownloadSpeed(speed);
         }
+      });
+      unlisteners.push(u1);
 
-        if (typeof message === 'string') {
-          const m = message.toLowerCase();
-          if (
-            m.includes('下载完成') ||
-            m.includes('下载任务完成') ||
-            m.includes('下载任务执行成功') ||
-            m.includes('done') ||
-            m.includes('success')
-          ) {
+      // 后端状态机推送的任务阶段
+      const uPhase = await listen<JobPhasePayload>('job-phase', (event) => {
+        const { jobId, phase: backendPhase } = event.payload;
+        if (jobIdRef.current && jobId !== jobIdRef.current) return;
+
+        switch (backendPhase) {
+          case 'queued':
+          case 'loading':
+          case 'downloading':
+            setStatus('downloading');
+            setPhase('downloading');
+            break;
+          case 'decrypting':
+          case 'merging':
+            setStatus('downloading');
+            setPhase(backendPhase);
+            break;
+          case 'burning':
+            setPhase('burning');
+            break;
+          case 'paused':
+            setStatus('paused');
+            break;
+          case 'completed':
             setStatus('completed');
             setProgress(100);
             setPhase('completed');
-          } else if (m.includes('正在解密') || m.includes('decrypting')) {
-            setPhase('decrypting');
-          } else if (m.includes('正在合并') || m.includes('binary merging') || m.includes('merging')) {
-            setPhase('merging');
+            break;
+          case 'failed':
+            setStatus('failed');
+            setPhase('failed');
+            break;
+          case 'postProcessFailed':
+            // 下载已完成，只有后期处理失败，下载文件仍可使用
+            setStatus('completed');
+            setPhase('completed');
+            break;
+          case 'cancelled':
+            setStatus('pending');
+            break;
+        }
+      });
+      unlisteners.push(uPhase);
+
+      const uProgress = await listen<StreamProgress>('download-progress', (event) => {
+        const update = event.payload;
+        if (update.jobId && jobIdRef.current && update.jobId !== jobIdRef.current) return;
+
+        setStreams(prev => {
+          const rest = prev.filter(s => !(s.stream === update.stream && s.name === update.name));
+          return [...rest, update];
+        });
+
+        // 总进度与速度以视频流为准
+        if (update.stream === 'video') {
+          setProgress(Math.max(0, Math.min(100, Math.round(update.percent))));
+          if (typeof update.speed === 'number') {
+            setDownloadSpeed(`${(update.speed / 1024 / 1024).toFixed(2)}MBps`);
           }
         }
       });
-      unlisteners.push(u1);
+      unlisteners.push(uProgress);
+
+      // 后端后期处理流水线的步骤进度
+      const uPost = await listen<PostProcessProgressPayload>('post-process-progress', (event) => {
+        const { jobId, step, message, timestamp } = event.payload;
+        if (jobIdRef.current && jobId !== jobIdRef.current) return;
+        const level: LogEntry['level'] = step === 'failed' ? 'ERROR' : 'INFO';
+        setLogs(prev => [...prev, { level, message, timestamp }].slice(-200));
+        if (step === 'failed') {
+          setError(message);
+        }
+      });
+      unlisteners.push(uPost);
+
+      // 后期处理中 ffmpeg 的百分比、帧率、速度倍率与剩余时间
+      const uFfmpeg = await listen<NM3u8dlLogPayload>('ffmpeg-progress', (event) => {
+        const { job_id, progress: ffmpegProgress, speed } = event.payload;
+        if (!job_id || job_id !== jobIdRef.current) return;
+        if (typeof ffmpegProgress === 'number') {
+          setProgress(Math.max(0, Math.min(100, Math.round(ffmpegProgress))));
+        }
+        if (speed) {
+          setDownloadSpeed(speed);
+        }
+      });
+      unlisteners.push(uFfmpeg);
 
-      const u2 = await listen<string>('burn-subtitle-progress', (event) => {
-        const line = event.payload;
-        setLogs(prev => [...prev, { level: 'INFO' as LogEntry['level'], message: `字幕烧录进度: ${line}`, timestamp: new Date().toISOString() }].slice(-200));
+      const u2 = await listen<NM3u8dlLogPayload>('burn-subtitle-progress', (event) => {
+        const { message, progress: burnProgress, timestamp } = event.payload;
+        setLogs(prev => [...prev, { level: 'INFO' as LogEntry['level'], message: `字幕烧录${message}`, timestamp }].slice(-200));
+        if (typeof burnProgress === 'number') {
+          setProgress(Math.max(0, Math.min(100, Math.round(burnProgress))));
+        }
         setPhase('burning');
       });
       unlisteners.push(u2);
@@ -167,6 +287,7 @@ export function useDownload(): UseDownloadReturn {
       setProgress(0);
       setLogs([]);
       setDownloadSpeed('');
+      setStreams([]);
       setError('');
       setPhase('downloading');
 
@@ -176,21 +297,17 @@ export function useDownload(): UseDownloadReturn {
         const { getDeviceId } = await import('../utils/deviceId');
         const { validateLocalAuth } = await import('../utils/auth');
 
-        // 2. 构建下载参数 (修正后的版本)
-        const args = [
-          videoInfo.MPD,
-          '--save-dir', outputPath,
-          '--save-name', videoInfo.Title,
-          '--tmp-dir', `${outputPath}/.temp_${Date.now()}`,
-          '--thread-count', '16',
-          '--select-video', 'best',
-          '--select-audio', 'best',
-          '--select-subtitle', 'lang=zh-Hans',
-          '--binary-merge',
-          '--no-ansi-color',
-          '--no-log',
-          '--log-level', 'INFO',
-        ];
+        // 2. 构建下载请求（由后端转换为下载参数）
+        const keys: string[] = [];
+        const request: DownloadRequest = {
+          url: videoInfo.MPD,
+          saveDir: outputPath,
+          saveName: videoInfo.Title,
+          threadCount: 16,
+          select: { video: 'best', audio: 'best', subtitle: 'lang=zh-Hans' },
+          keys,
+          binaryMerge: true,
+        };
 
         // 3. 如果需要，获取解密密钥
         if (videoInfo.PSSH && videoInfo.LicenseURL) {
@@ -208,7 +325,7 @@ export function useDownload(): UseDownloadReturn {
 
             if (isSuccessResponse(keysResponse) && keysResponse.keys?.length) {
               keysResponse.keys.forEach(keyInfo => {
-                args.push('--key', `${keyInfo.kid}:${keyInfo.key}`);
+                keys.push(`${keyInfo.kid}:${keyInfo.key}`);
               });
             } else {
               throw new Error(keysResponse.message || '获取解密密钥失败');
@@ -220,12 +337,8 @@ export function useDownload(): UseDownloadReturn {
           }
         }
 
-        // 4. 调用后端命令
-        await invoke<string>('exec_download_command', {
-          command: 'N_m3u8DL-RE',
-          args: args,
-          workingDir: outputPath,
-        });
+        // 4. 调用后端命令（进程启动后立即返回任务 ID）
+        jobIdRef.current = await invoke<string>('start_download', { request });
 
         // 注意：后续的状态更新（如完成、失败）由事件监听器处理
         // 这里不再需要手动设置 setStatus('completed') 或处理错误
@@ -233,7 +346,7 @@ export function useDownload(): UseDownloadReturn {
 
       } catch (err: any) {
         // 这个 catch 主要捕获 invoke 调用失败或参数错误等问题
-        setError(`下载启动失败: ${err}`);
+        setError(`下载启动失败: ${errorMessage(err)}`);
         setStatus('failed');
       }
     },
@@ -241,10 +354,44 @@ export function useDownload(): UseDownloadReturn {
   );
 
   const cancelDownload = useCallback(() => {
+    const jobId = jobIdRef.current;
+    jobIdRef.current = null;
+    if (jobId) {
+      import('@tauri-apps/api/core')
+        .then(({ invoke }) => invoke('cancel_job', { jobId }))
+        .catch(err => {
+          setLogs(prev => [...prev, { level: 'WARN' as LogEntry['level'], message: `取消任务失败: ${errorMessage(err)}`, timestamp: new Date().toISOString() }].slice(-200));
+        });
+    }
+    setDownloadInfo(null);
     setStatus('pending');
     setProgress(0);
   }, []);
 
+  const pauseDownload = useCallback(async () => {
+    const jobId = jobIdRef.current;
+    if (!jobId) return;
+    try {
+      const { invoke } = await import('@tauri-apps/api/core');
+      await invoke('pause_job', { jobId });
+      setStatus('paused');
+    } catch (err) {
+      setLogs(prev => [...prev, { level: 'WARN' as LogEntry['level'], message: `暂停任务失败: ${errorMessage(err)}`, timestamp: new Date().toISOString() }].slice(-200));
+    }
+  }, []);
+
+  const resumeDownload = useCallback(async () => {
+    const jobId = jobIdRef.current;
+    if (!jobId) return;
+    try {
+      const { invoke } = await import('@tauri-apps/api/core');
+      await invoke('resume_job', { jobId });
+      setStatus('downloading');
+    } catch (err) {
+      setLogs(prev => [...prev, { level: 'WARN' as LogEntry['level'], message: `恢复任务失败: ${errorMessage(err)}`, timestamp: new Date().toISOString() }].slice(-200));
+    }
+  }, []);
+
   const handlePostProcessing = useCallback(async () => {
     if (!downloadInfo) {
       setError('下载信息丢失，无法进行后期处理。');
@@ -253,97 +400,27 @@ export function useDownload(): UseDownloadReturn {
     }
 
     const { videoInfo, outputPath } = downloadInfo;
+    // 后期处理在后端进行，完成时会再次推送 completed 阶段，先清空下载信息避免重复触发
+    setDownloadInfo(null);
     setLogs(prev => [...prev, { level: 'INFO' as LogEntry['level'], message: '下载完成，开始进行后期处理...', timestamp: new Date().toISOString() }]);
 
     try {
         const { invoke } = await import('@tauri-apps/api/core');
-        const entries: any[] = await readDir(outputPath);
-
-        const videoFile = entries.find(e => e.name?.startsWith(videoInfo.Title) && e.name.endsWith('.mp4'));
-        const audioFile = entries.find(e => e.name?.startsWith(videoInfo.Title) && e.name.endsWith('.m4a'));
-        const subtitleFile = entries.find(e => e.name?.startsWith(videoInfo.Title) && (e.name.endsWith('.srt') || e.name.endsWith('.vtt')));
-
-        if (!videoFile) {
-            setLogs(prev => [...prev, { level: 'WARN' as LogEntry['level'], message: '未找到视频文件，跳过后处理。可能无需合并。', timestamp: new Date().toISOString() }]);
-            return;
-        }
-
-        // 如果只有视频文件，没有独立音频文件，说明 N_m3u8DL-RE 可能已经合并了音视频
-        if (videoFile && !audioFile) {
-             setLogs(prev => [...prev, { level: 'INFO' as LogEntry['level'], message: '检测到音视频已合并，仅处理字幕烧录。', timestamp: new Date().toISOString() }]);
-        }
-
-        const finalOutputName = `${videoInfo.Title}_processed.mp4`;
-        const finalOutputPath = await join(outputPath, finalOutputName);
-        const videoInputPath = await join(outputPath, videoFile.name);
-        const mergedOutputName = `${videoInfo.Title}_merged.mp4`;
-        const mergedOutputPath = await join(outputPath, mergedOutputName);
-
-        let sourceForSubtitles = videoInputPath;
-
-        const waitStable = async (paths: string[], attempts = 5, intervalMs = 1000) => {
-          for (let i = 0; i < attempts; i++) {
-            const sizes1 = await Promise.all(paths.map(async p => {
-              const s = await stat(p);
-              return (s as any).size || 0;
-            }));
-            await new Promise(res => setTimeout(res, intervalMs));
-            const sizes2 = await Promise.all(paths.map(async p => {
-              const s = await stat(p);
-              return (s as any).size || 0;
-            }));
-            const stable = sizes1.length > 0 && sizes1.every((sz, idx) => sz > 0 && sz === sizes2[idx]);
-            if (stable) return true;
-          }
-          throw new Error('文件未稳定');
-        };
-
-        const filesToCheck: string[] = [videoInputPath];
-        if (audioFile) {
-          const audioInputPath = await join(outputPath, audioFile.name);
-          filesToCheck.push(audioInputPath);
-        }
-        await waitStable(filesToCheck);
-
-        if (audioFile) {
-            const audioInputPath = await join(outputPath, audioFile.name);
-            const mergeArgs: string[] = ['-y', '-i', videoInputPath, '-i', audioInputPath, '-map', '0:v:0', '-map', '1:a:0', '-c', 'copy', mergedOutputPath];
-            setLogs(prev => [...prev, { level: 'INFO' as LogEntry['level'], message: `执行音视频合并...`, timestamp: new Date().toISOString() }]);
-            setPhase('merging');
-            await invoke<string>('exec_merge_command', { command: 'ffmpeg', args: mergeArgs });
-            sourceForSubtitles = mergedOutputPath;
-        }
-
-        if (subtitleFile) {
-            const copyArgs: string[] = ['-y', '-i', sourceForSubtitles, '-c', 'copy', finalOutputPath];
-            setLogs(prev => [...prev, { level: 'INFO' as LogEntry['level'], message: `字幕烧录已禁用，复制输出...`, timestamp: new Date().toISOString() }]);
-            await invoke<string>('exec_merge_command', { command: 'ffmpeg', args: copyArgs });
-        } else {
-            const copyArgs: string[] = ['-y', '-i', sourceForSubtitles, '-c', 'copy', finalOutputPath];
-            setLogs(prev => [...prev, { level: 'INFO' as LogEntry['level'], message: `无字幕，复制输出...`, timestamp: new Date().toISOString() }]);
-            await invoke<string>('exec_merge_command', { command: 'ffmpeg', args: copyArgs });
-        }
-
-        setLogs(prev => [...prev, { level: 'INFO' as LogEntry['level'], message: `处理完成，输出文件位于: ${finalOutputPath}`, timestamp: new Date().toISOString() }]);
-        try {
-          const toDelete: string[] = [];
-          toDelete.push(videoInputPath);
-          if (audioFile) {
-            const audioInputPath = await join(outputPath, audioFile.name);
-            toDelete.push(audioInputPath);
-            toDelete.push(mergedOutputPath);
-          }
-          await invoke<number>('delete_files', { paths: toDelete });
-          setLogs(prev => [...prev, { level: 'INFO' as LogEntry['level'], message: `已清理中间文件`, timestamp: new Date().toISOString() }].slice(-200));
-        } catch (cleanupErr: any) {
-          setLogs(prev => [...prev, { level: 'WARN' as LogEntry['level'], message: `清理中间文件失败: ${cleanupErr.message || cleanupErr}`, timestamp: new Date().toISOString() }].slice(-200));
-        }
-        setPhase('completed');
-
+        // 复用下载任务 ID，后期处理的阶段事件和取消操作都沿用该 ID
+        const jobId = await invoke<string>('post_process_job', {
+          request: {
+            outputDir: outputPath,
+            title: videoInfo.Title,
+            jobId: jobIdRef.current ?? undefined,
+            subtitleMode: 'none',
+            cleanup: true,
+          },
+        });
+        jobIdRef.current = jobId;
     } catch (procError: any) {
-        const errorMessage = procError.message || procError.toString();
-        setError(`后期处理失败: ${errorMessage}`);
-        setLogs(prev => [...prev, { level: 'ERROR' as LogEntry['level'], message: `后期处理失败: ${errorMessage}`, timestamp: new Date().toISOString() }]);
+        const message = errorMessage(procError);
+        setError(`后期处理失败: ${message}`);
+        setLogs(prev => [...prev, { level: 'ERROR' as LogEntry['level'], message: `后期处理失败: ${message}`, timestamp: new Date().toISOString() }]);
         setStatus('failed');
         setPhase('failed');
     }
@@ -352,9 +429,9 @@ export function useDownload(): UseDownloadReturn {
   useEffect(() => {
     if (status === 'completed' && downloadInfo) {
       handlePostProcessing().catch(err => {
-        const errorMessage = err.message || err.toString();
-        setError(`调用后期处理时发生错误: ${errorMessage}`);
-        setLogs(prev => [...prev, { level: 'ERROR' as LogEntry['level'], message: `调用后期处理时发生错误: ${errorMessage}`, timestamp: new Date().toISOString() }]);
+        const message = errorMessage(err);
+        setError(`调用后期处理时发生错误: ${message}`);
+        setLogs(prev => [...prev, { level: 'ERROR' as LogEntry['level'], message: `调用后期处理时发生错误: ${message}`, timestamp: new Date().toISOString() }]);
         setStatus('failed');
         setPhase('failed');
       });
@@ -371,10 +448,13 @@ export function useDownload(): UseDownloadReturn {
     error,
     logs,
     downloadSpeed,
+    streams,
     phase,
     currentTask,
     startDownload,
     cancelDownload,
+    pauseDownload,
+    resumeDownload,
     clearError,
     setError,
   };
@@ -1,6 +1,6 @@
 import { useState, useEffect } from 'react';
 import type { CSSProperties } from 'react';
-import { readHistory, clearHistory } from '../utils/history';
+import { readHistory, clearHistory, onHistoryChanged } from '../utils/history';
 import type { DownloadRecord } from '../types/history';
 import { AppLayout } from '../components/layout/AppLayout';
 import { navigate } from '../utils/navigation';
@@ -158,6 +158,9 @@ const getIcon = (status: DownloadRecord['status']) => {
   if (status === 'failed') {
     return { glyph: 'X', color: '#ff3b30' };
   }
+  if (status === 'cancelled') {
+    return { glyph: '–', color: '#8e8e93' };
+  }
   return { glyph: '…', color: '#ffab00' };
 };
 
@@ -166,6 +169,11 @@ export default function HistoryPage() {
 
   useEffect(() => {
     loadHistory();
+    // 下载任务或其他窗口修改历史时刷新
+    const unlisten = onHistoryChanged(() => loadHistory());
+    return () => {
+      unlisten.then(fn => fn());
+    };
   }, []);
 
   const loadHistory = async () => {
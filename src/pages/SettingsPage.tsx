@@ -1,7 +1,7 @@
 import { useState, useEffect } from 'react';
 import type { CSSProperties } from 'react';
 import { downieTheme, commonStyles } from '../styles/downie-theme';
-import { readSettings, updateSettings } from '../utils/settings';
+import { readSettings, updateSettings, onSettingsChanged } from '../utils/settings';
 import type { AppSettings } from '../types/config';
 import { openWindow } from '../utils/windowManager';
 import { AppLayout } from '../components/layout/AppLayout';
@@ -105,6 +105,11 @@ export default function SettingsPage({ authed, deviceId }: SettingsPageProps) {
 
   useEffect(() => {
     loadSettings();
+    // 其他窗口保存配置时同步
+    const unlisten = onSettingsChanged(setSettings);
+    return () => {
+      unlisten.then(fn => fn());
+    };
   }, []);
 
   const loadSettings = async () => {
@@ -118,10 +123,11 @@ export default function SettingsPage({ authed, deviceId }: SettingsPageProps) {
 
   const handleSelectDirectory = async () => {
     try {
-      const { open } = await import('@tauri-apps/plugin-dialog');
-      const selected = await open({ directory: true, title: '选择默认下载目录' });
+      // 由后端弹出对话框，所选目录会被登记为允许写入的目录
+      const { invoke } = await import('@tauri-apps/api/core');
+      const selected = await invoke<string | null>('pick_save_dir', { title: '选择默认下载目录' });
       if (selected) {
-        const newSettings = { ...settings, defaultDownloadDir: selected as string };
+        const newSettings = { ...settings, defaultDownloadDir: selected };
         setSettings(newSettings);
         await updateSettings(newSettings);
       }
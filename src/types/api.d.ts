@@ -75,3 +75,43 @@ export function isSuccessResponse(response: { status: string }): boolean {
 export function isErrorResponse(response: { status: string }): boolean {
   return response.status === 'failed' || response.status === 'error';
 }
+
+// 后端命令错误（Tauri 命令失败时 reject 的值）
+export type AppErrorCode =
+  | 'TOOL_MISSING'
+  | 'DISK_FULL'
+  | 'PERMISSION_DENIED'
+  | 'FORBIDDEN'
+  | 'NOT_FOUND'
+  | 'NETWORK'
+  | 'TIMEOUT'
+  | 'INVALID_ARGUMENT'
+  | 'UNSAFE_PATH'
+  | 'PATH_NOT_ALLOWED'
+  | 'CANCELLED'
+  | 'DECRYPTION_FAILED'
+  | 'JOB_NOT_FOUND'
+  | 'INVALID_STATE'
+  | 'PROCESS_FAILED'
+  | 'IO'
+  | 'INTERNAL';
+
+export interface AppError {
+  code: AppErrorCode;
+  message: string;
+  details?: string;
+}
+
+export function isAppError(error: unknown): error is AppError {
+  return typeof error === 'object' && error !== null
+    && typeof (error as AppError).code === 'string'
+    && typeof (error as AppError).message === 'string';
+}
+
+// 取出错误中面向用户的说明
+export function errorMessage(error: unknown): string {
+  if (isAppError(error) || error instanceof Error) {
+    return error.message;
+  }
+  return String(error);
+}
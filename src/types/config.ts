@@ -3,6 +3,9 @@
  */
 
 export interface AppSettings {
+  /** 配置格式版本，由后端维护 */
+  version?: number;
+
   // 下载设置
   defaultDownloadDir?: string;
   autoSelectQuality?: boolean;
@@ -11,6 +14,8 @@ export interface AppSettings {
   // 字幕设置
   burnSubtitles?: boolean;
   subtitleLanguage?: 'zh' | 'en' | 'all';
+  /** 烧录字幕时使用硬件编码器 */
+  hardwareEncoding?: boolean;
   
   // 界面设置
   theme?: 'light' | 'dark' | 'auto';
@@ -18,5 +23,52 @@ export interface AppSettings {
   // 高级设置
   maxConcurrentDownloads?: number;
   retryAttempts?: number;
+  /** 网络请求超时（毫秒） */
   timeout?: number;
+  /** 外部工具无进展超时（毫秒）：进度不再增加、也没有新的输出超过该时间时终止，0 表示不限制 */
+  inactivityTimeout?: number;
+  /** 外部工具总运行时间上限（毫秒），0 表示不限制 */
+  maxRunTime?: number;
+  /** 删除中间文件时移入回收站（否则直接删除） */
+  useTrash?: boolean;
+  /** 回收站保留天数 */
+  trashRetentionDays?: number;
+  /** 启动时清理超过天数的日志，0 表示不清理 */
+  logRetentionDays?: number;
+  /** 日志文件格式：text 为文本行，json 为每行一个 JSON 对象 */
+  logFormat?: 'text' | 'json';
+  /** 单个日志文件超过该大小（MB）时轮转并压缩为 .log.gz */
+  logMaxFileSizeMb?: number;
+  /** 日志目录总大小上限（MB），超过时删除最旧的日志，0 表示不限制 */
+  logMaxTotalSizeMb?: number;
+  /** 为每个任务单独写一份日志（logs/jobs/<任务 ID>.log） */
+  jobLogFiles?: boolean;
+  /** 外部工具路径（N_m3u8DL-RE / ffmpeg -> 可执行文件绝对路径），只读，通过 pickToolPath 设置 */
+  toolPaths?: Record<string, string>;
+}
+
+/**
+ * 配置包导入选项（未指定的部分默认导入，工具路径只有明确指定时才导入）
+ */
+export interface ImportOptions {
+  settings?: boolean;
+  toolPaths?: boolean;
+  queue?: boolean;
+  history?: boolean;
+  /** 冲突时覆盖本机的值（默认保留本机的值） */
+  overwrite?: boolean;
+}
+
+/**
+ * 配置包导入结果
+ */
+export interface ImportReport {
+  settingsApplied: boolean;
+  toolPathsImported: number;
+  toolPathsSkipped: number;
+  queueImported: number;
+  queueSkipped: number;
+  historyImported: number;
+  historySkipped: number;
+  warnings: string[];
 }
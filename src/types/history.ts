@@ -2,16 +2,97 @@
  * 下载历史记录类型定义
  */
 
+import type { AppErrorCode } from './api';
+
+export type DownloadStatus = 'downloading' | 'completed' | 'failed' | 'cancelled';
+
 export interface DownloadRecord {
   id: string;
   title: string;
   mpdUrl: string;
-  status: 'downloading' | 'completed' | 'failed';
+  status: DownloadStatus;
   progress: number;
   createdAt: string;
   completedAt?: string;
+  saveDir?: string;
   files?: string[];
   errorMessage?: string;
+  errorCode?: AppErrorCode;
   fileSize?: number;
   duration?: string;
+  /** 任务的单独日志文件（由后端设置，可通过 readJobLog 读取） */
+  logFile?: string;
+  /** 后期处理失败的原因（下载本身已完成，状态保持 completed） */
+  postProcessError?: string;
+}
+
+/**
+ * 历史记录查询条件
+ */
+export interface HistoryQuery {
+  status?: DownloadStatus[];
+  /** 创建时间下限（ISO 8601，含） */
+  from?: string;
+  /** 创建时间上限（ISO 8601，不含） */
+  to?: string;
+  /** 标题关键字 */
+  title?: string;
+  /** 全文搜索：每个词都须出现在标题或下载地址中 */
+  search?: string;
+  offset?: number;
+  limit?: number;
+}
+
+export interface HistoryPage {
+  records: DownloadRecord[];
+  /** 符合条件的记录总数 */
+  total: number;
+}
+
+export interface PeriodStats {
+  /** `YYYY-MM-DD` 或 ISO 周 `YYYY-Www` */
+  period: string;
+  total: number;
+  completed: number;
+  failed: number;
+  bytes: number;
+}
+
+export interface FailureStats {
+  /** 未识别原因的失败为 null */
+  code: AppErrorCode | null;
+  count: number;
+  rate: number;
+}
+
+export interface HostStats {
+  host: string;
+  count: number;
+  bytes: number;
+}
+
+/**
+ * 下载历史统计（`history_stats`）
+ */
+export interface HistoryStats {
+  total: number;
+  completed: number;
+  failed: number;
+  cancelled: number;
+  bytesDownloaded: number;
+  /** 平均速度（字节/秒） */
+  averageSpeed: number | null;
+  failureRate: number;
+  failuresByCode: FailureStats[];
+  perDay: PeriodStats[];
+  perWeek: PeriodStats[];
+  topHosts: HostStats[];
+}
+
+/**
+ * `history-changed` 事件
+ */
+export interface HistoryChangedPayload {
+  action: 'insert' | 'update' | 'delete' | 'clear';
+  ids: string[];
 }
@@ -0,0 +1,30 @@
+/**
+ * 日志记录类型定义
+ */
+
+export type LogLevelName = 'DEBUG' | 'INFO' | 'WARN' | 'ERROR' | 'AUDIT';
+
+/**
+ * 一条日志记录（文本格式的旧日志只有时间、级别、工具和消息）
+ */
+export interface LogRecord {
+  timestamp: string;
+  level: LogLevelName | string;
+  tool?: string;
+  jobId?: string;
+  phase?: 'queued' | 'loading' | 'downloading' | 'decrypting' | 'merging' | 'burning' | 'paused' | 'completed' | 'failed' | 'cancelled' | 'postProcessFailed';
+  message: string;
+  /** 结构化字段，如下载进度 */
+  fields?: Record<string, unknown>;
+}
+
+/**
+ * 日志记录筛选条件
+ */
+export interface LogFilter {
+  jobId?: string;
+  tool?: string;
+  levels?: LogLevelName[];
+  /** 消息关键字（不区分大小写） */
+  search?: string;
+}
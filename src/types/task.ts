@@ -22,3 +22,37 @@ export interface DownloadTask {
   createdAt: string;
   completedAt?: string;
 }
+
+// 下载请求（后端转换为 N_m3u8DL-RE 参数，对应 start_download / enqueue_download 的 request）
+export interface StreamSelectors {
+  video?: string;
+  audio?: string;
+  subtitle?: string;
+}
+
+export interface DownloadRequest {
+  version?: number;
+  url: string;
+  saveDir: string;
+  saveName: string;
+  tmpDir?: string;
+  threadCount?: number;
+  select?: StreamSelectors;
+  drop?: StreamSelectors;
+  headers?: Record<string, string>;
+  keys?: string[]; // KID:KEY
+  customRange?: string;
+  maxSpeed?: string;
+  retryCount?: number;
+  binaryMerge?: boolean;
+  live?: {
+    recordLimit?: string; // HH:mm:ss
+    realTimeMerge?: boolean;
+    waitTime?: number; // 秒
+  };
+  mux?: {
+    format?: 'mp4' | 'mkv';
+    muxer?: 'ffmpeg' | 'mkvmerge';
+    skipSub?: boolean;
+  };
+}
@@ -1,91 +1,80 @@
 /**
- * 下载历史记录管理工具
+ * 下载历史记录管理工具（记录由后端保存，下载任务的记录由后端自动维护）
  */
 
-import { readFile, writeFile } from '@tauri-apps/plugin-fs';
-import { appDataDir, join } from '@tauri-apps/api/path';
-import type { DownloadRecord } from '../types/history';
+import { invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+import type {
+  DownloadRecord,
+  HistoryChangedPayload,
+  HistoryPage,
+  HistoryQuery,
+  HistoryStats,
+} from '../types/history';
 
-const HISTORY_FILE = 'download_history.json';
+/**
+ * 按条件分页查询下载历史（新记录在前）
+ */
+export async function queryHistory(query: HistoryQuery = {}): Promise<HistoryPage> {
+  return await invoke<HistoryPage>('query_history', { query });
+}
 
 /**
- * 获取历史记录文件路径
+ * 按标题和下载地址全文搜索下载历史
  */
-async function getHistoryFilePath(): Promise<string> {
-  const appDir = await appDataDir();
-  return await join(appDir, HISTORY_FILE);
+export async function searchHistory(search: string, query: Omit<HistoryQuery, 'search'> = {}): Promise<HistoryPage> {
+  return await queryHistory({ ...query, search });
 }
 
 /**
- * 读取下载历史记录
+ * 统计下载历史（可按状态、时间范围、关键字过滤）
  */
-export async function readHistory(): Promise<DownloadRecord[]> {
-  try {
-    const filePath = await getHistoryFilePath();
-    const content = await readFile(filePath);
-    const text = new TextDecoder().decode(content);
-    return JSON.parse(text);
-  } catch (error) {
-    // 文件不存在或读取失败，返回空数组
-    return [];
-  }
+export async function getHistoryStats(query: HistoryQuery = {}): Promise<HistoryStats> {
+  return await invoke<HistoryStats>('history_stats', { query });
 }
 
 /**
- * 保存下载历史记录
+ * 读取下载历史记录
  */
-export async function writeHistory(records: DownloadRecord[]): Promise<void> {
-  try {
-    const filePath = await getHistoryFilePath();
-    const content = new TextEncoder().encode(JSON.stringify(records, null, 2));
-    await writeFile(filePath, content);
-  } catch (error) {
-    console.error('保存历史记录失败:', error);
-    throw error;
-  }
+export async function readHistory(limit = 200): Promise<DownloadRecord[]> {
+  const page = await queryHistory({ limit });
+  return page.records;
 }
 
 /**
  * 添加新的下载记录
  */
 export async function addDownloadRecord(record: Omit<DownloadRecord, 'id' | 'createdAt'>): Promise<DownloadRecord> {
-  const records = await readHistory();
-  const newRecord: DownloadRecord = {
-    ...record,
-    id: Date.now().toString(),
-    createdAt: new Date().toISOString(),
-  };
-  
-  records.unshift(newRecord); // 新记录放在前面
-  await writeHistory(records);
-  return newRecord;
+  return await invoke<DownloadRecord>('add_history_record', { record });
 }
 
 /**
- * 更新下载记录状态
+ * 更新下载记录
  */
-export async function updateDownloadRecord(id: string, updates: Partial<DownloadRecord>): Promise<void> {
-  const records = await readHistory();
-  const index = records.findIndex(record => record.id === id);
-  
-  if (index !== -1) {
-    records[index] = { ...records[index], ...updates };
-    await writeHistory(records);
-  }
+export async function updateDownloadRecord(
+  id: string,
+  updates: Partial<Omit<DownloadRecord, 'id' | 'mpdUrl' | 'createdAt'>>,
+): Promise<void> {
+  await invoke('update_history_record', { id, patch: updates });
 }
 
 /**
  * 删除下载记录
  */
 export async function deleteDownloadRecord(id: string): Promise<void> {
-  const records = await readHistory();
-  const filteredRecords = records.filter(record => record.id !== id);
-  await writeHistory(filteredRecords);
+  await invoke('delete_history_records', { ids: [id] });
 }
 
 /**
  * 清空所有历史记录
  */
 export async function clearHistory(): Promise<void> {
-  await writeHistory([]);
+  await invoke('clear_history');
+}
+
+/**
+ * 监听历史记录变更（任意窗口或下载任务修改记录时触发）
+ */
+export async function onHistoryChanged(handler: (payload: HistoryChangedPayload) => void): Promise<UnlistenFn> {
+  return await listen<HistoryChangedPayload>('history-changed', event => handler(event.payload));
 }
@@ -0,0 +1,20 @@
+/**
+ * 日志文件读取工具
+ */
+
+import { invoke } from '@tauri-apps/api/core';
+import type { LogFilter, LogRecord } from '../types/log';
+
+/**
+ * 读取日志文件并解析为记录，返回符合条件的最后 maxLines 条
+ */
+export async function readLogRecords(filePath: string, filter: LogFilter = {}, maxLines?: number): Promise<LogRecord[]> {
+  return await invoke<LogRecord[]>('read_log_records', { filePath, maxLines, filter });
+}
+
+/**
+ * 读取任务的单独日志文件（历史记录的 id 即任务 ID）
+ */
+export async function readJobLog(jobId: string, maxLines?: number): Promise<string> {
+  return await invoke<string>('read_job_log', { jobId, maxLines });
+}
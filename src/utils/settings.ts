@@ -1,75 +1,34 @@
 /**
- * 应用配置管理工具
+ * 应用配置管理工具（配置由后端保存、校验和迁移，各窗口通过 settings-changed 事件同步）
  */
 
-import { readTextFile, writeTextFile, BaseDirectory, mkdir, exists } from '@tauri-apps/plugin-fs';
-import { appDataDir } from '@tauri-apps/api/path';
-import type { AppSettings } from '../types/config';
+import { invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+import type { AppSettings, ImportOptions, ImportReport } from '../types/config';
 import { logInfo, logError } from './logger';
 
-const SETTINGS_FILE = 'app_settings.json';
-
-/**
- * 默认配置
- */
-const DEFAULT_SETTINGS: AppSettings = {
-  defaultDownloadDir: '',
-  autoSelectQuality: true,
-  preferQuality: 'highest',
-  burnSubtitles: true,
-  subtitleLanguage: 'zh',
-  theme: 'light',
-  maxConcurrentDownloads: 1,
-  retryAttempts: 3,
-  timeout: 30000,
-};
-
 /**
  * 读取应用配置
  */
 export async function readSettings(): Promise<AppSettings> {
-  try {
-    const text = await readTextFile(SETTINGS_FILE, { baseDir: BaseDirectory.AppData });
-    const settings = JSON.parse(text);
-    
-    // 合并默认配置，确保所有字段都存在
-    return { ...DEFAULT_SETTINGS, ...settings };
-  } catch (error) {
-    // 文件不存在或读取失败，返回默认配置
-    logInfo('配置文件不存在，使用默认配置');
-    return DEFAULT_SETTINGS;
-  }
+  return await invoke<AppSettings>('get_settings');
 }
 
 /**
- * 确保应用数据目录存在
+ * 保存应用配置
  */
-async function ensureAppDataDir(): Promise<void> {
-  try {
-    const appDir = await appDataDir();
-    const dirExists = await exists(appDir);
-    
-    if (!dirExists) {
-      await mkdir(appDir, { recursive: true });
-      logInfo('应用数据目录已创建');
-    }
-  } catch (error) {
-    logError('创建应用数据目录失败', error);
-    throw error;
-  }
+export async function writeSettings(settings: AppSettings): Promise<void> {
+  await updateSettings(settings);
 }
 
 /**
- * 保存应用配置
+ * 更新配置（部分更新），返回后端校验后的完整配置
  */
-export async function writeSettings(settings: AppSettings): Promise<void> {
+export async function updateSettings(updates: Partial<AppSettings>): Promise<AppSettings> {
   try {
-    // 确保目录存在
-    await ensureAppDataDir();
-    
-    const content = JSON.stringify(settings, null, 2);
-    await writeTextFile(SETTINGS_FILE, content, { baseDir: BaseDirectory.AppData });
+    const settings = await invoke<AppSettings>('update_settings', { patch: updates });
     logInfo('配置已保存');
+    return settings;
   } catch (error) {
     logError('保存配置失败', error);
     throw error;
@@ -77,19 +36,43 @@ export async function writeSettings(settings: AppSettings): Promise<void> {
 }
 
 /**
- * 更新配置（部分更新）
+ * 重置为默认配置
  */
-export async function updateSettings(updates: Partial<AppSettings>): Promise<AppSettings> {
-  const currentSettings = await readSettings();
-  const newSettings = { ...currentSettings, ...updates };
-  await writeSettings(newSettings);
-  return newSettings;
+export async function resetSettings(): Promise<AppSettings> {
+  return await invoke<AppSettings>('reset_settings');
 }
 
 /**
- * 重置为默认配置
+ * 通过对话框选择外部工具（N_m3u8DL-RE / ffmpeg）的可执行文件；取消对话框时返回 null
  */
-export async function resetSettings(): Promise<AppSettings> {
-  await writeSettings(DEFAULT_SETTINGS);
-  return DEFAULT_SETTINGS;
+export async function pickToolPath(tool: 'N_m3u8DL-RE' | 'ffmpeg'): Promise<AppSettings | null> {
+  return await invoke<AppSettings | null>('pick_tool_path', { tool });
+}
+
+/**
+ * 清除工具路径，恢复使用内置工具
+ */
+export async function clearToolPath(tool: 'N_m3u8DL-RE' | 'ffmpeg'): Promise<AppSettings> {
+  return await invoke<AppSettings>('clear_tool_path', { tool });
+}
+
+/**
+ * 监听配置变更（任意窗口保存配置时触发）
+ */
+export async function onSettingsChanged(handler: (settings: AppSettings) => void): Promise<UnlistenFn> {
+  return await listen<AppSettings>('settings-changed', event => handler(event.payload));
+}
+
+/**
+ * 导出配置包（单个 JSON 文件：配置、工具路径、排队任务，可选下载历史），返回保存路径；取消对话框时返回 null
+ */
+export async function exportProfile(includeHistory = false): Promise<string | null> {
+  return await invoke<string | null>('export_profile', { includeHistory });
+}
+
+/**
+ * 通过对话框选择并导入配置包；取消对话框时返回 null
+ */
+export async function importProfile(options: ImportOptions = {}): Promise<ImportReport | null> {
+  return await invoke<ImportReport | null>('import_profile', { options });
 }